@@ -0,0 +1,29 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use rand::SeedableRng;
+use rand_chacha::ChaChaRng;
+use rand_pcg::Pcg64Mcg;
+use rng_nibbler::{Bitstream, RngBitstream};
+
+const TEST_WIDTHS: &[u32] = &[1, 4, 8, 33, 64];
+
+fn gen_bits(c: &mut Criterion) {
+    let mut chacha_bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+    let mut pcg_bitstream = RngBitstream::new(Pcg64Mcg::seed_from_u64(0));
+    let mut group = c.benchmark_group("gen_bits");
+    for &num_bits in TEST_WIDTHS {
+        group.bench_with_input(
+            BenchmarkId::new("RngBitstream<ChaChaRng>", num_bits),
+            &num_bits,
+            |b, &num_bits| b.iter(|| chacha_bitstream.gen_bits(num_bits)),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("RngBitstream<Pcg64Mcg>", num_bits),
+            &num_bits,
+            |b, &num_bits| b.iter(|| pcg_bitstream.gen_bits(num_bits)),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, gen_bits);
+criterion_main!(benches);
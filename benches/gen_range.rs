@@ -1,28 +1,9 @@
-use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
 use rand::{Rng, SeedableRng};
 use rand_chacha::ChaChaRng;
 use rand_pcg::Pcg64Mcg;
-use rng_nibbler::{BitstreamExt, RngBitstream};
-
-#[doc(hidden)]
-pub const TEST_RANGE_SIZES: &'static [u64] = &[
-    1,
-    2,
-    3,
-    4,
-    5,
-    7,
-    8,
-    9,
-    15,
-    16,
-    17,
-    (1 << 31) - 1,
-    1 << 31,
-    (1 << 31) + 1,
-    (1 << 62) + 1,
-    (1 << 63) - 1,
-];
+use rng_nibbler::bench_util::TEST_RANGE_SIZES;
+use rng_nibbler::{BitstreamExt, ConstRange, Distribution, RngBitstream, Strategy};
 
 fn gen_range(c: &mut Criterion) {
     let mut chacha = ChaChaRng::seed_from_u64(0);
@@ -41,6 +22,13 @@ fn gen_range(c: &mut Criterion) {
             &range_size,
             |b, &range_size| b.iter(|| chacha_bitstream.gen_range(range_size)),
         );
+        group.bench_with_input(
+            BenchmarkId::new("RngBitstream<ChaChaRng>/Throughput", range_size),
+            &range_size,
+            |b, &range_size| {
+                b.iter(|| chacha_bitstream.gen_range_with(range_size, Strategy::Throughput))
+            },
+        );
         group.bench_with_input(
             BenchmarkId::new("Pcg64Mcg", range_size),
             &range_size,
@@ -51,9 +39,89 @@ fn gen_range(c: &mut Criterion) {
             &range_size,
             |b, &range_size| b.iter(|| pcg_bitstream.gen_range(range_size)),
         );
+        group.bench_with_input(
+            BenchmarkId::new("RngBitstream<Pcg64Mcg>/Throughput", range_size),
+            &range_size,
+            |b, &range_size| {
+                b.iter(|| pcg_bitstream.gen_range_with(range_size, Strategy::Throughput))
+            },
+        );
+    }
+    group.finish();
+}
+
+fn gen_range_alternating(c: &mut Criterion) {
+    let mut pcg_bitstream = RngBitstream::new(Pcg64Mcg::seed_from_u64(0));
+    let mut group = c.benchmark_group("gen_range_alternating");
+    for &sizes in &[[3, 5, 6], [17, 33, 1000], [(1 << 31) + 1, 9, 100]] {
+        group.bench_with_input(
+            BenchmarkId::new("RngBitstream<Pcg64Mcg>", format!("{:?}", sizes)),
+            &sizes,
+            |b, sizes| {
+                b.iter(|| {
+                    sizes
+                        .iter()
+                        .map(|&size| pcg_bitstream.gen_range(size))
+                        .sum::<u64>()
+                })
+            },
+        );
     }
     group.finish();
 }
 
-criterion_group!(benches, gen_range);
+// Sizes just above a power of two reject the most often, and make the most
+// retries that need several more bits.
+fn above_power_of_two(c: &mut Criterion) {
+    let mut pcg_bitstream = RngBitstream::new(Pcg64Mcg::seed_from_u64(0));
+    let mut group = c.benchmark_group("above_power_of_two");
+    for &log2_size in &[2, 8, 16, 31, 48, 62] {
+        let range_size = (1u64 << log2_size) + 1;
+        group.bench_with_input(
+            BenchmarkId::new("RngBitstream<Pcg64Mcg>", range_size),
+            &range_size,
+            |b, &range_size| b.iter(|| pcg_bitstream.gen_range(range_size)),
+        );
+    }
+    group.finish();
+}
+
+fn gen_range_u32(c: &mut Criterion) {
+    let mut pcg_bitstream = RngBitstream::new(Pcg64Mcg::seed_from_u64(0));
+    let mut group = c.benchmark_group("gen_range_u32");
+    for &range_size in &[3u32, 17, 1000, (1 << 31) - 1] {
+        group.bench_with_input(
+            BenchmarkId::new("gen_range", range_size),
+            &range_size,
+            |b, &range_size| b.iter(|| pcg_bitstream.gen_range(range_size as u64)),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("gen_range_u32", range_size),
+            &range_size,
+            |b, &range_size| b.iter(|| pcg_bitstream.gen_range_u32(range_size)),
+        );
+    }
+    group.finish();
+}
+
+fn const_range(c: &mut Criterion) {
+    let mut pcg_bitstream = RngBitstream::new(Pcg64Mcg::seed_from_u64(0));
+    let mut group = c.benchmark_group("const_range");
+    group.bench_function(BenchmarkId::new("gen_range", 6), |b| {
+        b.iter(|| pcg_bitstream.gen_range(black_box(6)))
+    });
+    group.bench_function(BenchmarkId::new("ConstRange", 6), |b| {
+        b.iter(|| ConstRange::<6>.sample(&mut pcg_bitstream))
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    gen_range,
+    gen_range_alternating,
+    above_power_of_two,
+    gen_range_u32,
+    const_range
+);
 criterion_main!(benches);
@@ -0,0 +1,35 @@
+/* C interface to rng-nibbler, built with the `ffi` feature.
+ * Mirrors src/ffi.rs; see there for the safety requirements of each function. */
+
+#ifndef RNG_NIBBLER_H
+#define RNG_NIBBLER_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct RngNibbler RngNibbler;
+
+/* Creates a bitstream backed by ChaCha20 seeded with `seed`. */
+RngNibbler *rng_nibbler_new(uint64_t seed);
+
+/* Frees a handle from rng_nibbler_new. Accepts NULL. */
+void rng_nibbler_free(RngNibbler *handle);
+
+/* Returns the next `num_bits` (at most 64) bits of the stream. */
+uint64_t rng_nibbler_gen_bits(RngNibbler *handle, uint32_t num_bits);
+
+/* Returns a uniform value in 0..size. `size` must be nonzero. */
+uint64_t rng_nibbler_gen_range(RngNibbler *handle, uint64_t size);
+
+/* Fills `len` bytes at `bytes` with the next bits of the stream. */
+void rng_nibbler_fill_bytes(RngNibbler *handle, uint8_t *bytes, size_t len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* RNG_NIBBLER_H */
@@ -0,0 +1,125 @@
+use crate::{Bitstream, BitstreamExt};
+use std::convert::TryFrom;
+use std::time::Duration;
+
+/// How `Backoff` randomizes its delays, following the schemes in the AWS
+/// Architecture Blog post "Exponential Backoff And Jitter".
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum Jitter {
+    /// Uniform in `[0, min(cap, base * 2^attempt)]`.
+    #[default]
+    Full,
+    /// Uniform in `[base, previous delay * 3]`, capped at `cap`.
+    Decorrelated,
+}
+
+/// Produces randomized exponential backoff delays for retry loops. Delays are
+/// uniform over whole nanoseconds, so a seeded bitstream reproduces the same
+/// retry schedule every time.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Backoff {
+    base: Duration,
+    cap: Duration,
+    jitter: Jitter,
+    attempt: u32,
+    previous: Duration,
+}
+
+impl Backoff {
+    pub fn new(base: Duration, cap: Duration) -> Self {
+        Backoff {
+            base,
+            cap,
+            jitter: Jitter::default(),
+            attempt: 0,
+            previous: base,
+        }
+    }
+
+    pub fn with_jitter(self, jitter: Jitter) -> Self {
+        Backoff { jitter, ..self }
+    }
+
+    /// The number of delays produced since creation or the last `reset`.
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    /// Starts over from the base delay, e.g. after a successful request.
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+        self.previous = self.base;
+    }
+
+    pub fn next_delay<B: Bitstream + ?Sized>(&mut self, bitstream: &mut B) -> Duration {
+        let delay = match self.jitter {
+            Jitter::Full => {
+                let ceiling = self
+                    .base
+                    .checked_mul(1u32.checked_shl(self.attempt).unwrap_or(u32::MAX))
+                    .map_or(self.cap, |ceiling| ceiling.min(self.cap));
+                uniform_between(bitstream, Duration::ZERO, ceiling)
+            }
+            Jitter::Decorrelated => {
+                let high = self.previous.saturating_mul(3).max(self.base);
+                uniform_between(bitstream, self.base, high).min(self.cap)
+            }
+        };
+        self.attempt = self.attempt.saturating_add(1);
+        self.previous = delay;
+        delay
+    }
+}
+
+/// Uniform over whole nanoseconds in `[low, high]`, saturating at `u64::MAX`
+/// nanoseconds (about 584 years).
+fn uniform_between<B: Bitstream + ?Sized>(
+    bitstream: &mut B,
+    low: Duration,
+    high: Duration,
+) -> Duration {
+    let as_nanos = |duration: Duration| u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+    let (low, high) = (as_nanos(low), as_nanos(high));
+    let offset = match (high - low).checked_add(1) {
+        Some(size) => bitstream.gen_range(size),
+        None => bitstream.gen_bits(64),
+    };
+    Duration::from_nanos(low + offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Backoff, Jitter, RngBitstream};
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+    use std::time::Duration;
+
+    #[test]
+    fn delays_stay_in_their_windows() {
+        let base = Duration::from_millis(10);
+        let cap = Duration::from_secs(1);
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let mut full = Backoff::new(base, cap);
+        let mut decorrelated = Backoff::new(base, cap).with_jitter(Jitter::Decorrelated);
+        for _ in 0..100 {
+            for attempt in 0..10 {
+                let ceiling = (base * (1 << attempt)).min(cap);
+                assert!(full.next_delay(&mut bitstream) <= ceiling);
+                let delay = decorrelated.next_delay(&mut bitstream);
+                assert!(delay >= base && delay <= cap);
+            }
+            full.reset();
+            decorrelated.reset();
+        }
+
+        let schedule = |seed| {
+            let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(seed));
+            let mut backoff = Backoff::new(base, cap);
+            (0..10)
+                .map(|_| backoff.next_delay(&mut bitstream))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(schedule(5), schedule(5));
+        assert_ne!(schedule(5), schedule(6));
+    }
+}
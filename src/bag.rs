@@ -0,0 +1,126 @@
+use crate::{Bitstream, BitstreamExt};
+use std::collections::VecDeque;
+
+/// Draws items in random order without replacement, refilling when empty,
+/// so every item comes up exactly once per pass through the bag. Draws are
+/// one step of a lazy Fisher-Yates shuffle each.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ShuffleBag<T> {
+    items: Vec<T>,
+    // items[..drawn] have been drawn this pass.
+    drawn: usize,
+}
+
+impl<T> ShuffleBag<T> {
+    /// # Panics
+    ///
+    /// If `items` is empty.
+    pub fn new(items: Vec<T>) -> Self {
+        assert!(!items.is_empty(), "a shuffle bag needs at least one item");
+        ShuffleBag { items, drawn: 0 }
+    }
+
+    pub fn draw<B: Bitstream + ?Sized>(&mut self, bitstream: &mut B) -> &T {
+        if self.drawn == self.items.len() {
+            self.drawn = 0;
+        }
+        let remaining = self.items.len() - self.drawn;
+        let chosen = self.drawn + bitstream.gen_range(remaining as u64) as usize;
+        self.items.swap(self.drawn, chosen);
+        self.drawn += 1;
+        &self.items[self.drawn - 1]
+    }
+
+    /// How many items are left before the bag refills.
+    pub fn remaining(&self) -> usize {
+        self.items.len() - self.drawn
+    }
+}
+
+/// Values from `0..size`, each uniform among those not among the last
+/// `history_len` values drawn. With a history of 1, the same value never
+/// comes up twice in a row.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct HistoryAvoiding {
+    size: u64,
+    history_len: usize,
+    recent: VecDeque<u64>,
+}
+
+impl HistoryAvoiding {
+    /// # Panics
+    ///
+    /// If `history_len >= size`.
+    pub fn new(size: u64, history_len: usize) -> Self {
+        assert!(
+            (history_len as u64) < size,
+            "history must be shorter than the number of values"
+        );
+        HistoryAvoiding {
+            size,
+            history_len,
+            recent: VecDeque::with_capacity(history_len + 1),
+        }
+    }
+
+    pub fn sample<B: Bitstream + ?Sized>(&mut self, bitstream: &mut B) -> u64 {
+        let mut excluded: Vec<u64> = self.recent.iter().copied().collect();
+        excluded.sort_unstable();
+        // Draw among the allowed values, then step over the excluded ones.
+        let mut value = bitstream.gen_range(self.size - excluded.len() as u64);
+        for &skipped in &excluded {
+            if value >= skipped {
+                value += 1;
+            }
+        }
+        if self.history_len > 0 {
+            if self.recent.len() == self.history_len {
+                self.recent.pop_front();
+            }
+            self.recent.push_back(value);
+        }
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{HistoryAvoiding, RecordingBitstream, ReplayBitstream, RngBitstream, ShuffleBag};
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn bags_and_histories_avoid_repeats_and_replay() {
+        let mut bitstream = RecordingBitstream::new(RngBitstream::new(ChaChaRng::seed_from_u64(0)));
+        let mut bag = ShuffleBag::new(vec!['a', 'b', 'c', 'd', 'e']);
+        let mut drawn = Vec::new();
+        for _ in 0..100 {
+            drawn.push(*bag.draw(&mut bitstream));
+        }
+        for pass in drawn.chunks(5) {
+            let mut pass = pass.to_vec();
+            pass.sort_unstable();
+            assert_eq!(pass, ['a', 'b', 'c', 'd', 'e']);
+        }
+        assert_eq!(bag.remaining(), 0);
+
+        let mut avoiding = HistoryAvoiding::new(4, 2);
+        let values: Vec<u64> = (0..10000)
+            .map(|_| avoiding.sample(&mut bitstream))
+            .collect();
+        assert!(values
+            .windows(3)
+            .all(|w| w[0] != w[1] && w[0] != w[2] && w[1] != w[2]));
+        let zeros = values.iter().filter(|&&value| value == 0).count();
+        assert!(zeros > 2300 && zeros < 2700);
+
+        let (_, tape) = bitstream.into_parts();
+        let mut replay = ReplayBitstream::new(tape);
+        let mut bag = ShuffleBag::new(vec!['a', 'b', 'c', 'd', 'e']);
+        assert!(drawn.iter().all(|&item| item == *bag.draw(&mut replay)));
+        let mut avoiding = HistoryAvoiding::new(4, 2);
+        assert!(values
+            .iter()
+            .all(|&value| value == avoiding.sample(&mut replay)));
+    }
+}
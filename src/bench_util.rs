@@ -0,0 +1,86 @@
+//! Pieces of this crate's benchmarks, for downstream crates to compare their
+//! own samplers against it in their criterion suites.
+
+use crate::source::BitMeter;
+use crate::Bitstream;
+use std::hint::black_box;
+use std::time::Instant;
+
+/// The range sizes the `gen_range` benchmarks use: small sizes on either
+/// side of powers of two, and large ones where rejection is most and least
+/// likely.
+pub const TEST_RANGE_SIZES: &[u64] = &[
+    1,
+    2,
+    3,
+    4,
+    5,
+    7,
+    8,
+    9,
+    15,
+    16,
+    17,
+    (1 << 31) - 1,
+    1 << 31,
+    (1 << 31) + 1,
+    (1 << 62) + 1,
+    (1 << 63) - 1,
+];
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Timing {
+    pub samples: u64,
+    pub nanos_per_sample: f64,
+    pub bits_per_sample: f64,
+}
+
+/// Runs `sampler` `num_samples` times on `bitstream`, timing the whole run
+/// and counting the bits drawn. The counting adds a little time to each
+/// draw, so compare the timings with other `time_sampler` runs rather than
+/// with criterion's.
+///
+/// # Panics
+///
+/// If `num_samples` is 0.
+pub fn time_sampler<B, T, S>(bitstream: &mut B, num_samples: u64, mut sampler: S) -> Timing
+where
+    B: Bitstream + ?Sized,
+    S: FnMut(&mut dyn Bitstream) -> T,
+{
+    assert!(num_samples > 0, "can't time zero samples");
+    let mut meter = BitMeter::new(bitstream);
+    let start = Instant::now();
+    for _ in 0..num_samples {
+        black_box(sampler(&mut meter));
+    }
+    let nanos = start.elapsed().as_nanos() as f64;
+    Timing {
+        samples: num_samples,
+        nanos_per_sample: nanos / num_samples as f64,
+        bits_per_sample: meter.bits() as f64 / num_samples as f64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BitstreamExt, RngBitstream};
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn time_sampler_counts_bits() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let timing = time_sampler(&mut bitstream, 1000, |b| b.gen_range(16));
+        assert_eq!(timing.samples, 1000);
+        assert_eq!(timing.bits_per_sample, 4.0);
+        assert!(timing.nanos_per_sample > 0.0);
+
+        // Power-of-two-adjacent sizes are in the table to cover rejection.
+        for &size in TEST_RANGE_SIZES {
+            let timing = time_sampler(&mut bitstream, 1000, |b| b.gen_range(size));
+            assert!(timing.bits_per_sample >= (size as f64).log2() - 1e-9);
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use crate::{Bitstream, Distribution};
+
+/// A coin that comes up `true` with probability `p`, sampled exactly by
+/// comparing random bits against the binary expansion of `p` one at a time.
+/// That takes 2 bits on average, whatever `p` is.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Bernoulli {
+    // p = numerator / 2^num_bits, with numerator odd unless p is 0 or 1.
+    // num_bits can exceed 64 for tiny f64 probabilities.
+    numerator: u64,
+    num_bits: u32,
+}
+
+impl Bernoulli {
+    /// # Panics
+    ///
+    /// If `p` isn't in `[0, 1]`.
+    pub fn new(p: f64) -> Self {
+        assert!((0.0..=1.0).contains(&p), "probability must be in [0, 1]");
+        if p == 1.0 {
+            return Bernoulli {
+                numerator: 1,
+                num_bits: 0,
+            };
+        }
+        let bits = p.to_bits();
+        let exponent = (bits >> 52) as u32;
+        let fraction = bits & ((1 << 52) - 1);
+        // p = mantissa * 2^-num_bits, covering subnormals too.
+        let (mantissa, num_bits) = if exponent == 0 {
+            (fraction, 1074)
+        } else {
+            (fraction | (1 << 52), 1075 - exponent)
+        };
+        Bernoulli::normalized(mantissa, num_bits)
+    }
+
+    /// `true` with probability `numerator / 2^num_bits`.
+    ///
+    /// # Panics
+    ///
+    /// If `num_bits > 64`, or the probability would exceed 1.
+    pub fn dyadic(numerator: u64, num_bits: u32) -> Self {
+        assert!(num_bits <= 64, "num_bits must be at most 64");
+        if num_bits < 64 && numerator >= 1 << num_bits {
+            assert!(numerator == 1 << num_bits, "probability must be at most 1");
+            return Bernoulli {
+                numerator: 1,
+                num_bits: 0,
+            };
+        }
+        Bernoulli::normalized(numerator, num_bits)
+    }
+
+    fn normalized(numerator: u64, num_bits: u32) -> Self {
+        if numerator == 0 {
+            return Bernoulli {
+                numerator: 0,
+                num_bits: 0,
+            };
+        }
+        let trailing_zeros = numerator.trailing_zeros();
+        Bernoulli {
+            numerator: numerator >> trailing_zeros,
+            num_bits: num_bits - trailing_zeros,
+        }
+    }
+}
+
+impl Distribution<bool> for Bernoulli {
+    fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> bool {
+        // Generate a uniform u in [0, 1) bit by bit, stopping at the first
+        // bit where it differs from p. If all of p's bits match, u >= p.
+        for position in (0..self.num_bits).rev() {
+            let p_bit = self.numerator.checked_shr(position).unwrap_or(0) & 1;
+            let u_bit = bitstream.gen_bit() as u64;
+            if u_bit != p_bit {
+                return u_bit < p_bit;
+            }
+        }
+        self.num_bits == 0 && self.numerator == 1
+    }
+}
+
+/// The result is exactly `x` in expectation. NaN and out-of-range values
+/// behave like `as i64`.
+pub(crate) fn stochastic_round<B: Bitstream + ?Sized>(bitstream: &mut B, x: f64) -> i64 {
+    // For nonnegative x, x - floor(x) is exact: it just drops x's integer
+    // bits. That isn't true for negative x (try -1e-20), so round |x|.
+    let magnitude = x.abs();
+    let floor = magnitude.floor();
+    let fraction = magnitude - floor;
+    let rounded = if fraction > 0.0 && Bernoulli::new(fraction).sample(bitstream) {
+        floor + 1.0
+    } else {
+        floor
+    };
+    if x < 0.0 {
+        -rounded as i64
+    } else {
+        rounded as i64
+    }
+}
+
+/// # Panics
+///
+/// If `fraction_bits >= 64`.
+pub(crate) fn stochastic_round_fixed<B: Bitstream + ?Sized>(
+    bitstream: &mut B,
+    value: i64,
+    fraction_bits: u32,
+) -> i64 {
+    assert!(fraction_bits < 64, "fraction_bits must be less than 64");
+    let fraction = value as u64 & ((1 << fraction_bits) - 1);
+    let floor = value >> fraction_bits;
+    floor + Bernoulli::dyadic(fraction, fraction_bits).sample(bitstream) as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Bernoulli, BitstreamExt, CountingRngBitstream, Distribution};
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn bernoulli_is_exact_and_cheap() {
+        let mut bitstream = CountingRngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let num_samples = 100000;
+        for &p in &[0.0, 1e-300, 0.3, 0.5, 0.75, 1.0] {
+            let coin = Bernoulli::new(p);
+            let heads = (0..num_samples)
+                .filter(|_| coin.sample(&mut bitstream))
+                .count();
+            let frequency = heads as f64 / num_samples as f64;
+            assert!((frequency - p).abs() < 0.01, "{} came up {}", p, frequency);
+        }
+        assert!(bitstream.count() < 2 * 6 * num_samples);
+        assert!(!Bernoulli::dyadic(0, 64).sample(&mut bitstream));
+        assert!(Bernoulli::dyadic(8, 3).sample(&mut bitstream));
+    }
+
+    #[test]
+    fn stochastic_rounding_is_unbiased() {
+        let mut bitstream = CountingRngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let num_samples = 100000;
+        for &x in &[2.7, -1.25, 3.0, -1e-20] {
+            let sum: i64 = (0..num_samples)
+                .map(|_| bitstream.stochastic_round(x))
+                .sum();
+            let mean = sum as f64 / num_samples as f64;
+            assert!(
+                (mean - x).abs() < 0.01,
+                "{} rounded to {} on average",
+                x,
+                mean
+            );
+        }
+        let sum: i64 = (0..num_samples)
+            .map(|_| bitstream.stochastic_round_fixed(-0b1011, 2))
+            .sum();
+        assert!((sum as f64 / num_samples as f64 + 2.75).abs() < 0.01);
+    }
+}
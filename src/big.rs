@@ -0,0 +1,322 @@
+use crate::Bitstream;
+use num_bigint::BigUint;
+
+/// `num_bits` bits from the stream, the first drawn as the lowest.
+pub(crate) fn gen_biguint<B: Bitstream + ?Sized>(bitstream: &mut B, num_bits: u64) -> BigUint {
+    let mut digits = Vec::with_capacity((num_bits as usize).div_ceil(32));
+    let mut remaining = num_bits;
+    while remaining > 0 {
+        let chunk = remaining.min(32) as u32;
+        digits.push(bitstream.gen_bits(chunk) as u32);
+        remaining -= chunk as u64;
+    }
+    BigUint::new(digits)
+}
+
+/// The smallest number of bits that, appended to `leftover_size`
+/// possibilities, gives at least `size` possibilities.
+fn retry_bits_needed(leftover_size: &BigUint, size: &BigUint) -> u64 {
+    let bits_needed = (size.bits() - leftover_size.bits()).max(1);
+    if (leftover_size << bits_needed) < *size {
+        bits_needed + 1
+    } else {
+        bits_needed
+    }
+}
+
+/// `RangePlan::sample` for arbitrary-precision sizes: the same rejection
+/// sampling that keeps the rejected part of each draw, so it gives the same
+/// values for sizes that fit in a `u64`.
+///
+/// # Panics
+///
+/// If `size` is 0.
+pub(crate) fn gen_biguint_range<B: Bitstream + ?Sized>(
+    bitstream: &mut B,
+    size: &BigUint,
+) -> BigUint {
+    assert!(
+        *size > BigUint::from(0u32),
+        "can't sample from an empty range"
+    );
+    let bits_needed = (size - 1u32).bits();
+    let mut leftover = gen_biguint(bitstream, bits_needed);
+    if leftover < *size {
+        return leftover;
+    }
+    leftover -= size;
+    let mut leftover_size = (BigUint::from(1u32) << bits_needed) - size;
+    loop {
+        let bits_needed = retry_bits_needed(&leftover_size, size);
+        leftover += gen_biguint(bitstream, bits_needed) * &leftover_size;
+        if leftover < *size {
+            return leftover;
+        }
+        leftover -= size;
+        leftover_size = (leftover_size << bits_needed) - size;
+    }
+}
+
+/// The order of a common elliptic curve's group, for sampling secret scalars
+/// without the usual bias bugs (reducing 256 random bits mod `n`, say).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CurveOrder {
+    // Least significant first.
+    limbs: [u64; 4],
+}
+
+impl CurveOrder {
+    pub const SECP256K1: CurveOrder = CurveOrder {
+        limbs: [
+            0xBFD2_5E8C_D036_4141,
+            0xBAAE_DCE6_AF48_A03B,
+            0xFFFF_FFFF_FFFF_FFFE,
+            0xFFFF_FFFF_FFFF_FFFF,
+        ],
+    };
+    /// NIST P-256, also called secp256r1.
+    pub const P256: CurveOrder = CurveOrder {
+        limbs: [
+            0xF3B9_CAC2_FC63_2551,
+            0xBCE6_FAAD_A717_9E84,
+            0xFFFF_FFFF_FFFF_FFFF,
+            0xFFFF_FFFF_0000_0000,
+        ],
+    };
+    /// The prime order of Curve25519's main subgroup, as used by Ed25519.
+    pub const ED25519: CurveOrder = CurveOrder {
+        limbs: [
+            0x5812_631A_5CF5_D3ED,
+            0x14DE_F9DE_A2F7_9CD6,
+            0,
+            0x1000_0000_0000_0000,
+        ],
+    };
+
+    pub fn to_biguint(&self) -> BigUint {
+        BigUint::from_slice(
+            &self
+                .limbs
+                .iter()
+                .flat_map(|&limb| [limb as u32, (limb >> 32) as u32])
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    fn bits(&self) -> u32 {
+        let top = self.limbs.iter().rposition(|&limb| limb != 0).unwrap();
+        64 * top as u32 + 64 - self.limbs[top].leading_zeros()
+    }
+}
+
+fn limbs_to_be_bytes(limbs: [u64; 4]) -> [u8; 32] {
+    let mut bytes = [0; 32];
+    for (chunk, limb) in bytes.chunks_exact_mut(8).zip(limbs.iter().rev()) {
+        chunk.copy_from_slice(&limb.to_be_bytes());
+    }
+    bytes
+}
+
+/// A uniform scalar in `1..order`, big-endian.
+pub(crate) fn gen_scalar<B: Bitstream + ?Sized>(bitstream: &mut B, order: &CurveOrder) -> [u8; 32] {
+    let scalar = gen_biguint_range(bitstream, &(order.to_biguint() - 1u32)) + 1u32;
+    let mut limbs = [0; 4];
+    for (limb, digit) in limbs.iter_mut().zip(scalar.iter_u64_digits()) {
+        *limb = digit;
+    }
+    limbs_to_be_bytes(limbs)
+}
+
+/// Like `gen_scalar`, but every attempt draws the same number of bits and
+/// is compared to the order without branching on its value, so timing
+/// reveals only how many attempts were rejected, which says nothing about
+/// the accepted one.
+pub(crate) fn gen_scalar_constant_time<B: Bitstream + ?Sized>(
+    bitstream: &mut B,
+    order: &CurveOrder,
+) -> [u8; 32] {
+    let bits = order.bits();
+    loop {
+        let mut limbs = [0; 4];
+        for (index, limb) in limbs.iter_mut().enumerate() {
+            let limb_bits = bits.saturating_sub(64 * index as u32).min(64);
+            *limb = bitstream.gen_bits(limb_bits);
+        }
+        // The borrow out of `limbs - order` is 1 exactly when limbs < order.
+        let mut borrow = 0;
+        let mut any_set = 0;
+        for (&limb, &order_limb) in limbs.iter().zip(&order.limbs) {
+            let (difference, borrow_a) = limb.overflowing_sub(order_limb);
+            let (_, borrow_b) = difference.overflowing_sub(borrow);
+            borrow = (borrow_a | borrow_b) as u64;
+            any_set |= limb;
+        }
+        let nonzero = ((any_set | any_set.wrapping_neg()) >> 63) & 1;
+        if borrow & nonzero == 1 {
+            return limbs_to_be_bytes(limbs);
+        }
+    }
+}
+
+const SMALL_PRIMES: [u32; 24] = [
+    3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+];
+
+/// Miller–Rabin rounds per candidate, for a false-positive chance below
+/// 2^-80 even against adversarial inputs (random ones need far fewer).
+const MILLER_RABIN_ROUNDS: u32 = 40;
+
+/// Miller–Rabin on an odd `n`, with bases drawn from the stream.
+fn is_probable_prime<B: Bitstream + ?Sized>(bitstream: &mut B, n: &BigUint) -> bool {
+    for &prime in &SMALL_PRIMES {
+        if *n == BigUint::from(prime) {
+            return true;
+        }
+        if (n % prime) == BigUint::from(0u32) {
+            return false;
+        }
+    }
+    if *n < BigUint::from(97u32 * 97) {
+        return true;
+    }
+    let one = BigUint::from(1u32);
+    let n_minus_one = n - 1u32;
+    let twos = n_minus_one.trailing_zeros().unwrap();
+    let odd_part = &n_minus_one >> twos;
+    'rounds: for _ in 0..MILLER_RABIN_ROUNDS {
+        let base = gen_biguint_range(bitstream, &(n - 3u32)) + 2u32;
+        let mut x = base.modpow(&odd_part, n);
+        if x == one || x == n_minus_one {
+            continue;
+        }
+        for _ in 1..twos {
+            x = x.modpow(&BigUint::from(2u32), n);
+            if x == n_minus_one {
+                continue 'rounds;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+/// A random prime of exactly `num_bits` bits: odd candidates with the top bit
+/// set, until one passes trial division and Miller–Rabin. The candidates and
+/// the Miller–Rabin bases all come from the stream.
+///
+/// # Panics
+///
+/// If `num_bits` is less than 2.
+pub(crate) fn gen_prime<B: Bitstream + ?Sized>(bitstream: &mut B, num_bits: u64) -> BigUint {
+    assert!(num_bits >= 2, "there are no odd primes below 2 bits");
+    let top_and_bottom = (BigUint::from(1u32) << (num_bits - 1)) | BigUint::from(1u32);
+    loop {
+        let candidate = (gen_biguint(bitstream, num_bits - 2) << 1u32) | &top_and_bottom;
+        if is_probable_prime(bitstream, &candidate) {
+            return candidate;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::BitMeter;
+    use crate::{BitstreamExt, RngBitstream};
+    use num_bigint::BigUint;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn biguint_ranges_match_u64_ranges_and_stay_in_range() {
+        let mut big = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let mut small = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        for &size in &[1, 10, (1 << 40) + 3, u64::MAX / 3 * 2, u64::MAX] {
+            for _ in 0..1000 {
+                let expected = BigUint::from(small.gen_range(size));
+                assert_eq!(big.gen_biguint_range(&BigUint::from(size)), expected);
+            }
+        }
+
+        // An RSA-sized modulus, and a size just above a power of 2, where
+        // rejections are most likely.
+        let modulus = (BigUint::from(1u32) << 2048u32) - 159u32;
+        let above_power = (BigUint::from(1u32) << 1000u32) + 1u32;
+        let mut low_half = 0;
+        for _ in 0..1000 {
+            assert!(big.gen_biguint_range(&modulus) < modulus);
+            let value = big.gen_biguint_range(&above_power);
+            assert!(value < above_power);
+            low_half += (value.bits() < 1000) as u32;
+        }
+        assert!(low_half > 450 && low_half < 550);
+        assert!(big.gen_biguint(100).bits() <= 100);
+    }
+
+    #[test]
+    fn scalars_are_nonzero_and_below_the_curve_order() {
+        let ed25519: BigUint =
+            "7237005577332262213973186563042994240857116359379907606001950938285454250989"
+                .parse()
+                .unwrap();
+        assert_eq!(CurveOrder::ED25519.to_biguint(), ed25519);
+        let secp256k1 = BigUint::parse_bytes(
+            b"FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141",
+            16,
+        )
+        .unwrap();
+        assert_eq!(CurveOrder::SECP256K1.to_biguint(), secp256k1);
+        assert_eq!(CurveOrder::ED25519.bits(), 253);
+
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        for order in &[CurveOrder::SECP256K1, CurveOrder::P256, CurveOrder::ED25519] {
+            let n = order.to_biguint();
+            let mut high_half = 0;
+            for _ in 0..1000 {
+                for scalar in &[
+                    bitstream.gen_scalar(order),
+                    bitstream.gen_scalar_constant_time(order),
+                ] {
+                    let scalar = BigUint::from_bytes_be(scalar);
+                    assert!(scalar > BigUint::from(0u32) && scalar < n);
+                    high_half += (scalar.clone() * 2u32 >= n) as u32;
+                }
+            }
+            assert!(high_half > 900 && high_half < 1100);
+            assert!(bitstream.gen_below_modulus(&n) < n);
+        }
+
+        // The constant-time version draws whole attempts of 253 bits.
+        let mut meter = BitMeter::new(&mut bitstream);
+        for _ in 0..100 {
+            meter.gen_scalar_constant_time(&CurveOrder::ED25519);
+        }
+        assert!(meter.bits().is_multiple_of(253));
+    }
+
+    #[test]
+    fn primes_are_prime_and_replayable() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        for _ in 0..100 {
+            let prime = u64::try_from(bitstream.gen_prime(32)).unwrap();
+            assert_eq!(prime >> 31, 1);
+            assert!((2..=65536)
+                .take_while(|d| d * d <= prime)
+                .all(|d| prime % d != 0));
+        }
+        assert_eq!(bitstream.gen_prime(2), BigUint::from(3u32));
+
+        let prime = bitstream.gen_prime(512);
+        assert_eq!(prime.bits(), 512);
+        // Fermat's little theorem, as an independent check.
+        let two = BigUint::from(2u32);
+        assert_eq!(two.modpow(&(&prime - 1u32), &prime), BigUint::from(1u32));
+        let mut replay = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        for _ in 0..100 {
+            replay.gen_prime(32);
+        }
+        replay.gen_prime(2);
+        assert_eq!(replay.gen_prime(512), prime);
+    }
+}
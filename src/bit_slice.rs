@@ -0,0 +1,85 @@
+use crate::error::check_num_bits;
+use crate::{Bitstream, Error, TryBitstream};
+use bitvec::prelude::*;
+
+/// A bitstream that replays the bits of a `BitSlice`, in slice order.
+/// Asking for more bits than remain is a `SourceExhausted` error, or a panic
+/// through the `Bitstream` impl.
+pub struct BitSliceBitstream<'a, T: BitStore, O: BitOrder> {
+    bits: &'a BitSlice<T, O>,
+}
+
+impl<'a, T: BitStore, O: BitOrder> BitSliceBitstream<'a, T, O> {
+    pub fn new(bits: &'a BitSlice<T, O>) -> Self {
+        BitSliceBitstream { bits }
+    }
+
+    pub fn remaining(&self) -> &'a BitSlice<T, O> {
+        self.bits
+    }
+}
+
+impl<'a, T: BitStore, O: BitOrder> TryBitstream for BitSliceBitstream<'a, T, O> {
+    fn try_gen_bits(&mut self, num_bits: u32) -> Result<u64, Error> {
+        check_num_bits(num_bits)?;
+        if num_bits as usize > self.bits.len() {
+            return Err(Error::SourceExhausted {
+                requested: num_bits as u64,
+                available: self.bits.len() as u64,
+            });
+        }
+        let (taken, rest) = self.bits.split_at(num_bits as usize);
+        self.bits = rest;
+        Ok(taken
+            .iter()
+            .by_vals()
+            .enumerate()
+            .fold(0, |result, (index, bit)| result | ((bit as u64) << index)))
+    }
+}
+
+impl<'a, T: BitStore, O: BitOrder> Bitstream for BitSliceBitstream<'a, T, O> {
+    fn gen_bits(&mut self, num_bits: u32) -> u64 {
+        self.try_gen_bits(num_bits)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+}
+
+/// Collects the next `num_bits` bits of the stream into a `BitVec`, in stream order.
+pub fn gen_bitvec<B: Bitstream + ?Sized>(bitstream: &mut B, num_bits: usize) -> BitVec {
+    let mut result = BitVec::with_capacity(num_bits);
+    let mut remaining = num_bits;
+    while remaining > 0 {
+        let chunk = remaining.min(64);
+        let bits = bitstream.gen_bits(chunk as u32);
+        result.extend((0..chunk).map(|index| (bits >> index) & 1 == 1));
+        remaining -= chunk;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{gen_bitvec, BitSliceBitstream, Bitstream, Error, RngBitstream, TryBitstream};
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn bitvec_round_trips_through_bit_slice_bitstream() {
+        let mut rng_bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let bits = gen_bitvec(&mut rng_bitstream, 2080 + 253);
+        let mut direct = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let mut replay = BitSliceBitstream::new(&bits);
+        for num_bits in (0..=64).chain(0..=22) {
+            assert_eq!(replay.gen_bits(num_bits), direct.gen_bits(num_bits));
+        }
+        assert!(replay.remaining().is_empty());
+        assert!(matches!(
+            replay.try_gen_bits(1),
+            Err(Error::SourceExhausted {
+                requested: 1,
+                available: 0
+            })
+        ));
+    }
+}
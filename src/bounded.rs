@@ -0,0 +1,137 @@
+use crate::{Bitstream, BitstreamExt, Distribution};
+use std::cmp::Ordering;
+
+/// A type with finitely many values, numbered `0..COUNT`, so a uniform one
+/// takes one `gen_range(COUNT)`. Implement it for fieldless enums with
+/// [`bounded_sample!`](crate::bounded_sample).
+pub trait BoundedSample: Sized {
+    const COUNT: u64;
+
+    /// # Panics
+    ///
+    /// If `index >= COUNT`.
+    fn from_index(index: u64) -> Self;
+}
+
+/// The uniform distribution over a `BoundedSample` type.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct Bounded;
+
+impl<T: BoundedSample> Distribution<T> for Bounded {
+    fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> T {
+        T::from_index(bitstream.gen_range(T::COUNT))
+    }
+}
+
+impl BoundedSample for () {
+    const COUNT: u64 = 1;
+
+    fn from_index(index: u64) -> Self {
+        assert!(index < Self::COUNT, "index out of bounds");
+    }
+}
+
+impl BoundedSample for bool {
+    const COUNT: u64 = 2;
+
+    fn from_index(index: u64) -> Self {
+        assert!(index < Self::COUNT, "index out of bounds");
+        index == 1
+    }
+}
+
+impl BoundedSample for Ordering {
+    const COUNT: u64 = 3;
+
+    fn from_index(index: u64) -> Self {
+        match index {
+            0 => Ordering::Less,
+            1 => Ordering::Equal,
+            2 => Ordering::Greater,
+            _ => panic!("index out of bounds"),
+        }
+    }
+}
+
+/// Implements [`BoundedSample`] for a fieldless enum, numbering its
+/// variants in the order given.
+///
+/// ```
+/// use rng_nibbler::{bounded_sample, BitstreamExt, RngBitstream};
+///
+/// enum Direction {
+///     North,
+///     East,
+///     South,
+///     West,
+/// }
+/// bounded_sample!(Direction { North, East, South, West });
+///
+/// let mut bitstream = RngBitstream::seeded(0);
+/// let direction: Direction = bitstream.gen_bounded();
+/// ```
+#[macro_export]
+macro_rules! bounded_sample {
+    ($name:ident { $($variant:ident),+ $(,)? }) => {
+        impl $crate::BoundedSample for $name {
+            const COUNT: u64 = [$(stringify!($variant)),+].len() as u64;
+
+            fn from_index(index: u64) -> Self {
+                ::std::iter::empty()
+                    $(.chain(::std::iter::once($name::$variant)))+
+                    .nth(index as usize)
+                    .expect("index out of bounds")
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CountingRngBitstream;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[derive(Copy, Clone, PartialEq, Eq, Debug)]
+    enum Suit {
+        Clubs,
+        Diamonds,
+        Hearts,
+        Spades,
+        Stars,
+    }
+    crate::bounded_sample!(Suit {
+        Clubs,
+        Diamonds,
+        Hearts,
+        Spades,
+        Stars,
+    });
+
+    #[test]
+    fn bounded_types_are_uniform_and_cheap() {
+        let mut bitstream = CountingRngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let num_samples = 100000;
+        let heads = (0..num_samples)
+            .filter(|_| bitstream.gen_bounded::<bool>())
+            .count();
+        assert_eq!(bitstream.count(), num_samples as u64);
+        assert!((heads as f64 / num_samples as f64 - 0.5).abs() < 0.01);
+
+        let () = bitstream.gen_bounded();
+        assert_eq!(bitstream.count(), num_samples as u64);
+
+        assert_eq!(Suit::COUNT, 5);
+        let mut counts = [0; 5];
+        for _ in 0..num_samples {
+            let suit: Suit = bitstream.sample(Bounded);
+            counts[suit as usize] += 1;
+        }
+        for &count in &counts {
+            assert!((count as f64 / num_samples as f64 - 0.2).abs() < 0.01);
+        }
+        assert_eq!(Suit::from_index(4), Suit::Stars);
+        assert_eq!(Ordering::from_index(2), Ordering::Greater);
+    }
+}
@@ -0,0 +1,180 @@
+//! Playing cards, with shuffles and cuts that report how many random bits
+//! they used.
+
+use crate::seq::shuffle;
+use crate::source::BitMeter;
+use crate::{Bitstream, BitstreamExt};
+use std::fmt;
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Suit {
+    Clubs,
+    Diamonds,
+    Hearts,
+    Spades,
+}
+
+/// A card of a standard deck. Ranks run from 1 (ace) to 13 (king).
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Card {
+    pub rank: u8,
+    pub suit: Suit,
+}
+
+/// The usual short form, like `As` or `Td`.
+impl fmt::Display for Card {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rank = b"A23456789TJQK"[self.rank as usize - 1] as char;
+        let suit = match self.suit {
+            Suit::Clubs => 'c',
+            Suit::Diamonds => 'd',
+            Suit::Hearts => 'h',
+            Suit::Spades => 's',
+        };
+        write!(f, "{}{}", rank, suit)
+    }
+}
+
+/// A deck of cards, top card first.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Deck {
+    cards: Vec<Card>,
+}
+
+impl Deck {
+    /// The 52 cards in order by suit, then rank.
+    pub fn standard() -> Self {
+        let suits = [Suit::Clubs, Suit::Diamonds, Suit::Hearts, Suit::Spades];
+        let cards = suits
+            .iter()
+            .flat_map(|&suit| (1..=13).map(move |rank| Card { rank, suit }))
+            .collect();
+        Deck { cards }
+    }
+
+    pub fn cards(&self) -> &[Card] {
+        &self.cards
+    }
+
+    pub fn len(&self) -> usize {
+        self.cards.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cards.is_empty()
+    }
+
+    /// Shuffles uniformly by Fisher-Yates, returning the number of bits used.
+    pub fn shuffle<B: Bitstream + ?Sized>(&mut self, bitstream: &mut B) -> u64 {
+        let mut meter = BitMeter::new(bitstream);
+        shuffle(&mut meter, &mut self.cards);
+        meter.bits()
+    }
+
+    /// Shuffles uniformly by drawing the permutation's index in the
+    /// factorial number system, returning the number of bits used. The
+    /// digits are drawn a dozen or so at a time, as one `gen_range` over the
+    /// product of their radices, which wastes much less than drawing each
+    /// separately: about 231 bits for 52 cards, against 278 for `shuffle`
+    /// and a minimum of `log2(52!)`, about 225.6.
+    pub fn shuffle_factorial<B: Bitstream + ?Sized>(&mut self, bitstream: &mut B) -> u64 {
+        let mut meter = BitMeter::new(bitstream);
+        let len = self.cards.len();
+        let mut position = 0;
+        while position + 1 < len {
+            // Group radices len - position, len - position - 1, ... while
+            // their product fits.
+            let mut product: u64 = 1;
+            let mut end = position;
+            while end + 1 < len {
+                match product.checked_mul((len - end) as u64) {
+                    Some(next) => product = next,
+                    None => break,
+                }
+                end += 1;
+            }
+            let mut index = meter.gen_range(product);
+            for current in position..end {
+                let radix = (len - current) as u64;
+                let digit = (index % radix) as usize;
+                index /= radix;
+                self.cards.swap(current, current + digit);
+            }
+            position = end;
+        }
+        meter.bits()
+    }
+
+    /// Moves a uniformly random number of cards, from 1 to all but one, from
+    /// the top to the bottom, returning the number of bits used.
+    ///
+    /// # Panics
+    ///
+    /// If there are fewer than 2 cards.
+    pub fn cut<B: Bitstream + ?Sized>(&mut self, bitstream: &mut B) -> u64 {
+        assert!(self.cards.len() >= 2, "can't cut fewer than 2 cards");
+        let mut meter = BitMeter::new(bitstream);
+        let depth = 1 + meter.gen_range(self.cards.len() as u64 - 1) as usize;
+        self.cards.rotate_left(depth);
+        meter.bits()
+    }
+
+    /// Takes `num_cards` cards off the top.
+    ///
+    /// # Panics
+    ///
+    /// If there aren't enough cards.
+    pub fn deal(&mut self, num_cards: usize) -> Vec<Card> {
+        assert!(num_cards <= self.cards.len(), "not enough cards to deal");
+        self.cards.drain(..num_cards).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RngBitstream;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn shuffles_are_permutations_and_cheap() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let mut deck = Deck::standard();
+        assert_eq!(deck.cards()[0].to_string(), "Ac");
+        assert_eq!(deck.cards()[51].to_string(), "Ks");
+        let log2_factorial: f64 = (1..=52).map(|n| (n as f64).log2()).sum();
+
+        let mut fisher_yates = 0;
+        let mut factorial = 0;
+        for _ in 0..1000 {
+            fisher_yates += deck.shuffle(&mut bitstream);
+            factorial += deck.shuffle_factorial(&mut bitstream);
+        }
+        let factorial = factorial as f64 / 1000.0;
+        assert!(factorial < log2_factorial + 6.0, "{}", factorial);
+        assert!(fisher_yates as f64 / 1000.0 > factorial + 40.0);
+        let mut sorted = deck.clone();
+        sorted
+            .cards
+            .sort_unstable_by_key(|card| (card.suit, card.rank));
+        assert_eq!(sorted, Deck::standard());
+
+        // The factorial shuffle puts each card first equally often.
+        let mut counts = [0; 3];
+        for _ in 0..30000 {
+            let mut small = Deck {
+                cards: Deck::standard().cards[..3].to_vec(),
+            };
+            small.shuffle_factorial(&mut bitstream);
+            counts[small.cards[0].rank as usize - 1] += 1;
+        }
+        assert!(counts.iter().all(|&count| count > 9500 && count < 10500));
+
+        let top = deck.cards()[0];
+        let bits = deck.cut(&mut bitstream);
+        assert!(bits > 0 && deck.cards()[0] != top);
+        let hand = deck.deal(5);
+        assert_eq!((hand.len(), deck.len()), (5, 47));
+    }
+}
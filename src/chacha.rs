@@ -0,0 +1,130 @@
+use crate::RngBitstream;
+use rand::{RngCore, SeedableRng};
+
+/// ChaCha20 (Bernstein's original variant: 64-bit block counter, 64-bit
+/// nonce fixed at 0) as a generator, implemented here so that output is pinned
+/// by this crate rather than by `rand_chacha`'s version. Words are the
+/// keystream read as little-endian `u32`s, and a `u64` is two consecutive
+/// words, low first. This matches `rand_chacha::ChaCha20Rng::from_seed`.
+#[derive(Clone, Debug)]
+pub struct ChaCha20 {
+    key: [u32; 8],
+    counter: u64,
+    block: [u32; 16],
+    next_word: usize,
+}
+
+const CONSTANTS: [u32; 4] = [0x6170_7865, 0x3320_646e, 0x7962_2d32, 0x6b20_6574];
+
+fn quarter_round(state: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    state[a] = state[a].wrapping_add(state[b]);
+    state[d] = (state[d] ^ state[a]).rotate_left(16);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_left(12);
+    state[a] = state[a].wrapping_add(state[b]);
+    state[d] = (state[d] ^ state[a]).rotate_left(8);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_left(7);
+}
+
+impl ChaCha20 {
+    fn refill(&mut self) {
+        let mut initial = [0; 16];
+        initial[..4].copy_from_slice(&CONSTANTS);
+        initial[4..12].copy_from_slice(&self.key);
+        initial[12] = self.counter as u32;
+        initial[13] = (self.counter >> 32) as u32;
+        let mut state = initial;
+        for _ in 0..10 {
+            quarter_round(&mut state, 0, 4, 8, 12);
+            quarter_round(&mut state, 1, 5, 9, 13);
+            quarter_round(&mut state, 2, 6, 10, 14);
+            quarter_round(&mut state, 3, 7, 11, 15);
+            quarter_round(&mut state, 0, 5, 10, 15);
+            quarter_round(&mut state, 1, 6, 11, 12);
+            quarter_round(&mut state, 2, 7, 8, 13);
+            quarter_round(&mut state, 3, 4, 9, 14);
+        }
+        for (word, initial) in state.iter_mut().zip(&initial) {
+            *word = word.wrapping_add(*initial);
+        }
+        self.block = state;
+        self.counter = self.counter.wrapping_add(1);
+        self.next_word = 0;
+    }
+}
+
+impl SeedableRng for ChaCha20 {
+    type Seed = [u8; 32];
+
+    fn from_seed(seed: [u8; 32]) -> Self {
+        let mut key = [0; 8];
+        for (word, bytes) in key.iter_mut().zip(seed.chunks_exact(4)) {
+            *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        ChaCha20 {
+            key,
+            counter: 0,
+            block: [0; 16],
+            next_word: 16,
+        }
+    }
+}
+
+impl RngCore for ChaCha20 {
+    fn next_u32(&mut self) -> u32 {
+        if self.next_word == 16 {
+            self.refill();
+        }
+        self.next_word += 1;
+        self.block[self.next_word - 1]
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let low = self.next_u32() as u64;
+        low | ((self.next_u32() as u64) << 32)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(4) {
+            let bytes = self.next_u32().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// A seeded deterministic bitstream whose output depends only on this crate:
+/// bits are taken LSB-first from `ChaCha20`'s 64-bit words.
+pub type ChaChaBitstream = RngBitstream<ChaCha20>;
+
+impl RngBitstream<ChaCha20> {
+    pub fn from_seed(seed: [u8; 32]) -> Self {
+        RngBitstream::new(ChaCha20::from_seed(seed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Bitstream, ChaChaBitstream, RngBitstream};
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    #[test]
+    fn matches_chacha20_test_vectors_and_rand_chacha() {
+        // The all-zero key and nonce keystream starts 76 b8 e0 ad a0 f1 3d 90.
+        let mut zero = ChaChaBitstream::from_seed([0; 32]);
+        assert_eq!(zero.gen_bits(64), 0x903d_f1a0_ade0_b876);
+
+        let seed: [u8; 32] = std::array::from_fn(|index| index as u8 * 7);
+        let mut native = ChaChaBitstream::from_seed(seed);
+        let mut reference = RngBitstream::new(ChaCha20Rng::from_seed(seed));
+        for num_bits in (0..=64).cycle().take(2000) {
+            assert_eq!(native.gen_bits(num_bits), reference.gen_bits(num_bits));
+        }
+    }
+}
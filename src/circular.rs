@@ -0,0 +1,137 @@
+//! Distributions on the circle. Each samples angles in radians, in
+//! `[-pi, pi)`, or points on the unit circle as `[cos, sin]`.
+
+use crate::continuous::standard_normal;
+use crate::dist::open_unit_f64;
+use crate::{Bitstream, Distribution};
+use std::f64::consts::PI;
+
+/// Wraps `angle` into `[-pi, pi)`.
+fn wrap(angle: f64) -> f64 {
+    let wrapped = (angle + PI).rem_euclid(2.0 * PI) - PI;
+    // rem_euclid can round up to exactly 2 pi.
+    if wrapped >= PI {
+        -PI
+    } else {
+        wrapped
+    }
+}
+
+/// A uniformly random direction.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct UniformAngle;
+
+impl Distribution<f64> for UniformAngle {
+    fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> f64 {
+        wrap(2.0 * PI * open_unit_f64(bitstream) - PI)
+    }
+}
+
+impl Distribution<[f64; 2]> for UniformAngle {
+    fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> [f64; 2] {
+        let angle: f64 = self.sample(bitstream);
+        [angle.cos(), angle.sin()]
+    }
+}
+
+/// The von Mises distribution, the circular analogue of the normal: density
+/// proportional to `exp(concentration * cos(angle - mean))`. Sampled by Best
+/// and Fisher's rejection method.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct VonMises {
+    mean: f64,
+    concentration: f64,
+    // Best and Fisher's r.
+    r: f64,
+}
+
+impl VonMises {
+    /// # Panics
+    ///
+    /// If `mean` isn't finite, or `concentration` is negative or not finite.
+    pub fn new(mean: f64, concentration: f64) -> Self {
+        assert!(mean.is_finite(), "mean must be finite");
+        assert!(
+            concentration >= 0.0 && concentration.is_finite(),
+            "concentration must be finite and nonnegative"
+        );
+        let tau = 1.0 + (1.0 + 4.0 * concentration * concentration).sqrt();
+        let rho = (tau - (2.0 * tau).sqrt()) / (2.0 * concentration);
+        VonMises {
+            mean,
+            concentration,
+            r: (1.0 + rho * rho) / (2.0 * rho),
+        }
+    }
+}
+
+impl Distribution<f64> for VonMises {
+    fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> f64 {
+        if self.concentration < 1e-6 {
+            return UniformAngle.sample(bitstream);
+        }
+        if self.concentration > 1e6 {
+            // Indistinguishable from a normal with variance 1 / concentration,
+            // and r would be swamped by rounding.
+            return wrap(self.mean + standard_normal(bitstream) / self.concentration.sqrt());
+        }
+        loop {
+            let z = (PI * open_unit_f64(bitstream)).cos();
+            let f = (1.0 + self.r * z) / (self.r + z);
+            let c = self.concentration * (self.r - f);
+            let u = open_unit_f64(bitstream);
+            if c * (2.0 - c) > u || (c / u).ln() + 1.0 >= c {
+                let offset = f.clamp(-1.0, 1.0).acos();
+                let offset = if open_unit_f64(bitstream) < 0.5 {
+                    -offset
+                } else {
+                    offset
+                };
+                return wrap(self.mean + offset);
+            }
+        }
+    }
+}
+
+impl Distribution<[f64; 2]> for VonMises {
+    fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> [f64; 2] {
+        let angle: f64 = self.sample(bitstream);
+        [angle.cos(), angle.sin()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BitstreamExt, RngBitstream};
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn circular_distributions_have_the_right_spread() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let num_samples = 100000;
+        // The mean resultant length of von Mises is I1(k) / I0(k): about
+        // 0.4464 for k = 1 and 0.9485 for k = 10.
+        for &(concentration, expected) in &[(0.0, 0.0), (1.0, 0.4464), (10.0, 0.9485)] {
+            let distribution = VonMises::new(3.0, concentration);
+            let mut sum = [0.0, 0.0];
+            for _ in 0..num_samples {
+                let angle: f64 = bitstream.sample(distribution);
+                assert!((-PI..PI).contains(&angle));
+                let point: [f64; 2] = [angle.cos(), angle.sin()];
+                sum[0] += point[0];
+                sum[1] += point[1];
+            }
+            let length = (sum[0] * sum[0] + sum[1] * sum[1]).sqrt() / num_samples as f64;
+            assert!((length - expected).abs() < 0.01, "{}", length);
+            if concentration > 0.0 {
+                assert!((sum[1].atan2(sum[0]) - 3.0).abs() < 0.05);
+            }
+        }
+        let point: [f64; 2] = bitstream.sample(UniformAngle);
+        assert!((point[0].hypot(point[1]) - 1.0).abs() < 1e-12);
+        let near: f64 = bitstream.sample(VonMises::new(PI - 1e-9, 1e9));
+        assert!(PI - near.abs() < 1e-3);
+    }
+}
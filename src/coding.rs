@@ -0,0 +1,414 @@
+//! Samplers that double as decoders, paired with encoders, so the random
+//! decisions of a simulation can be stored in about their entropy and
+//! replayed exactly.
+//!
+//! `CoinDecoder` is a Bernoulli sampler that reads its bits as an arithmetic
+//! code: on a random bitstream it flips coins using close to `H(p)` bits
+//! each, and on the output of a `CoinEncoder` given the same probabilities it
+//! reproduces the encoded flips. `HuffmanCode` does the same for a fixed
+//! categorical distribution, more simply but less tightly.
+
+use crate::error::assert_num_bits;
+use crate::{Bitstream, Distribution};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+// The coder keeps 62-bit bounds, so 2 * high + 1 still fits in a u64.
+const PRECISION: u32 = 62;
+const WHOLE: u64 = 1 << PRECISION;
+const HALF: u64 = WHOLE / 2;
+const QUARTER: u64 = WHOLE / 4;
+
+/// The probability of `false` as a 32-bit fixed-point fraction, kept away
+/// from 0 and 1 so both outcomes stay encodable.
+fn false_weight(p: f64) -> u64 {
+    assert!((0.0..=1.0).contains(&p), "probability must be in [0, 1]");
+    (((1.0 - p) * (1u64 << 32) as f64).round() as u64).clamp(1, (1 << 32) - 1)
+}
+
+/// The last value `false` covers in `low..=high`.
+fn split(low: u64, high: u64, p: f64) -> u64 {
+    let range = (high - low + 1) as u128;
+    low + ((range * false_weight(p) as u128) >> 32) as u64 - 1
+}
+
+/// Encodes coin flips into bytes, 8 bits per byte, least significant bit
+/// first, as `BitstreamExt::fill_bytes` would fill them.
+#[derive(Clone, Debug)]
+pub struct CoinEncoder {
+    low: u64,
+    high: u64,
+    pending: u64,
+    bytes: Vec<u8>,
+    num_bits: u64,
+}
+
+impl Default for CoinEncoder {
+    fn default() -> Self {
+        CoinEncoder::new()
+    }
+}
+
+impl CoinEncoder {
+    pub fn new() -> Self {
+        CoinEncoder {
+            low: 0,
+            high: WHOLE - 1,
+            pending: 0,
+            bytes: Vec::new(),
+            num_bits: 0,
+        }
+    }
+
+    fn push_bit(&mut self, bit: bool) {
+        if self.num_bits.is_multiple_of(8) {
+            self.bytes.push(0);
+        }
+        *self.bytes.last_mut().unwrap() |= (bit as u8) << (self.num_bits % 8);
+        self.num_bits += 1;
+    }
+
+    fn emit(&mut self, bit: bool) {
+        self.push_bit(bit);
+        for _ in 0..self.pending {
+            self.push_bit(!bit);
+        }
+        self.pending = 0;
+    }
+
+    /// Appends a flip that came up `outcome` on a coin with probability `p`
+    /// of `true`. Decoding must use the same `p`.
+    ///
+    /// # Panics
+    ///
+    /// If `p` isn't in `[0, 1]`.
+    pub fn encode(&mut self, p: f64, outcome: bool) {
+        let split = split(self.low, self.high, p);
+        if outcome {
+            self.low = split + 1;
+        } else {
+            self.high = split;
+        }
+        loop {
+            if self.high < HALF {
+                self.emit(false);
+            } else if self.low >= HALF {
+                self.emit(true);
+                self.low -= HALF;
+                self.high -= HALF;
+            } else if self.low >= QUARTER && self.high < 3 * QUARTER {
+                self.pending += 1;
+                self.low -= QUARTER;
+                self.high -= QUARTER;
+            } else {
+                break;
+            }
+            self.low *= 2;
+            self.high = 2 * self.high + 1;
+        }
+    }
+
+    /// The encoded bytes. A decoder reading past them must see 0 bits, as it
+    /// does from `BytesBitstream`.
+    pub fn finish(mut self) -> Vec<u8> {
+        self.pending += 1;
+        self.emit(self.low >= QUARTER);
+        self.bytes
+    }
+}
+
+/// Flips coins by arithmetic decoding of its bits.
+#[derive(Clone, Debug)]
+pub struct CoinDecoder {
+    low: u64,
+    high: u64,
+    value: u64,
+    started: bool,
+}
+
+impl Default for CoinDecoder {
+    fn default() -> Self {
+        CoinDecoder::new()
+    }
+}
+
+impl CoinDecoder {
+    pub fn new() -> Self {
+        CoinDecoder {
+            low: 0,
+            high: WHOLE - 1,
+            value: 0,
+            started: false,
+        }
+    }
+
+    /// A flip of a coin with probability `p` of `true`, quantized to a
+    /// multiple of 2^-32. The first flip reads 62 bits; after that, each
+    /// reads only as many as it narrows the code down by.
+    ///
+    /// # Panics
+    ///
+    /// If `p` isn't in `[0, 1]`.
+    pub fn flip<B: Bitstream + ?Sized>(&mut self, bitstream: &mut B, p: f64) -> bool {
+        if !self.started {
+            for _ in 0..PRECISION {
+                self.value = 2 * self.value + bitstream.gen_bit() as u64;
+            }
+            self.started = true;
+        }
+        let split = split(self.low, self.high, p);
+        let outcome = self.value > split;
+        if outcome {
+            self.low = split + 1;
+        } else {
+            self.high = split;
+        }
+        loop {
+            let offset = if self.high < HALF {
+                0
+            } else if self.low >= HALF {
+                HALF
+            } else if self.low >= QUARTER && self.high < 3 * QUARTER {
+                QUARTER
+            } else {
+                break;
+            };
+            self.low = 2 * (self.low - offset);
+            self.high = 2 * (self.high - offset) + 1;
+            self.value = 2 * (self.value - offset) + bitstream.gen_bit() as u64;
+        }
+        outcome
+    }
+}
+
+/// A canonical Huffman code for integer weights. As a `Distribution`, it
+/// reads bits until they spell a codeword and returns that symbol, so
+/// symbol `i` comes up with probability `2^-length(i)`: within a bit of the
+/// entropy on average, but only exactly proportional to the weights when
+/// they're powers of 2 over their sum. `encode` is its inverse.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct HuffmanCode {
+    lengths: Vec<u32>,
+    // Symbols with codes, by (length, symbol): canonical order.
+    sorted: Vec<usize>,
+    // For each length: how many codewords have it, and the first of them.
+    counts: Vec<u64>,
+    first_codes: Vec<u64>,
+}
+
+impl HuffmanCode {
+    /// Symbols of weight 0 get no codeword.
+    ///
+    /// # Panics
+    ///
+    /// If every weight is 0, or a codeword would be longer than 64 bits
+    /// (which takes weights spanning a ratio of about 2^44).
+    pub fn new(weights: &[u64]) -> Self {
+        let mut lengths = vec![0; weights.len()];
+        // Each tree is its total weight and its leaves.
+        let mut heap: BinaryHeap<Reverse<(u128, Vec<usize>)>> = weights
+            .iter()
+            .enumerate()
+            .filter(|&(_, &weight)| weight > 0)
+            .map(|(symbol, &weight)| Reverse((weight as u128, vec![symbol])))
+            .collect();
+        assert!(!heap.is_empty(), "total weight must be positive");
+        while heap.len() > 1 {
+            let Reverse((weight_a, mut leaves)) = heap.pop().unwrap();
+            let Reverse((weight_b, leaves_b)) = heap.pop().unwrap();
+            leaves.extend(leaves_b);
+            for &leaf in &leaves {
+                lengths[leaf] += 1;
+            }
+            heap.push(Reverse((weight_a + weight_b, leaves)));
+        }
+        let max_length = *lengths.iter().max().unwrap();
+        assert!(max_length <= 64, "codewords would be over 64 bits long");
+
+        let mut sorted: Vec<usize> = (0..weights.len()).filter(|&s| weights[s] > 0).collect();
+        sorted.sort_by_key(|&symbol| (lengths[symbol], symbol));
+        let mut counts = vec![0; max_length as usize + 1];
+        for &symbol in &sorted {
+            counts[lengths[symbol] as usize] += 1;
+        }
+        let mut first_codes = vec![0; max_length as usize + 1];
+        let mut code = 0u64;
+        for length in 1..=max_length as usize {
+            code = (code + counts[length - 1]) << 1;
+            first_codes[length] = code;
+        }
+        HuffmanCode {
+            lengths,
+            sorted,
+            counts,
+            first_codes,
+        }
+    }
+
+    /// The codeword lengths, 0 for symbols without one (and for the only
+    /// symbol, if there's just one).
+    pub fn lengths(&self) -> &[u32] {
+        &self.lengths
+    }
+
+    /// The codeword for `symbol` and its length. The most significant of the
+    /// `length` bits is the first one read.
+    ///
+    /// # Panics
+    ///
+    /// If `symbol` has no codeword.
+    pub fn codeword(&self, symbol: usize) -> (u64, u32) {
+        let length = self.lengths[symbol];
+        assert!(
+            length > 0 || self.sorted == [symbol],
+            "symbol {} has weight 0",
+            symbol
+        );
+        let rank = self.sorted[..]
+            .iter()
+            .filter(|&&other| self.lengths[other] == length)
+            .position(|&other| other == symbol)
+            .unwrap() as u64;
+        (self.first_codes[length as usize] + rank, length)
+    }
+
+    /// Encodes `symbols` as bytes, 8 bits per byte, least significant bit
+    /// first, for decoding with `BytesBitstream`.
+    ///
+    /// # Panics
+    ///
+    /// If a symbol has no codeword.
+    pub fn encode(&self, symbols: impl IntoIterator<Item = usize>) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut num_bits = 0u64;
+        for symbol in symbols {
+            let (code, length) = self.codeword(symbol);
+            for place in (0..length).rev() {
+                if num_bits.is_multiple_of(8) {
+                    bytes.push(0);
+                }
+                *bytes.last_mut().unwrap() |= (((code >> place) & 1) as u8) << (num_bits % 8);
+                num_bits += 1;
+            }
+        }
+        bytes
+    }
+}
+
+impl Distribution<usize> for HuffmanCode {
+    fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> usize {
+        let mut code = 0;
+        let mut offset = 0;
+        for length in 0..self.counts.len() {
+            if length > 0 {
+                code = (code << 1) | bitstream.gen_bit() as u64;
+            }
+            let index = code.wrapping_sub(self.first_codes[length]);
+            if index < self.counts[length] {
+                return self.sorted[offset + index as usize];
+            }
+            offset += self.counts[length] as usize;
+        }
+        unreachable!("a complete prefix code always ends in a codeword")
+    }
+}
+
+/// A bitstream over bytes, least significant bit first, followed by zeros
+/// forever.
+pub struct BytesBitstream<'a> {
+    bytes: &'a [u8],
+    position: u64,
+}
+
+impl<'a> BytesBitstream<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        BytesBitstream { bytes, position: 0 }
+    }
+}
+
+impl Bitstream for BytesBitstream<'_> {
+    fn gen_bits(&mut self, num_bits: u32) -> u64 {
+        assert_num_bits(num_bits);
+        let mut result = 0;
+        for index in 0..num_bits {
+            let byte = self.bytes.get((self.position / 8) as usize).unwrap_or(&0);
+            result |= (((byte >> (self.position % 8)) & 1) as u64) << index;
+            self.position += 1;
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{measure, BitstreamExt, RngBitstream};
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn flips_encode_to_about_their_entropy_and_replay() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let probabilities: Vec<f64> = (0..10000).map(|i| [0.1, 0.5, 0.01][i % 3]).collect();
+        let entropy: f64 = probabilities
+            .iter()
+            .map(|p| -p * p.log2() - (1.0 - p) * (1.0 - p).log2())
+            .sum();
+
+        let mut decoder = CoinDecoder::new();
+        let mut flips = Vec::new();
+        let measurement = measure(&mut bitstream, 1, entropy, |b| {
+            flips = probabilities.iter().map(|&p| decoder.flip(b, p)).collect();
+        });
+        assert!(measurement.overhead() < 100.0, "{:?}", measurement);
+        let trues = |p| {
+            (0..10000)
+                .filter(|&i| probabilities[i] == p && flips[i])
+                .count()
+        };
+        assert!((trues(0.1) as i32 - 333).abs() < 60);
+        assert!((trues(0.5) as i32 - 1667).abs() < 120);
+
+        let mut encoder = CoinEncoder::new();
+        for (&p, &flip) in probabilities.iter().zip(&flips) {
+            encoder.encode(p, flip);
+        }
+        let bytes = encoder.finish();
+        assert!(bytes.len() as f64 * 8.0 < entropy + 64.0);
+
+        let mut replay = BytesBitstream::new(&bytes);
+        let mut decoder = CoinDecoder::new();
+        let replayed: Vec<bool> = probabilities
+            .iter()
+            .map(|&p| decoder.flip(&mut replay, p))
+            .collect();
+        assert_eq!(replayed, flips);
+    }
+
+    #[test]
+    fn huffman_codes_sample_and_encode_inversely() {
+        let code = HuffmanCode::new(&[1, 1, 0, 2, 4]);
+        assert_eq!(code.lengths(), &[3, 3, 0, 2, 1]);
+        assert_eq!(code.codeword(4), (0b0, 1));
+        assert_eq!(code.codeword(3), (0b10, 2));
+        assert_eq!(code.codeword(0), (0b110, 3));
+        assert_eq!(code.codeword(1), (0b111, 3));
+
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let symbols: Vec<usize> = bitstream.samples(&code).take(80000).collect();
+        let count = |symbol| symbols.iter().filter(|&&s| s == symbol).count() as i32;
+        for (symbol, expected) in [(0, 10000), (1, 10000), (2, 0), (3, 20000), (4, 40000)] {
+            assert!((count(symbol) - expected).abs() < 600);
+        }
+
+        let bytes = code.encode(symbols.iter().copied());
+        let num_bits: u32 = symbols.iter().map(|&s| code.lengths()[s]).sum();
+        assert_eq!(bytes.len(), num_bits.div_ceil(8) as usize);
+        let mut replay = BytesBitstream::new(&bytes);
+        let replayed: Vec<usize> = replay.samples(&code).take(80000).collect();
+        assert_eq!(replayed, symbols);
+
+        let only = HuffmanCode::new(&[0, 5]);
+        assert_eq!((only.codeword(1), bitstream.sample(&only)), ((0, 0), 1));
+    }
+}
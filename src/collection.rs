@@ -0,0 +1,65 @@
+use crate::{Bitstream, Distribution};
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// A `Vec` with a length drawn from `length`, then that many elements drawn
+/// from `element`. Use `Constant(n)` for a fixed length, or `Geometric` for
+/// the short-biased lengths fuzzers like.
+pub fn sample_vec<B, T, D, L>(bitstream: &mut B, element: D, length: L) -> Vec<T>
+where
+    B: Bitstream + ?Sized,
+    D: Distribution<T>,
+    L: Distribution<u64>,
+{
+    let length = length.sample(bitstream);
+    (0..length).map(|_| element.sample(bitstream)).collect()
+}
+
+/// A `HashMap` from `length` key-value pairs, each key drawn before its
+/// value. A key drawn again replaces its earlier value, so the map can come
+/// out smaller than `length`.
+pub fn sample_map<B, K, V, DK, DV>(
+    bitstream: &mut B,
+    key: DK,
+    value: DV,
+    length: usize,
+) -> HashMap<K, V>
+where
+    B: Bitstream + ?Sized,
+    K: Eq + Hash,
+    DK: Distribution<K>,
+    DV: Distribution<V>,
+{
+    (0..length)
+        .map(|_| (key.sample(bitstream), value.sample(bitstream)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BitstreamExt, Constant, Geometric, RngBitstream, UniformRange};
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn collections_draw_their_length_then_elements() {
+        let mut collected = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let mut separate = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let digits = UniformRange::new(10);
+        let values = sample_vec(&mut collected, digits, Constant(20));
+        let expected: Vec<u64> = (0..20).map(|_| separate.gen_range(10)).collect();
+        assert_eq!(values, expected);
+
+        let lengths: Vec<usize> = (0..10000)
+            .map(|_| sample_vec(&mut collected, digits, Geometric::with_mean(4.0)).len())
+            .collect();
+        let mean = lengths.iter().sum::<usize>() as f64 / lengths.len() as f64;
+        assert!((mean - 4.0).abs() < 0.2);
+
+        let map = sample_map(&mut collected, UniformRange::new(1 << 40), digits, 100);
+        assert_eq!(map.len(), 100);
+        assert!(map.values().all(|&value| value < 10));
+        assert_eq!(sample_map(&mut collected, Constant(7), digits, 5).len(), 1);
+    }
+}
@@ -0,0 +1,165 @@
+//! Random colors and palettes for generative art.
+
+use crate::dist::open_unit_f64;
+use crate::{Bitstream, BitstreamExt};
+use std::fmt;
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+/// Formats as `#rrggbb`.
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+/// Hue in degrees in `[0, 360)`, saturation and lightness in `[0, 1]`.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub struct Hsl {
+    pub h: f64,
+    pub s: f64,
+    pub l: f64,
+}
+
+impl Hsl {
+    pub fn to_rgb(self) -> Rgb {
+        let chroma = (1.0 - (2.0 * self.l - 1.0).abs()) * self.s;
+        let h = self.h.rem_euclid(360.0) / 60.0;
+        let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+        let (r, g, b) = match h as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        let m = self.l - chroma / 2.0;
+        let channel = |c: f64| ((c + m) * 255.0).round().clamp(0.0, 255.0) as u8;
+        Rgb {
+            r: channel(r),
+            g: channel(g),
+            b: channel(b),
+        }
+    }
+}
+
+/// A uniform 24-bit color, from 24 bits.
+pub fn uniform_rgb<B: Bitstream + ?Sized>(bitstream: &mut B) -> Rgb {
+    let bits = bitstream.gen_bits(24);
+    Rgb {
+        r: bits as u8,
+        g: (bits >> 8) as u8,
+        b: (bits >> 16) as u8,
+    }
+}
+
+/// Hue, saturation and lightness each uniform in their ranges. Uniform in
+/// HSL isn't uniform in RGB: it favors grays and very dark and light colors.
+pub fn uniform_hsl<B: Bitstream + ?Sized>(bitstream: &mut B) -> Hsl {
+    Hsl {
+        h: 360.0 * open_unit_f64(bitstream),
+        s: open_unit_f64(bitstream),
+        l: open_unit_f64(bitstream),
+    }
+}
+
+/// Color-wheel relationships between the hues of a palette.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Harmony {
+    /// Two hues opposite each other.
+    Complementary,
+    /// Three neighboring hues, 15 to 45 degrees apart.
+    Analogous,
+    /// Three hues evenly spaced.
+    Triadic,
+    /// A hue and the two on either side of its complement, 20 to 40
+    /// degrees from it.
+    SplitComplementary,
+    /// Four hues evenly spaced.
+    Tetradic,
+}
+
+/// A palette with a uniform base hue and the others placed by `harmony`.
+/// Saturation (0.5 to 0.9) and lightness (0.35 to 0.65) are drawn once and
+/// shared, so the colors sit together.
+pub fn palette<B: Bitstream + ?Sized>(bitstream: &mut B, harmony: Harmony) -> Vec<Hsl> {
+    let base = bitstream.gen_range(360) as f64;
+    let offsets = match harmony {
+        Harmony::Complementary => vec![0, 180],
+        Harmony::Analogous => {
+            let step = bitstream.gen_range_inclusive(15, 45);
+            vec![0, step, 2 * step]
+        }
+        Harmony::Triadic => vec![0, 120, 240],
+        Harmony::SplitComplementary => {
+            let spread = bitstream.gen_range_inclusive(20, 40);
+            vec![0, 180 - spread, 180 + spread]
+        }
+        Harmony::Tetradic => vec![0, 90, 180, 270],
+    };
+    let s = 0.5 + 0.4 * open_unit_f64(bitstream);
+    let l = 0.35 + 0.3 * open_unit_f64(bitstream);
+    offsets
+        .into_iter()
+        .map(|offset| Hsl {
+            h: (base + offset as f64) % 360.0,
+            s,
+            l,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CountingRngBitstream;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn colors_and_palettes_are_well_formed() {
+        let mut bitstream = CountingRngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let mut reds = 0;
+        for _ in 0..10000 {
+            reds += (uniform_rgb(&mut bitstream).r >= 128) as u32;
+        }
+        assert_eq!(bitstream.count(), 240000);
+        assert!((reds as f64 / 10000.0 - 0.5).abs() < 0.02);
+
+        let to_rgb = |h, s, l| Hsl { h, s, l }.to_rgb().to_string();
+        assert_eq!(to_rgb(0.0, 1.0, 0.5), "#ff0000");
+        assert_eq!(to_rgb(120.0, 1.0, 0.5), "#00ff00");
+        assert_eq!(to_rgb(240.0, 1.0, 0.25), "#000080");
+        assert_eq!(to_rgb(300.0, 0.0, 0.5), "#808080");
+        assert_eq!(to_rgb(-60.0, 1.0, 1.0), "#ffffff");
+        for _ in 0..1000 {
+            let color = uniform_hsl(&mut bitstream);
+            assert!((0.0..360.0).contains(&color.h));
+            color.to_rgb();
+        }
+
+        let hue_gap = |a: &Hsl, b: &Hsl| {
+            let gap = (a.h - b.h).rem_euclid(360.0);
+            gap.min(360.0 - gap)
+        };
+        for _ in 0..100 {
+            let colors = palette(&mut bitstream, Harmony::Triadic);
+            assert_eq!(colors.len(), 3);
+            assert_eq!(hue_gap(&colors[0], &colors[1]), 120.0);
+            assert!(colors
+                .iter()
+                .all(|c| c.s == colors[0].s && c.l == colors[0].l));
+            let colors = palette(&mut bitstream, Harmony::Analogous);
+            assert!((15.0..=45.0).contains(&hue_gap(&colors[0], &colors[1])));
+            assert_eq!(palette(&mut bitstream, Harmony::Tetradic).len(), 4);
+            let colors = palette(&mut bitstream, Harmony::SplitComplementary);
+            assert!((140.0..=160.0).contains(&hue_gap(&colors[0], &colors[1])));
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use crate::{Bitstream, BitstreamExt};
+
+/// `n` choose `k`, which fits in a `u64` for all `n <= 64`.
+pub(crate) fn binomial(n: u32, k: u32) -> u64 {
+    if k > n {
+        return 0;
+    }
+    let k = k.min(n - k);
+    let mut result: u128 = 1;
+    for i in 0..k {
+        // Each partial product is itself a binomial coefficient, so the
+        // division is exact.
+        result = result * (n - i) as u128 / (i + 1) as u128;
+    }
+    result as u64
+}
+
+/// # Panics
+///
+/// If `width > 64` or `weight > width`.
+pub(crate) fn gen_bits_with_weight<B: Bitstream + ?Sized>(
+    bitstream: &mut B,
+    width: u32,
+    weight: u32,
+) -> u64 {
+    assert!(width <= 64, "width must be at most 64");
+    assert!(weight <= width, "weight must be at most width");
+    // Unrank a uniform rank in the combinatorial number system: from the top
+    // position down, the words without that bit set come first.
+    let mut rank = bitstream.gen_range(binomial(width, weight));
+    let mut remaining = weight;
+    let mut word = 0;
+    for position in (0..width).rev() {
+        if remaining == 0 {
+            break;
+        }
+        let without = binomial(position, remaining);
+        if rank >= without {
+            word |= 1 << position;
+            rank -= without;
+            remaining -= 1;
+        }
+    }
+    word
+}
+
+#[cfg(test)]
+mod tests {
+    use super::binomial;
+    use crate::{BitstreamExt, CountingRngBitstream};
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn constant_weight_words_are_uniform_and_cheap() {
+        assert_eq!(binomial(64, 32), 1_832_624_140_942_590_534);
+        let mut bitstream = CountingRngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let num_samples = 100000;
+        let mut counts = std::collections::HashMap::new();
+        for _ in 0..num_samples {
+            let word = bitstream.gen_bits_with_weight(5, 2);
+            assert!(word < 32 && word.count_ones() == 2);
+            *counts.entry(word).or_insert(0) += 1;
+        }
+        assert_eq!(counts.len(), 10);
+        assert!(counts.values().all(|&count| count > 9500 && count < 10500));
+        let bits_per_sample = bitstream.count() as f64 / num_samples as f64;
+        assert!(bits_per_sample < 10f64.log2() + 2.0, "{}", bits_per_sample);
+
+        assert_eq!(bitstream.gen_bits_with_weight(64, 64), u64::MAX);
+        assert_eq!(bitstream.gen_bits_with_weight(64, 0), 0);
+        assert_eq!(bitstream.gen_bits_with_weight(64, 32).count_ones(), 32);
+    }
+}
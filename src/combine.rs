@@ -0,0 +1,218 @@
+use crate::error::assert_num_bits;
+use crate::{low_bits_mask, Bitstream, Error, TryBitstream};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+/// XORs together the draws of two bitstreams. The result is at least as
+/// unpredictable as the better of the two, as long as they're independent,
+/// so it's a way to mix a hardware or external entropy source into a PRNG
+/// without having to fully trust either.
+pub struct XorBitstream<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A, B> XorBitstream<A, B> {
+    pub fn new(first: A, second: B) -> Self {
+        XorBitstream { first, second }
+    }
+
+    pub fn into_inner(self) -> (A, B) {
+        (self.first, self.second)
+    }
+}
+
+impl<A: Bitstream, B: Bitstream> Bitstream for XorBitstream<A, B> {
+    fn gen_bits(&mut self, num_bits: u32) -> u64 {
+        self.first.gen_bits(num_bits) ^ self.second.gen_bits(num_bits)
+    }
+}
+
+/// A fallible first source (e.g. hardware) mixed into an infallible second.
+impl<A: TryBitstream, B: Bitstream> TryBitstream for XorBitstream<A, B> {
+    fn try_gen_bits(&mut self, num_bits: u32) -> Result<u64, Error> {
+        Ok(self.first.try_gen_bits(num_bits)? ^ self.second.gen_bits(num_bits))
+    }
+}
+
+/// Alternates between two bitstreams every `block_bits` bits: the first
+/// block comes from `first`, the next from `second`, and so on.
+pub struct InterleavedBitstream<A, B> {
+    first: A,
+    second: B,
+    block_bits: u32,
+    // Bits left in the current block, and whether it belongs to `second`.
+    remaining: u32,
+    on_second: bool,
+}
+
+impl<A, B> InterleavedBitstream<A, B> {
+    /// # Panics
+    ///
+    /// If `block_bits` isn't in `1..=64`.
+    pub fn new(first: A, second: B, block_bits: u32) -> Self {
+        assert!(
+            (1..=64).contains(&block_bits),
+            "block_bits must be in 1..=64"
+        );
+        InterleavedBitstream {
+            first,
+            second,
+            block_bits,
+            remaining: block_bits,
+            on_second: false,
+        }
+    }
+
+    pub fn into_inner(self) -> (A, B) {
+        (self.first, self.second)
+    }
+}
+
+impl<A: Bitstream, B: Bitstream> Bitstream for InterleavedBitstream<A, B> {
+    fn gen_bits(&mut self, num_bits: u32) -> u64 {
+        assert_num_bits(num_bits);
+        let mut result = 0;
+        let mut filled = 0;
+        while filled < num_bits {
+            if self.remaining == 0 {
+                self.remaining = self.block_bits;
+                self.on_second = !self.on_second;
+            }
+            let take = (num_bits - filled).min(self.remaining);
+            let bits = if self.on_second {
+                self.second.gen_bits(take)
+            } else {
+                self.first.gen_bits(take)
+            };
+            result |= bits << filled;
+            filled += take;
+            self.remaining -= take;
+        }
+        result
+    }
+}
+
+struct Demultiplexer<B> {
+    source: B,
+    block_bits: u32,
+    queues: Vec<VecDeque<u64>>,
+    next_output: usize,
+}
+
+/// One of the outputs of `demultiplex`.
+pub struct DemultiplexedBitstream<B> {
+    shared: Rc<RefCell<Demultiplexer<B>>>,
+    index: usize,
+    buffer: u64,
+    unused_bits: u32,
+}
+
+/// Splits `source` into `num_outputs` bitstreams, dealing its blocks of
+/// `block_bits` bits out round-robin: output `i` gets blocks `i`,
+/// `i + num_outputs`, and so on. Blocks are queued for outputs that haven't
+/// asked for them yet, so each output sees the same bits however the
+/// outputs' draws are ordered, e.g. when several consumers share one
+/// recorded tape.
+///
+/// # Panics
+///
+/// If `num_outputs` is 0 or `block_bits` isn't in `1..=64`.
+pub fn demultiplex<B: Bitstream>(
+    source: B,
+    num_outputs: usize,
+    block_bits: u32,
+) -> Vec<DemultiplexedBitstream<B>> {
+    assert!(num_outputs > 0, "there must be at least one output");
+    assert!(
+        (1..=64).contains(&block_bits),
+        "block_bits must be in 1..=64"
+    );
+    let shared = Rc::new(RefCell::new(Demultiplexer {
+        source,
+        block_bits,
+        queues: vec![VecDeque::new(); num_outputs],
+        next_output: 0,
+    }));
+    (0..num_outputs)
+        .map(|index| DemultiplexedBitstream {
+            shared: shared.clone(),
+            index,
+            buffer: 0,
+            unused_bits: 0,
+        })
+        .collect()
+}
+
+impl<B: Bitstream> Bitstream for DemultiplexedBitstream<B> {
+    fn gen_bits(&mut self, num_bits: u32) -> u64 {
+        assert_num_bits(num_bits);
+        let mut result = 0;
+        let mut filled = 0;
+        while filled < num_bits {
+            if self.unused_bits == 0 {
+                let mut shared = self.shared.borrow_mut();
+                let shared = &mut *shared;
+                while shared.queues[self.index].is_empty() {
+                    let block = shared.source.gen_bits(shared.block_bits);
+                    shared.queues[shared.next_output].push_back(block);
+                    shared.next_output = (shared.next_output + 1) % shared.queues.len();
+                }
+                self.buffer = shared.queues[self.index].pop_front().unwrap();
+                self.unused_bits = shared.block_bits;
+            }
+            let take = (num_bits - filled).min(self.unused_bits);
+            result |= (self.buffer & low_bits_mask(take)) << filled;
+            self.buffer = self.buffer.checked_shr(take).unwrap_or(0);
+            self.unused_bits -= take;
+            filled += take;
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{demultiplex, Bitstream, InterleavedBitstream, RngBitstream, XorBitstream};
+
+    #[test]
+    fn xor_combines_draw_by_draw() {
+        let mut first = RngBitstream::seeded(1);
+        let mut second = RngBitstream::seeded(2);
+        let mut combined = XorBitstream::new(RngBitstream::seeded(1), RngBitstream::seeded(2));
+        for num_bits in (0..=64).cycle().take(500) {
+            let expected = first.gen_bits(num_bits) ^ second.gen_bits(num_bits);
+            assert_eq!(combined.gen_bits(num_bits), expected);
+        }
+    }
+
+    #[test]
+    fn demultiplex_undoes_interleave() {
+        let interleaved =
+            InterleavedBitstream::new(RngBitstream::seeded(1), RngBitstream::seeded(2), 24);
+        let mut outputs = demultiplex(interleaved, 2, 24);
+        let (mut first, mut second) = (RngBitstream::seeded(1), RngBitstream::seeded(2));
+        // Draw out of step, so the second output's blocks queue up first.
+        for num_bits in (0..=64).cycle().take(500) {
+            assert_eq!(outputs[1].gen_bits(num_bits), second.gen_bits(num_bits));
+        }
+        for num_bits in (0..=64).rev().cycle().take(500) {
+            assert_eq!(outputs[0].gen_bits(num_bits), first.gen_bits(num_bits));
+            assert_eq!(outputs[1].gen_bits(7), second.gen_bits(7));
+        }
+
+        // Output i gets bytes i and i + 3 of the source, in any draw order.
+        let word = RngBitstream::seeded(3).gen_bits(48);
+        let mut outputs = demultiplex(RngBitstream::seeded(3), 3, 8);
+        let bytes: Vec<u64> = [0, 0, 1, 2, 2, 1]
+            .iter()
+            .map(|&output| outputs[output].gen_bits(8))
+            .collect();
+        let expected: Vec<u64> = [0, 3, 1, 2, 5, 4]
+            .iter()
+            .map(|byte| (word >> (8 * byte)) & 0xff)
+            .collect();
+        assert_eq!(bytes, expected);
+    }
+}
@@ -0,0 +1,635 @@
+use crate::dist::{invert_lazy_uniform, open_unit_f64};
+use crate::{Bitstream, Distribution};
+
+/// The exponential distribution with the given rate (mean `1 / rate`).
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Exponential {
+    rate: f64,
+}
+
+impl Exponential {
+    /// # Panics
+    ///
+    /// If `rate` isn't positive and finite.
+    pub fn new(rate: f64) -> Self {
+        assert!(
+            rate > 0.0 && rate.is_finite(),
+            "rate must be positive and finite"
+        );
+        Exponential { rate }
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+}
+
+impl Distribution<f64> for Exponential {
+    fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> f64 {
+        -open_unit_f64(bitstream).ln() / self.rate
+    }
+}
+
+/// A standard normal `f64`, by the Box-Muller transform.
+pub(crate) fn standard_normal<B: Bitstream + ?Sized>(bitstream: &mut B) -> f64 {
+    let radius = (-2.0 * open_unit_f64(bitstream).ln()).sqrt();
+    let angle = 2.0 * std::f64::consts::PI * open_unit_f64(bitstream);
+    radius * angle.cos()
+}
+
+/// The log of a Gamma(`shape`, 1) variate, by Marsaglia and Tsang's method.
+/// Shapes below 1 use Gamma(shape + 1) * U^(1 / shape), in log space so
+/// tiny shapes don't underflow to 0.
+pub(crate) fn ln_gamma_variate<B: Bitstream + ?Sized>(bitstream: &mut B, shape: f64) -> f64 {
+    if shape < 1.0 {
+        let boost = open_unit_f64(bitstream).ln() / shape;
+        return ln_gamma_variate(bitstream, shape + 1.0) + boost;
+    }
+    let d = shape - 1.0 / 3.0;
+    let c = 1.0 / (9.0 * d).sqrt();
+    loop {
+        let x = standard_normal(bitstream);
+        let v = 1.0 + c * x;
+        if v <= 0.0 {
+            continue;
+        }
+        let v = v * v * v;
+        let u = open_unit_f64(bitstream);
+        if u < 1.0 - 0.0331 * x.powi(4) || u.ln() < 0.5 * x * x + d * (1.0 - v + v.ln()) {
+            return (d * v).ln();
+        }
+    }
+}
+
+/// The Gamma distribution with the given shape and scale (mean
+/// `shape * scale`).
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Gamma {
+    shape: f64,
+    scale: f64,
+}
+
+impl Gamma {
+    /// # Panics
+    ///
+    /// If `shape` or `scale` isn't positive and finite.
+    pub fn new(shape: f64, scale: f64) -> Self {
+        assert!(
+            shape > 0.0 && shape.is_finite(),
+            "shape must be positive and finite"
+        );
+        assert!(
+            scale > 0.0 && scale.is_finite(),
+            "scale must be positive and finite"
+        );
+        Gamma { shape, scale }
+    }
+}
+
+impl Distribution<f64> for Gamma {
+    fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> f64 {
+        ln_gamma_variate(bitstream, self.shape).exp() * self.scale
+    }
+}
+
+/// The Beta distribution on `[0, 1]`, as `X / (X + Y)` for Gamma variates
+/// `X` and `Y` with shapes `alpha` and `beta`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Beta {
+    alpha: f64,
+    beta: f64,
+}
+
+impl Beta {
+    /// # Panics
+    ///
+    /// If `alpha` or `beta` isn't positive and finite.
+    pub fn new(alpha: f64, beta: f64) -> Self {
+        assert!(
+            alpha > 0.0 && alpha.is_finite() && beta > 0.0 && beta.is_finite(),
+            "alpha and beta must be positive and finite"
+        );
+        Beta { alpha, beta }
+    }
+}
+
+impl Distribution<f64> for Beta {
+    fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> f64 {
+        let x = ln_gamma_variate(bitstream, self.alpha);
+        let y = ln_gamma_variate(bitstream, self.beta);
+        1.0 / (1.0 + (y - x).exp())
+    }
+}
+
+/// The normal distribution with the given mean and standard deviation.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Normal {
+    mean: f64,
+    std_dev: f64,
+}
+
+impl Normal {
+    /// # Panics
+    ///
+    /// If `mean` isn't finite, or `std_dev` is negative or not finite.
+    pub fn new(mean: f64, std_dev: f64) -> Self {
+        assert!(mean.is_finite(), "mean must be finite");
+        assert!(
+            std_dev >= 0.0 && std_dev.is_finite(),
+            "std_dev must be finite and nonnegative"
+        );
+        Normal { mean, std_dev }
+    }
+}
+
+impl Distribution<f64> for Normal {
+    fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> f64 {
+        self.mean + self.std_dev * standard_normal(bitstream)
+    }
+}
+
+/// The chi-square distribution with `degrees_of_freedom` degrees of
+/// freedom: Gamma with shape `degrees_of_freedom / 2` and scale 2.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct ChiSquared {
+    gamma: Gamma,
+}
+
+impl ChiSquared {
+    /// # Panics
+    ///
+    /// If `degrees_of_freedom` isn't positive and finite.
+    pub fn new(degrees_of_freedom: f64) -> Self {
+        ChiSquared {
+            gamma: Gamma::new(degrees_of_freedom / 2.0, 2.0),
+        }
+    }
+}
+
+impl Distribution<f64> for ChiSquared {
+    fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> f64 {
+        self.gamma.sample(bitstream)
+    }
+}
+
+/// Student's t distribution: a standard normal divided by the square root
+/// of an independent chi-square over its degrees of freedom.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct StudentT {
+    degrees_of_freedom: f64,
+    chi_squared: ChiSquared,
+}
+
+impl StudentT {
+    /// # Panics
+    ///
+    /// If `degrees_of_freedom` isn't positive and finite.
+    pub fn new(degrees_of_freedom: f64) -> Self {
+        StudentT {
+            degrees_of_freedom,
+            chi_squared: ChiSquared::new(degrees_of_freedom),
+        }
+    }
+}
+
+impl Distribution<f64> for StudentT {
+    fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> f64 {
+        let z = standard_normal(bitstream);
+        z / (self.chi_squared.sample(bitstream) / self.degrees_of_freedom).sqrt()
+    }
+}
+
+/// The F distribution: the ratio of two independent chi-squares, each over
+/// its degrees of freedom.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct FisherF {
+    numerator: (f64, ChiSquared),
+    denominator: (f64, ChiSquared),
+}
+
+impl FisherF {
+    /// # Panics
+    ///
+    /// If either number of degrees of freedom isn't positive and finite.
+    pub fn new(numerator_degrees: f64, denominator_degrees: f64) -> Self {
+        FisherF {
+            numerator: (numerator_degrees, ChiSquared::new(numerator_degrees)),
+            denominator: (denominator_degrees, ChiSquared::new(denominator_degrees)),
+        }
+    }
+}
+
+impl Distribution<f64> for FisherF {
+    fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> f64 {
+        let numerator = self.numerator.1.sample(bitstream) / self.numerator.0;
+        numerator / (self.denominator.1.sample(bitstream) / self.denominator.0)
+    }
+}
+
+/// The triangular distribution on `[min, max]` with its peak at `mode`,
+/// sampled by inverting its CDF at a lazily drawn uniform.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Triangular {
+    min: f64,
+    mode: f64,
+    max: f64,
+}
+
+impl Triangular {
+    /// # Panics
+    ///
+    /// Unless `min <= mode <= max` and `min < max`, all finite.
+    pub fn new(min: f64, mode: f64, max: f64) -> Self {
+        assert!(
+            min.is_finite() && max.is_finite() && min <= mode && mode <= max && min < max,
+            "need finite min <= mode <= max with min < max"
+        );
+        Triangular { min, mode, max }
+    }
+}
+
+impl Distribution<f64> for Triangular {
+    fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> f64 {
+        let width = self.max - self.min;
+        let left = self.mode - self.min;
+        invert_lazy_uniform(bitstream, |u| {
+            if u * width < left {
+                self.min + (u * width * left).sqrt()
+            } else {
+                self.max - ((1.0 - u) * width * (self.max - self.mode)).sqrt()
+            }
+        })
+    }
+}
+
+/// The (modified) PERT distribution used in project estimates: a Beta
+/// distribution stretched over `[min, max]`, with mean
+/// `(min + shape * mode + max) / (shape + 2)`. The classic PERT shape is 4;
+/// higher shapes concentrate more around the mode.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Pert {
+    min: f64,
+    mode: f64,
+    max: f64,
+    beta: Beta,
+}
+
+impl Pert {
+    /// # Panics
+    ///
+    /// As `Triangular::new`.
+    pub fn new(min: f64, mode: f64, max: f64) -> Self {
+        Triangular::new(min, mode, max);
+        let beta = Pert::beta(min, mode, max, 4.0);
+        Pert {
+            min,
+            mode,
+            max,
+            beta,
+        }
+    }
+
+    /// # Panics
+    ///
+    /// If `shape` is negative or not finite.
+    pub fn with_shape(self, shape: f64) -> Self {
+        assert!(
+            shape >= 0.0 && shape.is_finite(),
+            "shape must be finite and nonnegative"
+        );
+        Pert {
+            beta: Pert::beta(self.min, self.mode, self.max, shape),
+            ..self
+        }
+    }
+
+    fn beta(min: f64, mode: f64, max: f64, shape: f64) -> Beta {
+        let width = max - min;
+        Beta::new(
+            1.0 + shape * (mode - min) / width,
+            1.0 + shape * (max - mode) / width,
+        )
+    }
+}
+
+impl Distribution<f64> for Pert {
+    fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> f64 {
+        self.min + (self.max - self.min) * self.beta.sample(bitstream)
+    }
+}
+
+/// The `k`-th smallest of `n` independent uniforms on `[0, 1)`, drawn
+/// directly as Beta(`k`, `n - k + 1`) in constant time, without drawing or
+/// sorting the `n` uniforms.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct OrderStatistic {
+    beta: Beta,
+}
+
+impl OrderStatistic {
+    /// # Panics
+    ///
+    /// Unless `1 <= k <= n`.
+    pub fn new(n: u64, k: u64) -> Self {
+        assert!(1 <= k && k <= n, "need 1 <= k <= n");
+        OrderStatistic {
+            beta: Beta::new(k as f64, (n - k + 1) as f64),
+        }
+    }
+}
+
+impl Distribution<f64> for OrderStatistic {
+    fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> f64 {
+        self.beta.sample(bitstream)
+    }
+}
+
+/// `n` independent uniforms on `[0, 1)`, already sorted: the normalized
+/// running sums of `n + 1` exponential spacings. This takes linear time
+/// rather than the `n log n` of sorting.
+pub fn sorted_uniforms<B: Bitstream + ?Sized>(bitstream: &mut B, n: usize) -> Vec<f64> {
+    let mut sum = 0.0;
+    let mut values: Vec<f64> = (0..n)
+        .map(|_| {
+            sum -= open_unit_f64(bitstream).ln();
+            sum
+        })
+        .collect();
+    let total = sum - open_unit_f64(bitstream).ln();
+    for value in &mut values {
+        *value /= total;
+    }
+    values
+}
+
+/// Random probability vectors with density proportional to
+/// `product(x_i^(alpha_i - 1))`: normalized Gamma(alpha_i) variates.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Dirichlet {
+    alphas: Vec<f64>,
+}
+
+impl Dirichlet {
+    /// # Panics
+    ///
+    /// If `alphas` is empty, or any of them isn't positive and finite.
+    pub fn new(alphas: Vec<f64>) -> Self {
+        assert!(
+            !alphas.is_empty(),
+            "a Dirichlet needs at least one component"
+        );
+        assert!(
+            alphas.iter().all(|&alpha| alpha > 0.0 && alpha.is_finite()),
+            "alphas must be positive and finite"
+        );
+        Dirichlet { alphas }
+    }
+
+    /// The symmetric Dirichlet with `num_components` equal alphas.
+    pub fn symmetric(alpha: f64, num_components: usize) -> Self {
+        Dirichlet::new(vec![alpha; num_components])
+    }
+}
+
+impl Distribution<Vec<f64>> for Dirichlet {
+    fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> Vec<f64> {
+        let logs: Vec<f64> = self
+            .alphas
+            .iter()
+            .map(|&alpha| ln_gamma_variate(bitstream, alpha))
+            .collect();
+        let max = logs.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let mut values: Vec<f64> = logs.iter().map(|log| (log - max).exp()).collect();
+        let total: f64 = values.iter().sum();
+        for value in &mut values {
+            *value /= total;
+        }
+        values
+    }
+}
+
+/// Random probability vectors by stick breaking (the GEM distribution,
+/// truncated): each component takes a Beta(1, `concentration`) fraction of
+/// what's left of the stick, and the last takes the rest. Small
+/// concentrations put most of the mass in the first few components.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct StickBreaking {
+    concentration: f64,
+    num_components: usize,
+}
+
+impl StickBreaking {
+    /// # Panics
+    ///
+    /// If `concentration` isn't positive and finite, or `num_components` is
+    /// 0.
+    pub fn new(concentration: f64, num_components: usize) -> Self {
+        assert!(
+            concentration > 0.0 && concentration.is_finite(),
+            "concentration must be positive and finite"
+        );
+        assert!(num_components > 0, "need at least one component");
+        StickBreaking {
+            concentration,
+            num_components,
+        }
+    }
+}
+
+impl Distribution<Vec<f64>> for StickBreaking {
+    fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> Vec<f64> {
+        let mut remaining = 1.0;
+        let mut values = Vec::with_capacity(self.num_components);
+        for _ in 1..self.num_components {
+            // Beta(1, a) by inversion: 1 - U^(1/a).
+            let fraction = -(open_unit_f64(bitstream).ln() / self.concentration).exp_m1();
+            values.push(remaining * fraction);
+            remaining *= 1.0 - fraction;
+        }
+        values.push(remaining);
+        values
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{sorted_uniforms, Normal, OrderStatistic, Pert, RngBitstream, StickBreaking};
+    use crate::{Beta, BitstreamExt, ChiSquared, Dirichlet, Exponential, FisherF, Gamma};
+    use crate::{StudentT, Triangular};
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn exponential_has_the_right_mean() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let mean = bitstream
+            .samples(Exponential::new(4.0))
+            .take(100000)
+            .sum::<f64>()
+            / 100000.0;
+        assert!((mean - 0.25).abs() < 0.005);
+    }
+
+    #[test]
+    fn gamma_and_beta_have_the_right_moments() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let num_samples = 100000;
+        let moments = |samples: Vec<f64>| {
+            let mean = samples.iter().sum::<f64>() / num_samples as f64;
+            let variance =
+                samples.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / num_samples as f64;
+            (mean, variance)
+        };
+        for &(shape, scale) in &[(0.3, 2.0), (1.0, 1.0), (4.5, 0.5)] {
+            let samples = bitstream
+                .samples(Gamma::new(shape, scale))
+                .take(num_samples)
+                .collect();
+            let (mean, variance) = moments(samples);
+            assert!((mean / (shape * scale) - 1.0).abs() < 0.02, "{}", mean);
+            assert!(
+                (variance / (shape * scale * scale) - 1.0).abs() < 0.05,
+                "{}",
+                variance
+            );
+        }
+        for &(alpha, beta) in &[(0.5, 0.5), (2.0, 5.0), (1e-3, 1.0)] {
+            let samples: Vec<f64> = bitstream
+                .samples(Beta::new(alpha, beta))
+                .take(num_samples)
+                .collect();
+            assert!(samples.iter().all(|x| (0.0..=1.0).contains(x)));
+            let (mean, variance) = moments(samples);
+            let expected_mean = alpha / (alpha + beta);
+            let expected_variance = alpha * beta / ((alpha + beta).powi(2) * (alpha + beta + 1.0));
+            assert!((mean - expected_mean).abs() < 0.005, "{}", mean);
+            assert!((variance - expected_variance).abs() < 0.05 * expected_variance + 1e-4);
+        }
+    }
+
+    #[test]
+    fn derived_distributions_have_the_right_moments() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let num_samples = 100000;
+        let mut moments = |distribution: &dyn Fn(&mut RngBitstream<ChaChaRng>) -> f64| {
+            let samples: Vec<f64> = (0..num_samples)
+                .map(|_| distribution(&mut bitstream))
+                .collect();
+            let mean = samples.iter().sum::<f64>() / num_samples as f64;
+            let variance =
+                samples.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / num_samples as f64;
+            (mean, variance)
+        };
+        let close = |(mean, variance): (f64, f64), expected: (f64, f64)| {
+            assert!(
+                (mean - expected.0).abs() < 0.03,
+                "{} vs {}",
+                mean,
+                expected.0
+            );
+            assert!(
+                (variance / expected.1 - 1.0).abs() < 0.05,
+                "{} vs {}",
+                variance,
+                expected.1
+            );
+        };
+        close(moments(&|b| b.sample(Normal::new(3.0, 2.0))), (3.0, 4.0));
+        close(moments(&|b| b.sample(ChiSquared::new(5.0))), (5.0, 10.0));
+        close(moments(&|b| b.sample(StudentT::new(10.0))), (0.0, 1.25));
+        // F(10, 20): mean 20/18, variance 2 * 20^2 * 28 / (10 * 18^2 * 16).
+        close(
+            moments(&|b| b.sample(FisherF::new(10.0, 20.0))),
+            (20.0 / 18.0, 22400.0 / 51840.0),
+        );
+    }
+
+    #[test]
+    fn estimate_distributions_stay_in_range() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let num_samples = 100000;
+        let triangular: Vec<f64> = bitstream
+            .samples(Triangular::new(1.0, 2.0, 5.0))
+            .take(num_samples)
+            .collect();
+        assert!(triangular.iter().all(|x| (1.0..=5.0).contains(x)));
+        let mean = triangular.iter().sum::<f64>() / num_samples as f64;
+        assert!((mean - 8.0 / 3.0).abs() < 0.01);
+        // P(X < mode) = (mode - min) / (max - min).
+        let below_mode = triangular.iter().filter(|&&x| x < 2.0).count();
+        assert!((below_mode as f64 / num_samples as f64 - 0.25).abs() < 0.005);
+
+        for &(shape, expected_mean) in &[(4.0, 11.5 / 6.0), (10.0, 20.5 / 12.0)] {
+            let pert: Vec<f64> = bitstream
+                .samples(Pert::new(1.0, 1.5, 4.5).with_shape(shape))
+                .take(num_samples)
+                .collect();
+            assert!(pert.iter().all(|x| (1.0..=4.5).contains(x)));
+            let mean = pert.iter().sum::<f64>() / num_samples as f64;
+            assert!((mean - expected_mean).abs() < 0.01, "{}", mean);
+        }
+    }
+
+    #[test]
+    fn order_statistics_have_the_right_means() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let num_samples = 20000;
+        // The k-th of n has mean k / (n + 1).
+        let mean = bitstream
+            .samples(OrderStatistic::new(1_000_000, 10))
+            .take(num_samples)
+            .sum::<f64>()
+            / num_samples as f64;
+        assert!((mean * 1_000_001.0 / 10.0 - 1.0).abs() < 0.02);
+
+        let mut sums = [0.0; 4];
+        for _ in 0..num_samples {
+            let values = sorted_uniforms(&mut bitstream, 4);
+            assert!(values.windows(2).all(|pair| pair[0] <= pair[1]));
+            assert!(values.iter().all(|value| (0.0..1.0).contains(value)));
+            for (sum, value) in sums.iter_mut().zip(&values) {
+                *sum += value;
+            }
+        }
+        for (k, sum) in sums.iter().enumerate() {
+            assert!((sum / num_samples as f64 - (k + 1) as f64 / 5.0).abs() < 0.005);
+        }
+    }
+
+    #[test]
+    fn random_probability_vectors_have_the_right_means() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let num_samples = 20000;
+        let alphas = [0.5, 2.0, 7.5];
+        let mut sums = [0.0; 3];
+        for sample in bitstream
+            .samples(Dirichlet::new(alphas.to_vec()))
+            .take(num_samples)
+        {
+            assert!((sample.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+            for (sum, value) in sums.iter_mut().zip(&sample) {
+                *sum += value;
+            }
+        }
+        for (sum, alpha) in sums.iter().zip(&alphas) {
+            assert!((sum / num_samples as f64 - alpha / 10.0).abs() < 0.005);
+        }
+        let sparse = bitstream.sample(Dirichlet::symmetric(1e-3, 5));
+        assert!(sparse.iter().all(|value| value.is_finite()));
+
+        // The first piece is Beta(1, 3), with mean 1/4; the second has mean
+        // 3/4 * 1/4.
+        let mut sums = [0.0; 4];
+        for sample in bitstream
+            .samples(StickBreaking::new(3.0, 4))
+            .take(num_samples)
+        {
+            assert!((sample.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+            for (sum, value) in sums.iter_mut().zip(&sample) {
+                *sum += value;
+            }
+        }
+        assert!((sums[0] / num_samples as f64 - 0.25).abs() < 0.005);
+        assert!((sums[1] / num_samples as f64 - 0.1875).abs() < 0.005);
+    }
+}
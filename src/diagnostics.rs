@@ -0,0 +1,422 @@
+//! Statistical checks on bitstreams, for sanity-checking a hardware or
+//! third-party source (or a new `Bitstream` impl) before relying on it.
+//! These can catch gross defects; passing them doesn't make a source good.
+
+use crate::{low_bits_mask, Bitstream, RangePlan};
+
+/// Draws `num_bits` bits, 64 at a time.
+fn collect_bits<B: Bitstream + ?Sized>(bitstream: &mut B, num_bits: u64) -> Vec<bool> {
+    let mut bits = Vec::with_capacity(num_bits as usize);
+    while (bits.len() as u64) < num_bits {
+        let chunk = (num_bits - bits.len() as u64).min(64) as u32;
+        let word = bitstream.gen_bits(chunk);
+        bits.extend((0..chunk).map(|index| (word >> index) & 1 == 1));
+    }
+    bits
+}
+
+/// Min-entropy per bit by three of the NIST SP 800-90B estimators, for
+/// binary data. Each is a lower-bound estimate; trust the smallest.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct MinEntropyEstimate {
+    pub most_common_value: f64,
+    pub collision: f64,
+    pub markov: f64,
+}
+
+impl MinEntropyEstimate {
+    pub fn min(&self) -> f64 {
+        self.most_common_value.min(self.collision).min(self.markov)
+    }
+}
+
+/// 99% confidence, as SP 800-90B uses.
+const Z: f64 = 2.576;
+
+/// Section 6.3.1: the upper confidence bound on the frequency of the more
+/// common bit.
+fn most_common_value(bits: &[bool]) -> f64 {
+    let length = bits.len() as f64;
+    let ones = bits.iter().filter(|&&bit| bit).count() as f64;
+    let p = ones.max(length - ones) / length;
+    let upper = (p + Z * (p * (1.0 - p) / (length - 1.0)).sqrt()).min(1.0);
+    -upper.log2()
+}
+
+/// Section 6.3.2: the mean distance to the first repeated value. For bits
+/// that's 2 with probability `p^2 + q^2` and 3 otherwise, so a lower bound
+/// on the mean gives an upper bound on `p`.
+fn collision(bits: &[bool]) -> f64 {
+    let mut distances = Vec::new();
+    let mut index = 0;
+    while index + 2 < bits.len() {
+        let distance = if bits[index] == bits[index + 1] { 2 } else { 3 };
+        distances.push(distance as f64);
+        index += distance;
+    }
+    let count = distances.len() as f64;
+    let mean = distances.iter().sum::<f64>() / count;
+    let variance = distances.iter().map(|d| (d - mean).powi(2)).sum::<f64>() / (count - 1.0);
+    let lower_mean = mean - Z * variance.sqrt() / count.sqrt();
+    // mean = 3 - (p^2 + (1 - p)^2), solved for p >= 1/2.
+    let p = (1.0 + (2.0 * (3.0 - lower_mean) - 1.0).max(0.0).sqrt()) / 2.0;
+    -p.min(1.0).log2()
+}
+
+/// Section 6.3.3: the probability of the likeliest 128-bit sequence under a
+/// first-order Markov model fitted to the bits.
+fn markov(bits: &[bool]) -> f64 {
+    let mut starts = [0.0f64; 2];
+    let mut transitions = [[0.0f64; 2]; 2];
+    for pair in bits.windows(2) {
+        starts[pair[0] as usize] += 1.0;
+        transitions[pair[0] as usize][pair[1] as usize] += 1.0;
+    }
+    let length = bits.len() as f64;
+    let p = |from: usize, to: usize| -> f64 { transitions[from][to] / starts[from].max(1.0) };
+    let ones = bits.iter().filter(|&&bit| bit).count() as f64;
+    let (p0, p1) = ((length - ones) / length, ones / length);
+    let log_probabilities = [
+        p0.log2() + 127.0 * p(0, 0).log2(),
+        p0.log2() + 64.0 * p(0, 1).log2() + 63.0 * p(1, 0).log2(),
+        p0.log2() + p(0, 1).log2() + 126.0 * p(1, 1).log2(),
+        p1.log2() + p(1, 0).log2() + 126.0 * p(0, 0).log2(),
+        p1.log2() + 64.0 * p(1, 0).log2() + 63.0 * p(0, 1).log2(),
+        p1.log2() + 127.0 * p(1, 1).log2(),
+    ];
+    let max = log_probabilities
+        .iter()
+        .copied()
+        .fold(f64::NEG_INFINITY, f64::max);
+    (-max / 128.0).min(1.0)
+}
+
+/// Draws `num_bits` bits and estimates their min-entropy per bit. SP
+/// 800-90B asks for at least a million.
+///
+/// # Panics
+///
+/// If `num_bits < 1000`.
+pub fn min_entropy<B: Bitstream + ?Sized>(bitstream: &mut B, num_bits: u64) -> MinEntropyEstimate {
+    assert!(num_bits >= 1000, "estimates need at least 1000 bits");
+    let bits = collect_bits(bitstream, num_bits);
+    MinEntropyEstimate {
+        most_common_value: most_common_value(&bits),
+        collision: collision(&bits),
+        markov: markov(&bits),
+    }
+}
+
+/// The results of [`bias_report`] for one `gen_bits` width. The chi-square
+/// statistics each have one degree of freedom.
+#[derive(Clone, PartialEq, Debug)]
+pub struct WidthReport {
+    pub num_bits: u32,
+    /// Whether any draw had bits set at or above `num_bits`.
+    pub spilled: bool,
+    /// Ones versus zeros at each bit position.
+    pub frequency: Vec<f64>,
+    /// Agreement between each bit position and the next one up.
+    pub adjacent: Vec<f64>,
+    /// Agreement between each bit position and the same position in the
+    /// next draw.
+    pub serial: Vec<f64>,
+}
+
+impl WidthReport {
+    pub fn max_chi_square(&self) -> f64 {
+        self.frequency
+            .iter()
+            .chain(&self.adjacent)
+            .chain(&self.serial)
+            .copied()
+            .fold(0.0, f64::max)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct BiasReport {
+    pub widths: Vec<WidthReport>,
+}
+
+impl BiasReport {
+    pub fn max_chi_square(&self) -> f64 {
+        self.widths
+            .iter()
+            .map(WidthReport::max_chi_square)
+            .fold(0.0, f64::max)
+    }
+
+    /// No spilled bits and no statistic above 30. A fair source exceeds 30
+    /// with probability about 4e-8 per statistic, so well under 1e-3 over
+    /// the roughly 6000 in a report.
+    pub fn is_plausible(&self) -> bool {
+        self.widths.iter().all(|width| !width.spilled) && self.max_chi_square() < 30.0
+    }
+}
+
+/// `(2 * hits - trials)^2 / trials`: the chi-square statistic for `hits`
+/// against an expected half of `trials`.
+fn chi_square(hits: u64, trials: u64) -> f64 {
+    let difference = 2.0 * hits as f64 - trials as f64;
+    difference * difference / trials as f64
+}
+
+/// Draws `samples_per_width` values at each width from 1 to 64 and tests
+/// each bit position for bias and for correlation with its neighbor and
+/// with itself in the next draw.
+///
+/// # Panics
+///
+/// If `samples_per_width < 2`.
+pub fn bias_report<B: Bitstream + ?Sized>(bitstream: &mut B, samples_per_width: u64) -> BiasReport {
+    assert!(samples_per_width >= 2, "need at least 2 samples per width");
+    let widths = (1..=64)
+        .map(|num_bits| {
+            let width = num_bits as usize;
+            let mut spilled = false;
+            let mut ones = vec![0; width];
+            let mut adjacent_agreements = vec![0; width - 1];
+            let mut serial_agreements = vec![0; width];
+            let mut previous = None;
+            for _ in 0..samples_per_width {
+                let bits = bitstream.gen_bits(num_bits);
+                spilled |= bits & !low_bits_mask(num_bits) != 0;
+                let agreements = !(bits ^ (bits >> 1));
+                let serial = previous.map(|previous: u64| !(bits ^ previous));
+                for index in 0..width {
+                    ones[index] += (bits >> index) & 1;
+                    if index + 1 < width {
+                        adjacent_agreements[index] += (agreements >> index) & 1;
+                    }
+                    if let Some(serial) = serial {
+                        serial_agreements[index] += (serial >> index) & 1;
+                    }
+                }
+                previous = Some(bits);
+            }
+            let chi_squares = |hits: Vec<u64>, trials| {
+                hits.into_iter()
+                    .map(|hits| chi_square(hits, trials))
+                    .collect()
+            };
+            WidthReport {
+                num_bits,
+                spilled,
+                frequency: chi_squares(ones, samples_per_width),
+                adjacent: chi_squares(adjacent_agreements, samples_per_width),
+                serial: chi_squares(serial_agreements, samples_per_width - 1),
+            }
+        })
+        .collect();
+    BiasReport { widths }
+}
+
+/// Checks that the bitstreams made by `new` follow the `Bitstream`
+/// contract, for testing new implementations:
+///
+/// - `gen_bits(num_bits)` never sets bits at or above `num_bits`;
+/// - `gen_bits(0)` is 0 and consumes nothing;
+/// - `gen_bits(64)` can set every bit, and can leave every bit clear;
+/// - `gen_bit()` matches `gen_bits(1)`, and `range_plan` matches
+///   `RangePlan::new`, so both count bits the same way as the defaults;
+/// - [`bias_report`] finds nothing implausible.
+///
+/// `new` must return identically seeded bitstreams each time.
+///
+/// # Panics
+///
+/// With a description of the first check that fails.
+pub fn assert_conformance<B: Bitstream, F: FnMut() -> B>(mut new: F) {
+    let mut bitstream = new();
+    for num_bits in 0..=64 {
+        for _ in 0..100 {
+            let bits = bitstream.gen_bits(num_bits);
+            assert_eq!(
+                bits & !low_bits_mask(num_bits),
+                0,
+                "gen_bits({}) returned {:#x}",
+                num_bits,
+                bits
+            );
+        }
+    }
+
+    let (mut a, mut b) = (new(), new());
+    assert_eq!(a.gen_bits(64), b.gen_bits(64), "`new` isn't deterministic");
+    for _ in 0..10 {
+        assert_eq!(a.gen_bits(0), 0, "gen_bits(0) returned nonzero");
+    }
+    assert_eq!(a.gen_bits(64), b.gen_bits(64), "gen_bits(0) consumed bits");
+
+    let (mut ever_set, mut ever_clear) = (0, 0);
+    for _ in 0..1000 {
+        let bits = bitstream.gen_bits(64);
+        ever_set |= bits;
+        ever_clear |= !bits;
+    }
+    assert_eq!(
+        ever_set,
+        u64::MAX,
+        "some bits of gen_bits(64) are stuck at 0"
+    );
+    assert_eq!(
+        ever_clear,
+        u64::MAX,
+        "some bits of gen_bits(64) are stuck at 1"
+    );
+
+    let (mut a, mut b) = (new(), new());
+    for _ in 0..1000 {
+        assert_eq!(
+            a.gen_bit(),
+            b.gen_bits(1) == 1,
+            "gen_bit differs from gen_bits(1)"
+        );
+    }
+    for size in (1..100).chain((0..64).map(|shift| u64::MAX >> shift)) {
+        assert_eq!(
+            bitstream.range_plan(size),
+            RangePlan::new(size),
+            "range_plan({}) differs from RangePlan::new",
+            size
+        );
+    }
+
+    let report = bias_report(&mut bitstream, 10_000);
+    assert!(
+        report.is_plausible(),
+        "bias report is implausible: {:?}",
+        report
+    );
+}
+
+/// Defines a test named `$name` that runs
+/// [`assert_conformance`](diagnostics::assert_conformance) on the
+/// bitstreams made by `$new`.
+///
+/// ```
+/// # use rand::SeedableRng;
+/// rng_nibbler::bitstream_conformance!(
+///     chacha_conforms,
+///     rng_nibbler::RngBitstream::new(rand_chacha::ChaChaRng::seed_from_u64(0))
+/// );
+/// ```
+#[macro_export]
+macro_rules! bitstream_conformance {
+    ($name:ident, $new:expr) => {
+        #[test]
+        fn $name() {
+            $crate::diagnostics::assert_conformance(|| $new);
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Bernoulli, Distribution, RngBitstream};
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    /// Each bit repeats the last with probability `repeat`.
+    struct Sticky {
+        inner: RngBitstream<ChaChaRng>,
+        repeat: Bernoulli,
+        last: bool,
+    }
+
+    impl Bitstream for Sticky {
+        fn gen_bits(&mut self, num_bits: u32) -> u64 {
+            (0..num_bits).fold(0, |word, index| {
+                if !self.repeat.sample(&mut self.inner) {
+                    self.last = !self.last;
+                }
+                word | (self.last as u64) << index
+            })
+        }
+    }
+
+    #[test]
+    fn estimates_catch_bias_and_correlation() {
+        let mut fair = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let estimate = min_entropy(&mut fair, 1_000_000);
+        assert!(estimate.most_common_value > 0.99, "{:?}", estimate);
+        assert!(estimate.collision > 0.85, "{:?}", estimate);
+        assert!(estimate.markov > 0.99, "{:?}", estimate);
+
+        let mut biased = RngBitstream::new(ChaChaRng::seed_from_u64(1));
+        let coin = Bernoulli::new(0.75);
+        let bits: Vec<bool> = (0..100_000).map(|_| coin.sample(&mut biased)).collect();
+        let expected = -(0.75f64).log2();
+        assert!((most_common_value(&bits) - expected).abs() < 0.02);
+        assert!(collision(&bits) < expected + 0.05);
+
+        // Balanced, so the most-common-value estimate misses the correlation.
+        let mut sticky = Sticky {
+            inner: RngBitstream::new(ChaChaRng::seed_from_u64(2)),
+            repeat: Bernoulli::new(0.9),
+            last: false,
+        };
+        let estimate = min_entropy(&mut sticky, 100_000);
+        assert!(estimate.most_common_value > 0.9, "{:?}", estimate);
+        assert!(
+            (estimate.markov - -(0.9f64).log2()).abs() < 0.02,
+            "{:?}",
+            estimate
+        );
+        assert!(estimate.collision < 0.2, "{:?}", estimate);
+    }
+
+    /// Copies bit 0 into bit 1 whenever there is one.
+    struct Doubled(RngBitstream<ChaChaRng>);
+
+    impl Bitstream for Doubled {
+        fn gen_bits(&mut self, num_bits: u32) -> u64 {
+            let bits = self.0.gen_bits(num_bits);
+            if num_bits >= 2 {
+                (bits & !2) | (bits & 1) << 1
+            } else {
+                bits
+            }
+        }
+    }
+
+    #[test]
+    fn bias_report_flags_flawed_widths() {
+        let mut fair = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let report = bias_report(&mut fair, 10_000);
+        assert_eq!(report.widths.len(), 64);
+        assert_eq!(report.widths[63].adjacent.len(), 63);
+        assert!(report.is_plausible(), "{}", report.max_chi_square());
+
+        let report = bias_report(&mut Doubled(fair), 10_000);
+        assert!(!report.is_plausible());
+        assert!(report.widths[0].max_chi_square() < 30.0);
+        for width in &report.widths[1..] {
+            assert!(width.adjacent[0] > 1000.0);
+            assert!(width.frequency.iter().all(|&chi| chi < 30.0));
+        }
+    }
+
+    crate::bitstream_conformance!(
+        rng_bitstream_conforms,
+        RngBitstream::new(ChaChaRng::seed_from_u64(0))
+    );
+    crate::bitstream_conformance!(
+        split_mix_conforms,
+        crate::SplitMixBitstream::new(crate::SplitMix64::new(0))
+    );
+
+    #[test]
+    #[should_panic(expected = "gen_bits(3) returned")]
+    fn conformance_catches_spilled_bits() {
+        struct Unmasked(RngBitstream<ChaChaRng>);
+        impl Bitstream for Unmasked {
+            fn gen_bits(&mut self, num_bits: u32) -> u64 {
+                self.0.gen_bits(num_bits) | ((num_bits == 3) as u64 * 8)
+            }
+        }
+        assert_conformance(|| Unmasked(RngBitstream::new(ChaChaRng::seed_from_u64(0))));
+    }
+}
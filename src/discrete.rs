@@ -0,0 +1,291 @@
+use crate::dist::open_unit_f64;
+use crate::{Bitstream, Distribution};
+
+/// The number of successes in `n` independent trials that each succeed with
+/// probability `p`.
+///
+/// Small means (below 10) count successes by drawing the gaps between them;
+/// larger ones use Hörmann's BTRS transformed rejection, which needs about 2
+/// uniforms per sample however large `n` is.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Binomial {
+    n: u64,
+    p: f64,
+}
+
+impl Binomial {
+    /// # Panics
+    ///
+    /// If `p` isn't in `[0, 1]`.
+    pub fn new(n: u64, p: f64) -> Self {
+        assert!((0.0..=1.0).contains(&p), "probability must be in [0, 1]");
+        Binomial { n, p }
+    }
+}
+
+impl Distribution<u64> for Binomial {
+    fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> u64 {
+        if self.p > 0.5 {
+            return self.n - Binomial::new(self.n, 1.0 - self.p).sample(bitstream);
+        }
+        if self.p == 0.0 || self.n == 0 {
+            return 0;
+        }
+        if self.n as f64 * self.p < 10.0 {
+            binomial_by_gaps(bitstream, self.n, self.p)
+        } else {
+            binomial_btrs(bitstream, self.n, self.p)
+        }
+    }
+}
+
+/// The number of failures before the first success in independent trials
+/// that each succeed with probability `p`, by inversion. The mean is
+/// `(1 - p) / p`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Geometric {
+    p: f64,
+}
+
+impl Geometric {
+    /// # Panics
+    ///
+    /// If `p` isn't in `(0, 1]`.
+    pub fn new(p: f64) -> Self {
+        assert!(p > 0.0 && p <= 1.0, "probability must be in (0, 1]");
+        Geometric { p }
+    }
+
+    /// The geometric distribution with the given mean.
+    ///
+    /// # Panics
+    ///
+    /// If `mean` is negative or not finite.
+    pub fn with_mean(mean: f64) -> Self {
+        assert!(
+            mean >= 0.0 && mean.is_finite(),
+            "mean must be nonnegative and finite"
+        );
+        Geometric::new(1.0 / (1.0 + mean))
+    }
+}
+
+impl Distribution<u64> for Geometric {
+    fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> u64 {
+        if self.p == 1.0 {
+            return 0;
+        }
+        // Saturates for astronomically unlikely values.
+        (open_unit_f64(bitstream).ln() / (-self.p).ln_1p()).floor() as u64
+    }
+}
+
+fn binomial_by_gaps<B: Bitstream + ?Sized>(bitstream: &mut B, n: u64, p: f64) -> u64 {
+    // The gap to the next success is geometric.
+    let log_q = (-p).ln_1p();
+    let mut trials: u64 = 0;
+    let mut successes = 0;
+    loop {
+        let gap = (open_unit_f64(bitstream).ln() / log_q).ceil();
+        if gap > (n - trials) as f64 {
+            return successes;
+        }
+        trials += gap as u64;
+        successes += 1;
+    }
+}
+
+/// `ln(k!) - (ln(sqrt(2 pi)) + (k + 1/2) ln(k + 1) - (k + 1))`, the error of
+/// Stirling's approximation.
+fn stirling_tail(k: f64) -> f64 {
+    const SMALL: [f64; 10] = [
+        0.081_061_466_795_327_26,
+        0.041_340_695_955_409_29,
+        0.027_677_925_684_998_34,
+        0.020_790_672_103_765_09,
+        0.016_644_691_189_821_19,
+        0.013_876_128_823_070_75,
+        0.011_896_709_945_891_77,
+        0.010_411_265_261_972_09,
+        0.009_255_462_182_712_733,
+        0.008_330_563_433_362_87,
+    ];
+    if k <= 9.0 {
+        return SMALL[k as usize];
+    }
+    let k1_squared = (k + 1.0) * (k + 1.0);
+    (1.0 / 12.0 - (1.0 / 360.0 - 1.0 / 1260.0 / k1_squared) / k1_squared) / (k + 1.0)
+}
+
+fn binomial_btrs<B: Bitstream + ?Sized>(bitstream: &mut B, n: u64, p: f64) -> u64 {
+    let n_f = n as f64;
+    let q = 1.0 - p;
+    let spq = (n_f * p * q).sqrt();
+    let b = 1.15 + 2.53 * spq;
+    let a = -0.0873 + 0.0248 * b + 0.01 * p;
+    let c = n_f * p + 0.5;
+    let v_r = 0.92 - 4.2 / b;
+    let r = p / q;
+    let alpha = (2.83 + 5.1 / b) * spq;
+    let m = ((n_f + 1.0) * p).floor();
+    loop {
+        let u = open_unit_f64(bitstream) - 0.5;
+        let v = open_unit_f64(bitstream);
+        let us = 0.5 - u.abs();
+        let k = ((2.0 * a / us + b) * u + c).floor();
+        if k < 0.0 || k > n_f {
+            continue;
+        }
+        if us >= 0.07 && v <= v_r {
+            return k as u64;
+        }
+        let v = (v * alpha / (a / (us * us) + b)).ln();
+        let bound = (m + 0.5) * ((m + 1.0) / (r * (n_f - m + 1.0))).ln()
+            + (n_f + 1.0) * ((n_f - m + 1.0) / (n_f - k + 1.0)).ln()
+            + (k + 0.5) * (r * (n_f - k + 1.0) / (k + 1.0)).ln()
+            + stirling_tail(m)
+            + stirling_tail(n_f - m)
+            - stirling_tail(k)
+            - stirling_tail(n_f - k);
+        if v <= bound {
+            return k as u64;
+        }
+    }
+}
+
+/// Splits `num_trials` trials among categories with probabilities
+/// proportional to `weights`, returning the count for each category. Each
+/// count is one binomial draw conditioned on the counts before it, so this
+/// takes a few uniforms per category rather than one draw per trial.
+///
+/// # Panics
+///
+/// If any weight is negative or not finite, or they are all 0.
+pub fn multinomial<B: Bitstream + ?Sized>(
+    bitstream: &mut B,
+    num_trials: u64,
+    weights: &[f64],
+) -> Vec<u64> {
+    assert!(
+        weights
+            .iter()
+            .all(|weight| weight.is_finite() && *weight >= 0.0),
+        "weights must be finite and nonnegative"
+    );
+    let mut remaining_weight: f64 = weights.iter().sum();
+    assert!(remaining_weight > 0.0, "total weight must be positive");
+    // Rounding in `remaining_weight` can leave the last category with a
+    // probability just under 1, so it takes whatever is left outright.
+    let last = weights.iter().rposition(|&weight| weight > 0.0);
+    let mut remaining_trials = num_trials;
+    let mut counts = Vec::with_capacity(weights.len());
+    for (index, &weight) in weights.iter().enumerate() {
+        let count = if remaining_trials == 0 || weight == 0.0 {
+            0
+        } else if Some(index) == last || weight >= remaining_weight {
+            remaining_trials
+        } else {
+            Binomial::new(remaining_trials, weight / remaining_weight).sample(bitstream)
+        };
+        counts.push(count);
+        remaining_trials -= count;
+        remaining_weight -= weight;
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{multinomial, Binomial, BitstreamExt, Geometric, RngBitstream};
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn binomial_and_multinomial_have_the_right_moments() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let num_samples = 50000;
+        for &(n, p) in &[
+            (10, 0.3),
+            (1000, 0.003),
+            (1000, 0.4),
+            (1 << 40, 0.9),
+            (50, 1.0),
+        ] {
+            let samples: Vec<f64> = bitstream
+                .samples(Binomial::new(n, p))
+                .take(num_samples)
+                .map(|k| k as f64)
+                .collect();
+            assert!(samples.iter().all(|&k| k <= n as f64));
+            let mean = samples.iter().sum::<f64>() / num_samples as f64;
+            let variance =
+                samples.iter().map(|k| (k - mean) * (k - mean)).sum::<f64>() / num_samples as f64;
+            let expected_variance = n as f64 * p * (1.0 - p);
+            let tolerance = 4.0 * (expected_variance / num_samples as f64).sqrt() + 1e-9;
+            assert!(
+                (mean - n as f64 * p).abs() < tolerance,
+                "{} {} {}",
+                n,
+                p,
+                mean
+            );
+            assert!(
+                (variance - expected_variance).abs() < 0.05 * expected_variance + 1e-9,
+                "{} {} {}",
+                n,
+                p,
+                variance
+            );
+        }
+
+        // Exact probabilities for a case that goes through BTRS.
+        let mut counts = [0; 41];
+        for k in bitstream.samples(Binomial::new(40, 0.5)).take(200000) {
+            counts[k as usize] += 1;
+        }
+        let mut probability = 0.5f64.powi(40);
+        for (k, &count) in counts.iter().enumerate() {
+            let expected = 200000.0 * probability;
+            assert!(
+                (count as f64 - expected).abs() < 5.0 * expected.sqrt() + 1.0,
+                "{} came up {} times, not {}",
+                k,
+                count,
+                expected
+            );
+            probability *= (40 - k) as f64 / (k + 1) as f64;
+        }
+
+        let mut totals = [0; 4];
+        for _ in 0..10000 {
+            let counts = multinomial(&mut bitstream, 100, &[1.0, 0.0, 3.0, 6.0]);
+            assert_eq!(counts.iter().sum::<u64>(), 100);
+            for (total, count) in totals.iter_mut().zip(&counts) {
+                *total += count;
+            }
+        }
+        assert_eq!(totals[1], 0);
+        assert!((totals[0] as f64 / 1e6 - 0.1).abs() < 0.002);
+        assert!((totals[3] as f64 / 1e6 - 0.6).abs() < 0.002);
+        for _ in 0..1000 {
+            let counts = multinomial(&mut bitstream, 1_000_000_000_000_000, &[0.1, 0.2, 0.3]);
+            assert_eq!(counts.iter().sum::<u64>(), 1_000_000_000_000_000);
+        }
+    }
+
+    #[test]
+    fn geometric_has_the_right_mean() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let num_samples = 100000;
+        for &mean in &[0.0, 0.5, 3.0, 100.0] {
+            let geometric = Geometric::with_mean(mean);
+            let total: u64 = (0..num_samples).map(|_| bitstream.sample(geometric)).sum();
+            let sample_mean = total as f64 / num_samples as f64;
+            assert!(
+                (sample_mean - mean).abs() <= 0.02 * (mean + 1.0),
+                "{}",
+                sample_mean
+            );
+        }
+    }
+}
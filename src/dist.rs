@@ -0,0 +1,167 @@
+use crate::Bitstream;
+use std::marker::PhantomData;
+
+/// Something that can produce values of type `T` from a bitstream.
+pub trait Distribution<T> {
+    fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> T;
+}
+
+impl<T, D: Distribution<T> + ?Sized> Distribution<T> for &D {
+    fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> T {
+        (**self).sample(bitstream)
+    }
+}
+
+/// Always the same value, e.g. a fixed length for `sample_vec`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct Constant<T>(pub T);
+
+impl<T: Clone> Distribution<T> for Constant<T> {
+    fn sample<B: Bitstream + ?Sized>(&self, _bitstream: &mut B) -> T {
+        self.0.clone()
+    }
+}
+
+/// Samples each distribution in turn, first to last.
+impl<T, D: Distribution<T>, const N: usize> Distribution<[T; N]> for [D; N] {
+    fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> [T; N] {
+        std::array::from_fn(|index| self[index].sample(bitstream))
+    }
+}
+
+macro_rules! tuple_distribution {
+    ($(($T:ident, $D:ident, $index:tt)),+) => {
+        /// Samples each distribution in turn, first to last.
+        impl<$($T, $D: Distribution<$T>),+> Distribution<($($T,)+)> for ($($D,)+) {
+            fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> ($($T,)+) {
+                ($(self.$index.sample(bitstream),)+)
+            }
+        }
+    };
+}
+
+tuple_distribution!((T0, D0, 0));
+tuple_distribution!((T0, D0, 0), (T1, D1, 1));
+tuple_distribution!((T0, D0, 0), (T1, D1, 1), (T2, D2, 2));
+tuple_distribution!((T0, D0, 0), (T1, D1, 1), (T2, D2, 2), (T3, D3, 3));
+tuple_distribution!(
+    (T0, D0, 0),
+    (T1, D1, 1),
+    (T2, D2, 2),
+    (T3, D3, 3),
+    (T4, D4, 4)
+);
+tuple_distribution!(
+    (T0, D0, 0),
+    (T1, D1, 1),
+    (T2, D2, 2),
+    (T3, D3, 3),
+    (T4, D4, 4),
+    (T5, D5, 5)
+);
+tuple_distribution!(
+    (T0, D0, 0),
+    (T1, D1, 1),
+    (T2, D2, 2),
+    (T3, D3, 3),
+    (T4, D4, 4),
+    (T5, D5, 5),
+    (T6, D6, 6)
+);
+tuple_distribution!(
+    (T0, D0, 0),
+    (T1, D1, 1),
+    (T2, D2, 2),
+    (T3, D3, 3),
+    (T4, D4, 4),
+    (T5, D5, 5),
+    (T6, D6, 6),
+    (T7, D7, 7)
+);
+
+/// A uniform `f64` in the open interval `(0, 1)`, from 52 bits: the midpoint
+/// of one of 2^52 equal subintervals.
+pub(crate) fn open_unit_f64<B: Bitstream + ?Sized>(bitstream: &mut B) -> f64 {
+    (bitstream.gen_bits(52) as f64 + 0.5) / (1u64 << 52) as f64
+}
+
+/// `inverse_cdf`, which must be nondecreasing on `[0, 1]`, evaluated at a
+/// uniform whose bits are drawn one at a time only until every value still
+/// possible maps to the same `f64`. The result is what inverting an
+/// infinitely precise uniform gives, up to the rounding inside `inverse_cdf`,
+/// and costs only as many bits as the output's precision needs. Past 64 bits
+/// it settles for the midpoint of what's left.
+pub(crate) fn invert_lazy_uniform<B, F>(bitstream: &mut B, inverse_cdf: F) -> f64
+where
+    B: Bitstream + ?Sized,
+    F: Fn(f64) -> f64,
+{
+    let mut numerator = 0u64;
+    for num_bits in 1..=64 {
+        numerator = (numerator << 1) | bitstream.gen_bit() as u64;
+        let scale = 0.5f64.powi(num_bits);
+        let low = inverse_cdf(numerator as f64 * scale);
+        if low == inverse_cdf((numerator as f64 + 1.0) * scale) {
+            return low;
+        }
+    }
+    inverse_cdf((numerator as f64 + 0.5) * 0.5f64.powi(64))
+}
+
+pub struct Samples<'a, B: ?Sized, D, T> {
+    pub(crate) bitstream: &'a mut B,
+    pub(crate) distribution: D,
+    pub(crate) _marker: PhantomData<fn() -> T>,
+}
+
+impl<'a, B: Bitstream + ?Sized, D: Distribution<T>, T> Iterator for Samples<'a, B, D, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        Some(self.distribution.sample(self.bitstream))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::invert_lazy_uniform;
+    use crate::{
+        Bernoulli, BitstreamExt, CountingRngBitstream, Distribution, RngBitstream, UniformRange,
+    };
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn lazy_inversion_draws_only_the_precision_needed() {
+        // Spacing between f64s is 2^-52 in [1, 2) and 2^-33 in [2^19, 2^20),
+        // so pinning down 1 + u takes more bits than 2^19 + u.
+        for &(offset, max_bits) in &[(1.0, 55), (524288.0, 36)] {
+            let mut bitstream = CountingRngBitstream::new(ChaChaRng::seed_from_u64(0));
+            for _ in 0..100 {
+                let x = invert_lazy_uniform(&mut bitstream, |u| offset + u);
+                assert!(x >= offset && x <= offset + 1.0);
+            }
+            assert!(bitstream.count() <= 100 * max_bits);
+        }
+    }
+
+    #[test]
+    fn tuples_and_arrays_sample_in_order() {
+        let mut combined = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let mut separate = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let (size, coin) = (UniformRange::new(10), Bernoulli::new(0.3));
+        for _ in 0..100 {
+            let (a, b, c) = (&size, &coin, UniformRange::new(1000)).sample(&mut combined);
+            assert_eq!(a, size.sample(&mut separate));
+            assert_eq!(b, coin.sample(&mut separate));
+            assert_eq!(c, separate.gen_range(1000));
+
+            let values = [UniformRange::new(3), UniformRange::new(5)].sample(&mut combined);
+            assert_eq!(values, [separate.gen_range(3), separate.gen_range(5)]);
+        }
+    }
+}
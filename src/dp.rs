@@ -0,0 +1,230 @@
+//! Differential privacy mechanisms that sample exactly.
+//!
+//! Mechanisms built on floating-point noise leak information through the
+//! gaps and rounding of floats (Mironov, "On Significance of the Least
+//! Significant Bits for Differential Privacy"). These instead take the
+//! privacy parameter as a rational number and only ever compare integers, so
+//! every output has exactly the probability the mechanism promises, following
+//! Canonne, Kamath and Steinke, "The Discrete Gaussian for Differential
+//! Privacy".
+
+use crate::{Bitstream, BitstreamExt};
+use std::convert::TryFrom;
+
+/// A privacy parameter `epsilon = numerator / denominator`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Epsilon {
+    numerator: u64,
+    denominator: u64,
+}
+
+impl Epsilon {
+    /// # Panics
+    ///
+    /// If either argument is 0.
+    pub fn new(numerator: u64, denominator: u64) -> Self {
+        assert!(numerator > 0, "epsilon must be positive");
+        assert!(denominator > 0, "epsilon's denominator must be nonzero");
+        Epsilon {
+            numerator,
+            denominator,
+        }
+    }
+}
+
+/// `true` with probability `numerator / denominator`.
+fn bernoulli_ratio<B: Bitstream + ?Sized>(
+    bitstream: &mut B,
+    numerator: u64,
+    denominator: u64,
+) -> bool {
+    bitstream.gen_range(denominator) < numerator
+}
+
+/// `true` with probability `exp(-numerator / denominator)`.
+fn bernoulli_exp_neg<B: Bitstream + ?Sized>(
+    bitstream: &mut B,
+    numerator: u128,
+    denominator: u64,
+) -> bool {
+    let mut whole = numerator / denominator as u128;
+    while whole > 0 {
+        if !bernoulli_exp_neg_at_most_1(bitstream, 1, 1) {
+            return false;
+        }
+        whole -= 1;
+    }
+    let fraction = (numerator % denominator as u128) as u64;
+    bernoulli_exp_neg_at_most_1(bitstream, fraction, denominator)
+}
+
+/// `bernoulli_exp_neg` for `numerator <= denominator`: the largest `k` such
+/// that `k - 1` coins with probabilities `gamma, gamma/2, ..., gamma/(k-1)`
+/// all come up `true` is odd with probability `exp(-gamma)`.
+fn bernoulli_exp_neg_at_most_1<B: Bitstream + ?Sized>(
+    bitstream: &mut B,
+    numerator: u64,
+    denominator: u64,
+) -> bool {
+    let mut k = 1;
+    while bernoulli_ratio(bitstream, numerator, denominator) && bernoulli_ratio(bitstream, 1, k) {
+        k += 1;
+    }
+    k % 2 == 1
+}
+
+/// k-ary randomized response: reports `value` with probability
+/// `e^epsilon / (e^epsilon + num_values - 1)`, and otherwise one of the other
+/// values in `0..num_values`, uniformly.
+///
+/// # Panics
+///
+/// If `value >= num_values`.
+pub fn randomized_response<B: Bitstream + ?Sized>(
+    bitstream: &mut B,
+    value: u64,
+    num_values: u64,
+    epsilon: Epsilon,
+) -> u64 {
+    assert!(value < num_values, "value must be less than num_values");
+    // Propose uniformly; keep the truth always and any lie with probability
+    // e^-epsilon.
+    loop {
+        let proposal = bitstream.gen_range(num_values);
+        if proposal == value
+            || bernoulli_exp_neg(bitstream, epsilon.numerator as u128, epsilon.denominator)
+        {
+            return proposal;
+        }
+    }
+}
+
+/// Adds two-sided geometric noise to `value`: noise `y` has probability
+/// proportional to `exp(-epsilon * |y| / sensitivity)`. The sum saturates at
+/// the bounds of `i64`.
+///
+/// # Panics
+///
+/// If `sensitivity` is 0, or `epsilon`'s denominator times `sensitivity`
+/// overflows a `u64`.
+pub fn geometric_mechanism<B: Bitstream + ?Sized>(
+    bitstream: &mut B,
+    value: i64,
+    sensitivity: u64,
+    epsilon: Epsilon,
+) -> i64 {
+    assert!(sensitivity > 0, "sensitivity must be positive");
+    // The noise scale is t/s.
+    let s = epsilon.numerator;
+    let t = epsilon
+        .denominator
+        .checked_mul(sensitivity)
+        .expect("epsilon's denominator times sensitivity overflowed");
+    loop {
+        let u = bitstream.gen_range(t);
+        if !bernoulli_exp_neg(bitstream, u as u128, t) {
+            continue;
+        }
+        let mut v: u128 = 0;
+        while bernoulli_exp_neg(bitstream, 1, 1) {
+            v += 1;
+        }
+        let magnitude = (u as u128 + t as u128 * v) / s as u128;
+        let negative = bitstream.gen_bit();
+        if negative && magnitude == 0 {
+            continue;
+        }
+        let magnitude = i64::try_from(magnitude).unwrap_or(i64::MAX);
+        return if negative {
+            value.saturating_sub(magnitude)
+        } else {
+            value.saturating_add(magnitude)
+        };
+    }
+}
+
+/// Picks an index with probability proportional to
+/// `exp(epsilon * utilities[index] / (2 * sensitivity))`.
+///
+/// # Panics
+///
+/// If `utilities` is empty, `sensitivity` is 0, or `epsilon`'s denominator
+/// times `2 * sensitivity` overflows a `u64`.
+pub fn exponential_mechanism<B: Bitstream + ?Sized>(
+    bitstream: &mut B,
+    utilities: &[i64],
+    sensitivity: u64,
+    epsilon: Epsilon,
+) -> usize {
+    assert!(sensitivity > 0, "sensitivity must be positive");
+    let max = *utilities.iter().max().expect("no choices to pick from");
+    let denominator = epsilon
+        .denominator
+        .checked_mul(sensitivity)
+        .and_then(|product| product.checked_mul(2))
+        .expect("epsilon's denominator times 2 * sensitivity overflowed");
+    // Propose uniformly, and accept with probability
+    // exp(-epsilon * (max - utility) / (2 * sensitivity)); the best choice is
+    // always accepted.
+    loop {
+        let index = bitstream.gen_range(utilities.len() as u64) as usize;
+        let shortfall = max.wrapping_sub(utilities[index]) as u64;
+        let numerator = epsilon.numerator as u128 * shortfall as u128;
+        if bernoulli_exp_neg(bitstream, numerator, denominator) {
+            return index;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RngBitstream;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    fn assert_close(frequency: f64, expected: f64) {
+        assert!(
+            (frequency - expected).abs() < 0.01,
+            "{} vs {}",
+            frequency,
+            expected
+        );
+    }
+
+    #[test]
+    fn mechanisms_have_the_right_distributions() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let epsilon = Epsilon::new(1, 2);
+        let q = (-0.5f64).exp();
+        let num_trials = 100000;
+
+        let truthful = (0..num_trials)
+            .filter(|_| randomized_response(&mut bitstream, 2, 4, epsilon) == 2)
+            .count();
+        assert_close(truthful as f64 / num_trials as f64, 1.0 / (1.0 + 3.0 * q));
+
+        let mut counts = [0; 5];
+        for _ in 0..num_trials {
+            let noisy = geometric_mechanism(&mut bitstream, 100, 1, epsilon);
+            if (98..=102).contains(&noisy) {
+                counts[(noisy - 98) as usize] += 1;
+            }
+        }
+        for (count, distance) in counts.iter().zip(&[2, 1, 0, 1, 2]) {
+            let expected = (1.0 - q) / (1.0 + q) * q.powi(*distance);
+            assert_close(*count as f64 / num_trials as f64, expected);
+        }
+
+        let utilities = [0, 4, 2];
+        let mut counts = [0; 3];
+        for _ in 0..num_trials {
+            counts[exponential_mechanism(&mut bitstream, &utilities, 1, epsilon)] += 1;
+        }
+        let weights: Vec<f64> = utilities.iter().map(|&u| (u as f64 / 4.0).exp()).collect();
+        let total: f64 = weights.iter().sum();
+        for (count, weight) in counts.iter().zip(&weights) {
+            assert_close(*count as f64 / num_trials as f64, weight / total);
+        }
+    }
+}
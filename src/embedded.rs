@@ -0,0 +1,108 @@
+use crate::error::check_num_bits;
+use crate::{Bitstream, BitstreamExt, BitstreamReader, Error, TryBitstream};
+use embedded_hal::blocking::rng;
+use std::convert::Infallible;
+use std::fmt::Debug;
+use std::io;
+
+/// A bitstream over an embedded-hal RNG peripheral. Reads only as many bytes
+/// as each draw needs, since every byte from a hardware TRNG costs time.
+/// Peripheral errors become `Error::Io`, or a panic through the `Bitstream` impl.
+pub struct HalRngBitstream<R> {
+    rng: R,
+    bit_buffer: u128,
+    unused_bits: u32,
+}
+
+impl<R> HalRngBitstream<R> {
+    pub fn new(rng: R) -> Self {
+        HalRngBitstream {
+            rng,
+            bit_buffer: 0,
+            unused_bits: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.rng
+    }
+}
+
+impl<R: rng::Read> TryBitstream for HalRngBitstream<R>
+where
+    R::Error: Debug,
+{
+    fn try_gen_bits(&mut self, num_bits: u32) -> Result<u64, Error> {
+        check_num_bits(num_bits)?;
+        if num_bits > self.unused_bits {
+            let num_bytes = (num_bits - self.unused_bits).div_ceil(8) as usize;
+            let mut bytes = [0; 8];
+            self.rng.read(&mut bytes[..num_bytes]).map_err(|error| {
+                io::Error::other(format!("hardware RNG read failed: {:?}", error))
+            })?;
+            self.bit_buffer |= (u64::from_le_bytes(bytes) as u128) << self.unused_bits;
+            self.unused_bits += num_bytes as u32 * 8;
+        }
+        let result = (self.bit_buffer & ((1 << num_bits) - 1)) as u64;
+        self.bit_buffer >>= num_bits;
+        self.unused_bits -= num_bits;
+        Ok(result)
+    }
+}
+
+impl<R: rng::Read> Bitstream for HalRngBitstream<R>
+where
+    R::Error: Debug,
+{
+    fn gen_bits(&mut self, num_bits: u32) -> u64 {
+        self.try_gen_bits(num_bits)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+}
+
+/// Lets any bitstream stand in for an embedded-hal RNG peripheral.
+impl<B: Bitstream> rng::Read for BitstreamReader<B> {
+    type Error = Infallible;
+
+    fn read(&mut self, buffer: &mut [u8]) -> Result<(), Infallible> {
+        self.get_mut().fill_bytes(buffer);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Bitstream, BitstreamReader, HalRngBitstream, RngBitstream};
+    use embedded_hal::blocking::rng;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    struct CountingPeripheral<R> {
+        inner: R,
+        bytes_read: usize,
+    }
+
+    impl<R: rng::Read> rng::Read for CountingPeripheral<R> {
+        type Error = R::Error;
+
+        fn read(&mut self, buffer: &mut [u8]) -> Result<(), R::Error> {
+            self.bytes_read += buffer.len();
+            self.inner.read(buffer)
+        }
+    }
+
+    #[test]
+    fn hal_round_trip_preserves_the_stream_and_reads_minimal_bytes() {
+        let mut direct = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let mut hal = HalRngBitstream::new(CountingPeripheral {
+            inner: BitstreamReader::new(RngBitstream::new(ChaChaRng::seed_from_u64(0))),
+            bytes_read: 0,
+        });
+        let mut total_bits = 0;
+        for num_bits in (0..=64).chain((0..=64).rev()) {
+            assert_eq!(hal.gen_bits(num_bits), direct.gen_bits(num_bits));
+            total_bits += num_bits as usize;
+        }
+        assert_eq!(hal.into_inner().bytes_read, total_bits.div_ceil(8));
+    }
+}
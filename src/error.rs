@@ -0,0 +1,99 @@
+use std::fmt;
+use std::io;
+
+/// Everything that can go wrong in the crate's fallible APIs.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// A finite bit source ran out.
+    SourceExhausted { requested: u64, available: u64 },
+    /// Reading from an underlying device or file failed.
+    Io(io::Error),
+    /// An argument was out of range, e.g. a range size of 0.
+    InvalidParameter(&'static str),
+    /// An operation would have used more bits than it was allowed.
+    BudgetExceeded { budget: u64, requested: u64 },
+    /// A replayed tape didn't match the draws being asked for.
+    ReplayDivergence {
+        position: usize,
+        recorded_bits: u32,
+        requested_bits: u32,
+    },
+    /// A revealed seed doesn't hash to the published commitment.
+    CommitmentMismatch,
+    /// A transcript entry isn't what the revealed seed produces.
+    TranscriptMismatch { position: usize },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::SourceExhausted {
+                requested,
+                available,
+            } => write!(
+                f,
+                "bit source exhausted: asked for {} bits, {} remain",
+                requested, available
+            ),
+            Error::Io(error) => write!(f, "I/O error: {}", error),
+            Error::InvalidParameter(message) => write!(f, "invalid parameter: {}", message),
+            Error::BudgetExceeded { budget, requested } => write!(
+                f,
+                "bit budget exceeded: asked for {} bits with {} left",
+                requested, budget
+            ),
+            Error::ReplayDivergence {
+                position,
+                recorded_bits,
+                requested_bits,
+            } => write!(
+                f,
+                "replay diverged at draw {}: recorded {} bits, asked for {}",
+                position, recorded_bits, requested_bits
+            ),
+            Error::CommitmentMismatch => write!(f, "seed doesn't match the commitment"),
+            Error::TranscriptMismatch { position } => {
+                write!(f, "transcript doesn't match the seed at draw {}", position)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+pub(crate) fn check_num_bits(num_bits: u32) -> Result<(), Error> {
+    if num_bits > 64 {
+        return Err(Error::InvalidParameter("num_bits must be at most 64"));
+    }
+    Ok(())
+}
+
+/// `check_num_bits` for infallible `gen_bits` implementations.
+pub(crate) fn assert_num_bits(num_bits: u32) {
+    assert!(
+        num_bits <= 64,
+        "num_bits must be at most 64, got {}",
+        num_bits
+    );
+}
+
+pub(crate) fn check_size(size: u64) -> Result<(), Error> {
+    if size == 0 {
+        return Err(Error::InvalidParameter("range size must be nonzero"));
+    }
+    Ok(())
+}
@@ -0,0 +1,146 @@
+//! Provably fair draws by commit-reveal.
+//!
+//! The drawing party picks a secret 32-byte seed and publishes its
+//! `Commitment` before any draws. Each draw is `gen_range(size)` on
+//! `RngBitstream::new(ChaCha20Rng::from_seed(seed))`, logged with a label in
+//! a `Transcript`. Afterwards the seed is revealed, and anyone can check with
+//! `verify` that it matches the commitment and reproduces every draw.
+//!
+//! The commitment is SHA-256 of `COMMITMENT_PREFIX` followed by the seed.
+//! ChaCha20's output and the `gen_range` algorithm are both fixed, so a
+//! verifier can also re-derive the draws independently of this crate.
+
+use crate::{BitstreamExt, DefaultRng, Error, RngBitstream};
+use rand::{Rng, SeedableRng};
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+pub const COMMITMENT_PREFIX: &[u8] = b"rng-nibbler fairness commitment v1\0";
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Commitment(pub [u8; 32]);
+
+impl Commitment {
+    pub fn of(seed: &[u8; 32]) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(COMMITMENT_PREFIX);
+        hasher.update(seed);
+        Commitment(hasher.finalize().into())
+    }
+}
+
+/// Formats as lowercase hex.
+impl fmt::Display for Commitment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct TranscriptEntry {
+    pub label: String,
+    pub size: u64,
+    pub value: u64,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Transcript {
+    pub entries: Vec<TranscriptEntry>,
+}
+
+/// The drawing party's side: holds the secret seed and logs each draw.
+pub struct FairDraw {
+    seed: [u8; 32],
+    bitstream: RngBitstream<DefaultRng>,
+    transcript: Transcript,
+}
+
+impl FairDraw {
+    pub fn new(seed: [u8; 32]) -> Self {
+        FairDraw {
+            seed,
+            bitstream: RngBitstream::new(DefaultRng::from_seed(seed)),
+            transcript: Transcript::default(),
+        }
+    }
+
+    /// A draw with a seed from OS entropy.
+    pub fn from_entropy() -> Self {
+        FairDraw::new(rand::rngs::OsRng.gen())
+    }
+
+    /// The value to publish before drawing.
+    pub fn commitment(&self) -> Commitment {
+        Commitment::of(&self.seed)
+    }
+
+    /// A uniform value in `0..size`, logged under `label`.
+    ///
+    /// # Panics
+    ///
+    /// If `size` is 0.
+    pub fn draw(&mut self, label: &str, size: u64) -> u64 {
+        assert!(size > 0, "range size must be nonzero");
+        let value = self.bitstream.gen_range(size);
+        self.transcript.entries.push(TranscriptEntry {
+            label: label.to_owned(),
+            size,
+            value,
+        });
+        value
+    }
+
+    pub fn transcript(&self) -> &Transcript {
+        &self.transcript
+    }
+
+    /// Ends the drawing, returning the seed to reveal and the transcript.
+    pub fn reveal(self) -> ([u8; 32], Transcript) {
+        (self.seed, self.transcript)
+    }
+}
+
+/// Checks that `seed` matches `commitment` and that replaying the draws in
+/// `transcript` with it gives the recorded values.
+pub fn verify(
+    commitment: &Commitment,
+    seed: &[u8; 32],
+    transcript: &Transcript,
+) -> Result<(), Error> {
+    if Commitment::of(seed) != *commitment {
+        return Err(Error::CommitmentMismatch);
+    }
+    let mut bitstream = RngBitstream::new(DefaultRng::from_seed(*seed));
+    for (position, entry) in transcript.entries.iter().enumerate() {
+        if entry.size == 0 || bitstream.gen_range(entry.size) != entry.value {
+            return Err(Error::TranscriptMismatch { position });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn honest_draws_verify_and_tampering_is_caught() {
+        let mut draw = FairDraw::new([7; 32]);
+        let commitment = draw.commitment();
+        assert_eq!(commitment.to_string().len(), 64);
+        let winner = draw.draw("winner", 1000);
+        draw.draw("runner-up", 999);
+        let (seed, mut transcript) = draw.reveal();
+        assert_eq!(transcript.entries[0].value, winner);
+        assert!(verify(&commitment, &seed, &transcript).is_ok());
+        assert!(matches!(
+            verify(&commitment, &[8; 32], &transcript),
+            Err(Error::CommitmentMismatch)
+        ));
+        transcript.entries[1].value = (transcript.entries[1].value + 1) % 999;
+        assert!(matches!(
+            verify(&commitment, &seed, &transcript),
+            Err(Error::TranscriptMismatch { position: 1 })
+        ));
+    }
+}
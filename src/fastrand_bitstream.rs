@@ -0,0 +1,121 @@
+use crate::error::assert_num_bits;
+use crate::source::{BitOrder, LsbFirst};
+use crate::Bitstream;
+use std::cell::Cell;
+
+/// Takes `num_bits` bits from a buffer of `unused_bits` bits, refilling it
+/// with a word from `next_word` if needed, as `RngBitstream` does.
+fn take_bits(
+    bit_buffer: &mut u64,
+    unused_bits: &mut u32,
+    num_bits: u32,
+    next_word: impl FnOnce() -> u64,
+) -> u64 {
+    if num_bits <= *unused_bits {
+        *unused_bits -= num_bits;
+        LsbFirst::take(bit_buffer, num_bits)
+    } else {
+        assert_num_bits(num_bits);
+        let result = LsbFirst::take_refilled(bit_buffer, *unused_bits, next_word(), num_bits);
+        *unused_bits += 64 - num_bits;
+        result
+    }
+}
+
+/// A bitstream over a `fastrand::Rng`, taking bits LSB-first from its
+/// 64-bit outputs like `RngBitstream` does.
+#[derive(Clone, Debug)]
+pub struct FastrandBitstream {
+    rng: fastrand::Rng,
+    bit_buffer: u64,
+    unused_bits: u32,
+}
+
+impl FastrandBitstream {
+    pub fn new(rng: fastrand::Rng) -> Self {
+        FastrandBitstream {
+            rng,
+            bit_buffer: 0,
+            unused_bits: 0,
+        }
+    }
+
+    pub fn seeded(seed: u64) -> Self {
+        FastrandBitstream::new(fastrand::Rng::with_seed(seed))
+    }
+
+    pub fn into_inner(self) -> fastrand::Rng {
+        self.rng
+    }
+}
+
+impl Bitstream for FastrandBitstream {
+    fn gen_bits(&mut self, num_bits: u32) -> u64 {
+        let rng = &mut self.rng;
+        take_bits(
+            &mut self.bit_buffer,
+            &mut self.unused_bits,
+            num_bits,
+            || rng.u64(..),
+        )
+    }
+}
+
+thread_local! {
+    static BUFFER: Cell<(u64, u32)> = const { Cell::new((0, 0)) };
+}
+
+/// A handle to fastrand's thread-local generator, with this thread's
+/// leftover bits kept between draws. Those bits survive `fastrand::seed`, so
+/// reseed through `FastrandThreadBitstream::seed` for reproducible output.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FastrandThreadBitstream {
+    // Not Send: the handle only makes sense on the thread that created it.
+    _not_send: std::marker::PhantomData<*const ()>,
+}
+
+impl FastrandThreadBitstream {
+    /// Seeds fastrand's thread-local generator and drops this thread's
+    /// leftover bits, so the next draw starts from the new seed's first word.
+    pub fn seed(seed: u64) {
+        fastrand::seed(seed);
+        BUFFER.with(|buffer| buffer.set((0, 0)));
+    }
+}
+
+impl Bitstream for FastrandThreadBitstream {
+    fn gen_bits(&mut self, num_bits: u32) -> u64 {
+        BUFFER.with(|buffer| {
+            let (mut bit_buffer, mut unused_bits) = buffer.get();
+            let result = take_bits(&mut bit_buffer, &mut unused_bits, num_bits, || {
+                fastrand::u64(..)
+            });
+            buffer.set((bit_buffer, unused_bits));
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Bitstream, BitstreamExt, FastrandBitstream, FastrandThreadBitstream};
+
+    #[test]
+    fn fastrand_words_are_split_lsb_first() {
+        let mut bitstream = FastrandBitstream::seeded(5);
+        let mut rng = fastrand::Rng::with_seed(5);
+        let (first, second) = (rng.u64(..), rng.u64(..));
+        assert_eq!(bitstream.gen_bits(10), first & 0x3ff);
+        assert_eq!(
+            bitstream.gen_bits(60),
+            (first >> 10) | (second & 0x3f) << 54
+        );
+        assert_eq!(bitstream.gen_bits(58), second >> 6);
+
+        let mut thread = FastrandThreadBitstream::default();
+        thread.gen_bits(3);
+        FastrandThreadBitstream::seed(5);
+        assert_eq!(thread.gen_bits(10), first & 0x3ff);
+        assert!(thread.gen_range(6) < 6);
+    }
+}
@@ -0,0 +1,79 @@
+//! C bindings. Keep in sync with `include/rng_nibbler.h`.
+
+use crate::{Bitstream, BitstreamExt, DefaultRng, RngBitstream};
+use std::slice;
+
+/// Opaque handle for C callers.
+pub struct RngNibbler(RngBitstream<DefaultRng>);
+
+/// Creates a bitstream backed by ChaCha20 seeded with `seed`. Free it with
+/// `rng_nibbler_free`.
+#[no_mangle]
+pub extern "C" fn rng_nibbler_new(seed: u64) -> *mut RngNibbler {
+    Box::into_raw(Box::new(RngNibbler(RngBitstream::seeded(seed))))
+}
+
+/// # Safety
+/// `handle` must be null or a pointer returned by `rng_nibbler_new` that
+/// hasn't been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn rng_nibbler_free(handle: *mut RngNibbler) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+/// # Safety
+/// `handle` must be a live pointer from `rng_nibbler_new`, and
+/// `num_bits` must be at most 64.
+#[no_mangle]
+pub unsafe extern "C" fn rng_nibbler_gen_bits(handle: *mut RngNibbler, num_bits: u32) -> u64 {
+    (*handle).0.gen_bits(num_bits)
+}
+
+/// # Safety
+/// `handle` must be a live pointer from `rng_nibbler_new`, and `size` must
+/// be nonzero.
+#[no_mangle]
+pub unsafe extern "C" fn rng_nibbler_gen_range(handle: *mut RngNibbler, size: u64) -> u64 {
+    (*handle).0.gen_range(size)
+}
+
+/// # Safety
+/// `handle` must be a live pointer from `rng_nibbler_new`, and `bytes` must
+/// be valid for writing `len` bytes (it may be null if `len` is 0).
+#[no_mangle]
+pub unsafe extern "C" fn rng_nibbler_fill_bytes(
+    handle: *mut RngNibbler,
+    bytes: *mut u8,
+    len: usize,
+) {
+    if len > 0 {
+        (*handle)
+            .0
+            .fill_bytes(slice::from_raw_parts_mut(bytes, len));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ffi_functions_match_the_rust_api() {
+        let mut direct = RngBitstream::seeded(5);
+        unsafe {
+            let handle = rng_nibbler_new(5);
+            assert_eq!(rng_nibbler_gen_bits(handle, 13), direct.gen_bits(13));
+            assert_eq!(rng_nibbler_gen_range(handle, 6), direct.gen_range(6));
+            let mut bytes = [0u8; 11];
+            let mut expected = [0u8; 11];
+            rng_nibbler_fill_bytes(handle, bytes.as_mut_ptr(), bytes.len());
+            direct.fill_bytes(&mut expected);
+            assert_eq!(bytes, expected);
+            rng_nibbler_fill_bytes(handle, std::ptr::null_mut(), 0);
+            rng_nibbler_free(handle);
+            rng_nibbler_free(std::ptr::null_mut());
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use crate::source::mix64;
+use crate::{low_bits_mask, DefaultRng, RngBitstream};
+use std::hash::{Hash, Hasher};
+
+/// Random bits at any key, such as a grid coordinate, with no sequential
+/// state: the bits for a key depend only on the seed and the key, so they
+/// can be queried in any order, from any thread, any number of times.
+///
+/// Keys are hashed with a fixed hasher, so results are stable across runs
+/// and Rust versions. Integers hash the same on every platform, except that
+/// `usize` and `isize` keys above 32 bits don't exist on 32-bit targets.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct RandomField {
+    seed: u64,
+}
+
+impl RandomField {
+    pub fn new(seed: u64) -> Self {
+        RandomField { seed }
+    }
+
+    fn hash<K: Hash + ?Sized>(&self, key: &K) -> u64 {
+        let mut hasher = FieldHasher {
+            state: mix64(self.seed),
+        };
+        key.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// `num_bits` pseudorandom bits for `key`.
+    ///
+    /// # Panics
+    ///
+    /// If `num_bits > 64`.
+    pub fn random_at<K: Hash + ?Sized>(&self, key: &K, num_bits: u32) -> u64 {
+        assert!(num_bits <= 64, "can't generate more than 64 bits at once");
+        self.hash(key) & low_bits_mask(num_bits)
+    }
+
+    /// A whole bitstream for `key`, for when one value per key isn't enough.
+    /// Slower to start than `random_at`, but backed by `DefaultRng`.
+    pub fn bitstream_at<K: Hash + ?Sized>(&self, key: &K) -> RngBitstream<DefaultRng> {
+        RngBitstream::keyed(self.seed, &self.hash(key).to_le_bytes())
+    }
+}
+
+/// Each word is absorbed through the SplitMix64 finalizer, which is a
+/// bijection, so keys that differ in one word never collide.
+struct FieldHasher {
+    state: u64,
+}
+
+impl Hasher for FieldHasher {
+    fn finish(&self) -> u64 {
+        mix64(self.state ^ 0x9e37_79b9_7f4a_7c15)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for chunk in bytes.chunks(8) {
+            let mut word = [0; 8];
+            word[..chunk.len()].copy_from_slice(chunk);
+            self.write_u64(u64::from_le_bytes(word));
+        }
+        self.write_u64(bytes.len() as u64);
+    }
+
+    fn write_u8(&mut self, value: u8) {
+        self.write_u64(value as u64);
+    }
+
+    fn write_u16(&mut self, value: u16) {
+        self.write_u64(value as u64);
+    }
+
+    fn write_u32(&mut self, value: u32) {
+        self.write_u64(value as u64);
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.state = mix64(self.state ^ value).wrapping_add(0x9e37_79b9_7f4a_7c15);
+    }
+
+    fn write_u128(&mut self, value: u128) {
+        self.write_u64(value as u64);
+        self.write_u64((value >> 64) as u64);
+    }
+
+    fn write_usize(&mut self, value: usize) {
+        self.write_u64(value as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Bitstream, RandomField};
+
+    #[test]
+    fn bits_depend_only_on_seed_and_key() {
+        let field = RandomField::new(7);
+        let forward: Vec<u64> = (0..100i32).map(|x| field.random_at(&(x, -x), 64)).collect();
+        let backward: Vec<u64> = (0..100i32)
+            .rev()
+            .map(|x| field.random_at(&(x, -x), 64))
+            .collect();
+        assert!(forward.iter().eq(backward.iter().rev()));
+        assert_ne!(field.random_at(&(1, 2), 64), field.random_at(&(2, 1), 64));
+        assert_ne!(
+            field.random_at("a", 64),
+            RandomField::new(8).random_at("a", 64)
+        );
+        assert!(field.random_at("a", 5) < 32);
+        assert_eq!(field.random_at("a", 0), 0);
+
+        // Roughly half of the low bits over a grid are set.
+        let ones: u32 = (0..100i64)
+            .flat_map(|x| (0..100i64).map(move |y| (x, y)))
+            .map(|key| field.random_at(&key, 1) as u32)
+            .sum();
+        assert!((ones as i32 - 5000).abs() < 200);
+
+        let mut a = field.bitstream_at(&[3u8, 4]);
+        let mut b = field.bitstream_at(&[3u8, 4]);
+        assert_eq!(a.gen_bits(64), b.gen_bits(64));
+    }
+}
@@ -0,0 +1,190 @@
+//! Genetic-algorithm operators that use as few random bits as they can, so
+//! an experiment's entropy use can be measured with a `CountingRngBitstream`
+//! and replayed from a tape.
+
+use crate::{Bernoulli, Bitstream, BitstreamExt, Distribution};
+
+/// Each gene comes from either parent with probability 1/2, costing 1 bit
+/// per gene. The second child gets the genes the first didn't.
+///
+/// # Panics
+///
+/// If the parents have different lengths.
+pub fn uniform_crossover<B: Bitstream + ?Sized, T: Clone>(
+    bitstream: &mut B,
+    first: &[T],
+    second: &[T],
+) -> (Vec<T>, Vec<T>) {
+    assert_eq!(
+        first.len(),
+        second.len(),
+        "parents must have the same length"
+    );
+    let mut children = (
+        Vec::with_capacity(first.len()),
+        Vec::with_capacity(first.len()),
+    );
+    for (first_chunk, second_chunk) in first.chunks(64).zip(second.chunks(64)) {
+        let swaps = bitstream.gen_bools(first_chunk.len() as u32);
+        for (index, (a, b)) in first_chunk.iter().zip(second_chunk).enumerate() {
+            let (a, b) = if swaps >> index & 1 == 1 {
+                (b, a)
+            } else {
+                (a, b)
+            };
+            children.0.push(a.clone());
+            children.1.push(b.clone());
+        }
+    }
+    children
+}
+
+/// Swaps the parents' tails after a uniformly random cut point strictly
+/// inside the genome.
+///
+/// # Panics
+///
+/// If the parents have different lengths, or fewer than 2 genes.
+pub fn one_point_crossover<B: Bitstream + ?Sized, T: Clone>(
+    bitstream: &mut B,
+    first: &[T],
+    second: &[T],
+) -> (Vec<T>, Vec<T>) {
+    assert_eq!(
+        first.len(),
+        second.len(),
+        "parents must have the same length"
+    );
+    assert!(first.len() >= 2, "can't cut a genome of fewer than 2 genes");
+    let cut = 1 + bitstream.gen_range(first.len() as u64 - 1) as usize;
+    splice(first, second, cut, first.len())
+}
+
+/// Swaps the segments of the parents between two distinct, uniformly random
+/// cut points strictly inside the genome.
+///
+/// # Panics
+///
+/// If the parents have different lengths, or fewer than 3 genes.
+pub fn two_point_crossover<B: Bitstream + ?Sized, T: Clone>(
+    bitstream: &mut B,
+    first: &[T],
+    second: &[T],
+) -> (Vec<T>, Vec<T>) {
+    assert_eq!(
+        first.len(),
+        second.len(),
+        "parents must have the same length"
+    );
+    assert!(
+        first.len() >= 3,
+        "can't cut a genome of fewer than 3 genes twice"
+    );
+    let num_cuts = first.len() as u64 - 1;
+    let start = 1 + bitstream.gen_range(num_cuts) as usize;
+    let mut end = 1 + bitstream.gen_range(num_cuts - 1) as usize;
+    if end >= start {
+        end += 1;
+    }
+    splice(first, second, start.min(end), start.max(end))
+}
+
+/// The parents with `start..end` swapped between them.
+fn splice<T: Clone>(first: &[T], second: &[T], start: usize, end: usize) -> (Vec<T>, Vec<T>) {
+    let child = |outer: &[T], inner: &[T]| {
+        let mut child = outer[..start].to_vec();
+        child.extend_from_slice(&inner[start..end]);
+        child.extend_from_slice(&outer[end..]);
+        child
+    };
+    (child(first, second), child(second, first))
+}
+
+/// Flips each gene with exactly probability `rate`, costing 2 bits per gene
+/// on average whatever the rate.
+pub fn bit_flip_mutation<B: Bitstream + ?Sized>(
+    bitstream: &mut B,
+    genome: &mut [bool],
+    rate: Bernoulli,
+) {
+    for gene in genome {
+        if rate.sample(bitstream) {
+            *gene = !*gene;
+        }
+    }
+}
+
+/// The index of the fittest of `tournament_size` individuals drawn uniformly
+/// with replacement; ties go to the first drawn.
+///
+/// # Panics
+///
+/// If `fitnesses` is empty or `tournament_size` is 0.
+pub fn tournament_selection<B: Bitstream + ?Sized, F: PartialOrd>(
+    bitstream: &mut B,
+    fitnesses: &[F],
+    tournament_size: usize,
+) -> usize {
+    assert!(!fitnesses.is_empty(), "no individuals to select from");
+    assert!(tournament_size > 0, "tournament_size must be positive");
+    let mut values = bitstream.values(fitnesses.len() as u64);
+    let mut best = values.next().unwrap() as usize;
+    for _ in 1..tournament_size {
+        let challenger = values.next().unwrap() as usize;
+        if fitnesses[challenger] > fitnesses[best] {
+            best = challenger;
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CountingRngBitstream;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn operators_preserve_genes_and_use_few_bits() {
+        let mut bitstream = CountingRngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let first: Vec<u32> = (0..100).collect();
+        let second: Vec<u32> = (100..200).collect();
+
+        let (a, b) = uniform_crossover(&mut bitstream, &first, &second);
+        assert_eq!(bitstream.count(), 100);
+        for index in 0..100 {
+            let mut genes = [a[index], b[index]];
+            genes.sort_unstable();
+            assert_eq!(genes, [first[index], second[index]]);
+        }
+        let from_first = a.iter().filter(|&&gene| gene < 100).count();
+        assert!((30..70).contains(&from_first));
+
+        for _ in 0..100 {
+            let (a, b) = one_point_crossover(&mut bitstream, &first, &second);
+            let cut = a.iter().position(|&gene| gene >= 100).unwrap();
+            assert!(cut >= 1 && a[cut..].iter().eq(&second[cut..]) && b[..cut] == second[..cut]);
+            let (a, _) = two_point_crossover(&mut bitstream, &first, &second);
+            let swapped = a.iter().filter(|&&gene| gene >= 100).count();
+            assert!((1..99).contains(&swapped));
+            assert_eq!(a[0], 0);
+        }
+
+        let before = bitstream.count();
+        let mut genome = vec![false; 100000];
+        bit_flip_mutation(&mut bitstream, &mut genome, Bernoulli::new(0.01));
+        let flipped = genome.iter().filter(|&&gene| gene).count();
+        assert!((900..1100).contains(&flipped));
+        assert!(bitstream.count() - before < 210000);
+
+        let fitnesses = [3.0, 1.0, 4.0, 1.0, 5.0];
+        let mut wins = [0; 5];
+        for _ in 0..10000 {
+            wins[tournament_selection(&mut bitstream, &fitnesses, 2)] += 1;
+        }
+        // The best wins unless both picks miss it: 1 - (4/5)^2.
+        assert!((wins[4] as f64 / 10000.0 - 0.36).abs() < 0.02);
+        assert!(wins[4] > wins[2] && wins[2] > wins[0] && wins[0] > wins[1]);
+    }
+}
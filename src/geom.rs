@@ -0,0 +1,316 @@
+//! Uniformly random points in simple geometric domains, in any number of
+//! dimensions.
+//!
+//! Balls, discs and spheres are the unit versions centered on the origin;
+//! scale and translate the results as needed.
+
+use crate::continuous::standard_normal;
+use crate::dist::open_unit_f64;
+use crate::{Bitstream, BitstreamExt};
+
+/// A uniform point in the box with corners `min` and `max`.
+///
+/// # Panics
+///
+/// If any coordinate of `min` exceeds that of `max`.
+pub fn in_box<B: Bitstream + ?Sized, const N: usize>(
+    bitstream: &mut B,
+    min: [f64; N],
+    max: [f64; N],
+) -> [f64; N] {
+    let mut point = [0.0; N];
+    for ((coordinate, low), high) in point.iter_mut().zip(&min).zip(&max) {
+        assert!(low <= high, "min must not exceed max");
+        *coordinate = low + (high - low) * open_unit_f64(bitstream);
+    }
+    point
+}
+
+/// A uniform point on the surface of the unit sphere in `N` dimensions: a
+/// vector of independent Gaussians, normalized, points in a uniform direction.
+///
+/// # Panics
+///
+/// If `N` is 0.
+pub fn on_sphere<B: Bitstream + ?Sized, const N: usize>(bitstream: &mut B) -> [f64; N] {
+    assert!(N > 0, "a sphere needs at least one dimension");
+    loop {
+        let mut point = [0.0; N];
+        for coordinate in &mut point {
+            *coordinate = standard_normal(bitstream);
+        }
+        let norm = point.iter().map(|x| x * x).sum::<f64>().sqrt();
+        // Practically never 0, but that would have no direction at all.
+        if norm > 0.0 {
+            for coordinate in &mut point {
+                *coordinate /= norm;
+            }
+            return point;
+        }
+    }
+}
+
+/// A uniform point in the unit ball in `N` dimensions: a uniform direction
+/// at a distance whose distribution makes the density uniform, `u^(1/N)`.
+///
+/// # Panics
+///
+/// If `N` is 0.
+pub fn in_ball<B: Bitstream + ?Sized, const N: usize>(bitstream: &mut B) -> [f64; N] {
+    let mut point: [f64; N] = on_sphere(bitstream);
+    let radius = open_unit_f64(bitstream).powf(1.0 / N as f64);
+    for coordinate in &mut point {
+        *coordinate *= radius;
+    }
+    point
+}
+
+/// A uniform point in the unit disc.
+pub fn in_disc<B: Bitstream + ?Sized>(bitstream: &mut B) -> [f64; 2] {
+    in_ball(bitstream)
+}
+
+/// A uniform point in the simplex with the given vertices. The barycentric
+/// weights are normalized independent exponentials, which are uniform on the
+/// standard simplex.
+///
+/// # Panics
+///
+/// If `vertices` is empty.
+pub fn in_simplex<B: Bitstream + ?Sized, const N: usize>(
+    bitstream: &mut B,
+    vertices: &[[f64; N]],
+) -> [f64; N] {
+    assert!(!vertices.is_empty(), "a simplex needs at least one vertex");
+    let weights: Vec<f64> = vertices
+        .iter()
+        .map(|_| -open_unit_f64(bitstream).ln())
+        .collect();
+    let total: f64 = weights.iter().sum();
+    let mut point = [0.0; N];
+    for (vertex, weight) in vertices.iter().zip(&weights) {
+        for (coordinate, x) in point.iter_mut().zip(vertex) {
+            *coordinate += x * weight / total;
+        }
+    }
+    point
+}
+
+/// A uniform point in the triangle `abc`.
+pub fn in_triangle<B: Bitstream + ?Sized, const N: usize>(
+    bitstream: &mut B,
+    a: [f64; N],
+    b: [f64; N],
+    c: [f64; N],
+) -> [f64; N] {
+    in_simplex(bitstream, &[a, b, c])
+}
+
+/// Bridson's algorithm for Poisson-disk sampling: a random set of points in
+/// the box from the origin to `extent` with no two closer than
+/// `min_distance`, and no room left for another, for blue-noise placement.
+pub struct PoissonDisk<const N: usize> {
+    extent: [f64; N],
+    min_distance: f64,
+    attempts: u32,
+}
+
+impl<const N: usize> PoissonDisk<N> {
+    /// # Panics
+    ///
+    /// If `N` is 0, `min_distance` isn't positive and finite, or `extent`
+    /// has a coordinate that isn't positive and finite.
+    pub fn new(extent: [f64; N], min_distance: f64) -> Self {
+        assert!(N > 0, "the domain needs at least one dimension");
+        assert!(
+            min_distance > 0.0 && min_distance.is_finite(),
+            "min_distance must be positive and finite"
+        );
+        assert!(
+            extent.iter().all(|&x| x > 0.0 && x.is_finite()),
+            "extent must be positive and finite"
+        );
+        PoissonDisk {
+            extent,
+            min_distance,
+            attempts: 30,
+        }
+    }
+
+    /// How many candidates to try around each point before giving up on it
+    /// (default 30). Fewer is faster but leaves more gaps.
+    pub fn with_attempts(self, attempts: u32) -> Self {
+        PoissonDisk { attempts, ..self }
+    }
+
+    pub fn generate<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> Vec<[f64; N]> {
+        // Cells small enough to hold at most one point each.
+        let cell_size = self.min_distance / (N as f64).sqrt();
+        let mut grid_size = [0; N];
+        for (cells, extent) in grid_size.iter_mut().zip(&self.extent) {
+            *cells = (extent / cell_size).ceil() as usize;
+        }
+        let cell_of = |point: &[f64; N]| {
+            let mut cell = [0; N];
+            for ((index, x), cells) in cell.iter_mut().zip(point).zip(&grid_size) {
+                *index = ((x / cell_size) as usize).min(cells - 1);
+            }
+            cell
+        };
+        let flatten = |cell: &[usize; N]| {
+            cell.iter()
+                .zip(&grid_size)
+                .fold(0, |flat, (index, cells)| flat * cells + index)
+        };
+        let mut grid: Vec<Option<usize>> = vec![None; grid_size.iter().product()];
+        let reach = (N as f64).sqrt().ceil() as usize;
+        let is_free = |grid: &[Option<usize>], points: &[[f64; N]], candidate: &[f64; N]| {
+            let center = cell_of(candidate);
+            let mut low = [0; N];
+            let mut high = [0; N];
+            for dimension in 0..N {
+                low[dimension] = center[dimension].saturating_sub(reach);
+                high[dimension] = (center[dimension] + reach).min(grid_size[dimension] - 1);
+            }
+            // Visit the neighboring cells like an odometer.
+            let mut cell = low;
+            loop {
+                if let Some(other) = grid[flatten(&cell)] {
+                    let distance_squared: f64 = candidate
+                        .iter()
+                        .zip(&points[other])
+                        .map(|(a, b)| (a - b) * (a - b))
+                        .sum();
+                    if distance_squared < self.min_distance * self.min_distance {
+                        return false;
+                    }
+                }
+                let mut dimension = 0;
+                loop {
+                    if dimension == N {
+                        return true;
+                    }
+                    if cell[dimension] < high[dimension] {
+                        cell[dimension] += 1;
+                        break;
+                    }
+                    cell[dimension] = low[dimension];
+                    dimension += 1;
+                }
+            }
+        };
+
+        let first = in_box(bitstream, [0.0; N], self.extent);
+        grid[flatten(&cell_of(&first))] = Some(0);
+        let mut points = vec![first];
+        let mut active = vec![0];
+        while !active.is_empty() {
+            let which = bitstream.gen_range(active.len() as u64) as usize;
+            let center = points[active[which]];
+            let mut found = false;
+            for _ in 0..self.attempts {
+                // Uniform in the shell between min_distance and twice that.
+                let direction: [f64; N] = on_sphere(bitstream);
+                let inner = self.min_distance.powi(N as i32);
+                let outer = (2.0 * self.min_distance).powi(N as i32);
+                let radius =
+                    (inner + (outer - inner) * open_unit_f64(bitstream)).powf(1.0 / N as f64);
+                let mut candidate = center;
+                for (x, d) in candidate.iter_mut().zip(&direction) {
+                    *x += d * radius;
+                }
+                let in_bounds = candidate
+                    .iter()
+                    .zip(&self.extent)
+                    .all(|(x, extent)| (0.0..*extent).contains(x));
+                if in_bounds && is_free(&grid, &points, &candidate) {
+                    grid[flatten(&cell_of(&candidate))] = Some(points.len());
+                    active.push(points.len());
+                    points.push(candidate);
+                    found = true;
+                    break;
+                }
+            }
+            if !found {
+                active.swap_remove(which);
+            }
+        }
+        points
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RngBitstream;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn points_are_uniform_in_their_domains() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let num_samples = 100000;
+        let fraction = |count: usize| count as f64 / num_samples as f64;
+
+        let inside_half_box = (0..num_samples)
+            .map(|_| in_box(&mut bitstream, [-1.0, 2.0], [1.0, 4.0]))
+            .inspect(|p| assert!((-1.0..1.0).contains(&p[0]) && (2.0..4.0).contains(&p[1])))
+            .filter(|p| p[0] < 0.0)
+            .count();
+        assert!((fraction(inside_half_box) - 0.5).abs() < 0.01);
+
+        for _ in 0..1000 {
+            let p: [f64; 5] = on_sphere(&mut bitstream);
+            assert!((p.iter().map(|x| x * x).sum::<f64>() - 1.0).abs() < 1e-9);
+        }
+
+        // Half the volume of a 3-ball lies within radius 0.5^(1/3); a
+        // quarter of a disc within radius 0.5.
+        let inner_ball = (0..num_samples)
+            .map(|_| in_ball::<_, 3>(&mut bitstream))
+            .filter(|p| p.iter().map(|x| x * x).sum::<f64>().sqrt() < 0.5f64.cbrt())
+            .count();
+        assert!((fraction(inner_ball) - 0.5).abs() < 0.01);
+        let inner_disc = (0..num_samples)
+            .map(|_| in_disc(&mut bitstream))
+            .filter(|p| p[0] * p[0] + p[1] * p[1] < 0.25)
+            .count();
+        assert!((fraction(inner_disc) - 0.25).abs() < 0.01);
+
+        // The triangle (0,0), (2,0), (0,2): the part with x < 1 is 3/4.
+        let left = (0..num_samples)
+            .map(|_| in_triangle(&mut bitstream, [0.0, 0.0], [2.0, 0.0], [0.0, 2.0]))
+            .inspect(|p| assert!(p[0] >= 0.0 && p[1] >= 0.0 && p[0] + p[1] <= 2.0 + 1e-12))
+            .filter(|p| p[0] < 1.0)
+            .count();
+        assert!((fraction(left) - 0.75).abs() < 0.01);
+    }
+
+    #[test]
+    fn poisson_disk_points_are_spaced_and_reproducible() {
+        let sampler = PoissonDisk::new([10.0, 5.0], 0.5);
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let points = sampler.generate(&mut bitstream);
+        let distance =
+            |a: &[f64; 2], b: &[f64; 2]| ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2)).sqrt();
+        for (index, a) in points.iter().enumerate() {
+            assert!((0.0..10.0).contains(&a[0]) && (0.0..5.0).contains(&a[1]));
+            for b in &points[..index] {
+                assert!(distance(a, b) >= 0.5);
+            }
+        }
+        // Maximal: every spot in the domain is near some point.
+        for _ in 0..1000 {
+            let spot = in_box(&mut bitstream, [0.0; 2], [10.0, 5.0]);
+            assert!(points.iter().any(|p| distance(p, &spot) < 1.0));
+        }
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        assert_eq!(sampler.generate(&mut bitstream), points);
+
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(1));
+        let points = PoissonDisk::new([2.0; 3], 0.5)
+            .with_attempts(10)
+            .generate(&mut bitstream);
+        assert!(points.len() > 20);
+    }
+}
@@ -0,0 +1,244 @@
+//! Combinators for generating structured data (syntax trees, protocol
+//! messages) from a bitstream, e.g. as the randomness core of a fuzzer.
+//!
+//! Generators are anything implementing `Grammar`, including closures
+//! `Fn(&mut Context<'_>) -> T`. Recursive grammars are written as ordinary
+//! recursive functions that call `Context::recurse`, which bounds the depth.
+
+use crate::{Bernoulli, Bitstream, Distribution, RangePlan, WeightedIndex};
+
+/// The state threaded through a generation: the bitstream to draw from and
+/// how much recursion depth is left. `Context` is itself a `Bitstream`, so
+/// generators can sample from it directly.
+pub struct Context<'a> {
+    bitstream: &'a mut dyn Bitstream,
+    depth_remaining: u32,
+}
+
+impl<'a> Context<'a> {
+    pub fn new(bitstream: &'a mut dyn Bitstream, max_depth: u32) -> Self {
+        Context {
+            bitstream,
+            depth_remaining: max_depth,
+        }
+    }
+
+    pub fn depth_remaining(&self) -> u32 {
+        self.depth_remaining
+    }
+
+    /// Runs `branch` one level deeper, or `leaf` if the depth budget is used
+    /// up.
+    pub fn recurse<T>(
+        &mut self,
+        leaf: impl FnOnce(&mut Self) -> T,
+        branch: impl FnOnce(&mut Self) -> T,
+    ) -> T {
+        if self.depth_remaining == 0 {
+            return leaf(self);
+        }
+        self.depth_remaining -= 1;
+        let result = branch(self);
+        self.depth_remaining += 1;
+        result
+    }
+
+    pub fn generate<T>(&mut self, grammar: &(impl Grammar<T> + ?Sized)) -> T {
+        grammar.generate(self)
+    }
+}
+
+impl Bitstream for Context<'_> {
+    fn gen_bits(&mut self, num_bits: u32) -> u64 {
+        self.bitstream.gen_bits(num_bits)
+    }
+
+    fn range_plan(&mut self, size: u64) -> RangePlan {
+        self.bitstream.range_plan(size)
+    }
+}
+
+pub trait Grammar<T> {
+    fn generate(&self, context: &mut Context<'_>) -> T;
+}
+
+impl<T, F: Fn(&mut Context<'_>) -> T> Grammar<T> for F {
+    fn generate(&self, context: &mut Context<'_>) -> T {
+        self(context)
+    }
+}
+
+/// Picks one of several alternatives, uniformly or by weight.
+pub struct Choice<T> {
+    alternatives: Vec<Box<dyn Grammar<T>>>,
+    weights: WeightedIndex,
+}
+
+impl<T> Choice<T> {
+    /// # Panics
+    ///
+    /// If there are no alternatives.
+    pub fn new(alternatives: Vec<Box<dyn Grammar<T>>>) -> Self {
+        let weights = WeightedIndex::new(&vec![1; alternatives.len()]);
+        Choice {
+            alternatives,
+            weights,
+        }
+    }
+
+    /// # Panics
+    ///
+    /// As `WeightedIndex::new`.
+    pub fn weighted(alternatives: Vec<(u64, Box<dyn Grammar<T>>)>) -> Self {
+        let (weights, alternatives): (Vec<u64>, _) = alternatives.into_iter().unzip();
+        Choice {
+            alternatives,
+            weights: WeightedIndex::new(&weights),
+        }
+    }
+}
+
+impl<T> Grammar<T> for Choice<T> {
+    fn generate(&self, context: &mut Context<'_>) -> T {
+        let index = self.weights.sample(context);
+        self.alternatives[index].generate(context)
+    }
+}
+
+/// Generates a list whose length is geometrically distributed: before each
+/// item, a lazy coin flip (2 bits on average) decides whether to stop, up to
+/// an optional maximum length.
+pub struct Repeat<G> {
+    item: G,
+    another: Bernoulli,
+    max_len: usize,
+}
+
+impl<G> Repeat<G> {
+    /// Lists of `mean_len` items on average (before `with_max_len`).
+    ///
+    /// # Panics
+    ///
+    /// If `mean_len` is negative or not finite.
+    pub fn new(item: G, mean_len: f64) -> Self {
+        assert!(
+            mean_len >= 0.0 && mean_len.is_finite(),
+            "mean length must be finite and nonnegative"
+        );
+        Repeat {
+            item,
+            another: Bernoulli::new(mean_len / (mean_len + 1.0)),
+            max_len: usize::MAX,
+        }
+    }
+
+    pub fn with_max_len(self, max_len: usize) -> Self {
+        Repeat { max_len, ..self }
+    }
+}
+
+impl<T, G: Grammar<T>> Grammar<Vec<T>> for Repeat<G> {
+    fn generate(&self, context: &mut Context<'_>) -> Vec<T> {
+        let mut items = Vec::new();
+        while items.len() < self.max_len && self.another.sample(context) {
+            items.push(self.item.generate(context));
+        }
+        items
+    }
+}
+
+/// Generates `Some` with the given probability, and `None` otherwise.
+pub struct Optional<G> {
+    item: G,
+    present: Bernoulli,
+}
+
+impl<G> Optional<G> {
+    /// # Panics
+    ///
+    /// If `probability` isn't in `[0, 1]`.
+    pub fn new(item: G, probability: f64) -> Self {
+        Optional {
+            item,
+            present: Bernoulli::new(probability),
+        }
+    }
+}
+
+impl<T, G: Grammar<T>> Grammar<Option<T>> for Optional<G> {
+    fn generate(&self, context: &mut Context<'_>) -> Option<T> {
+        if self.present.sample(context) {
+            Some(self.item.generate(context))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BitstreamExt, RngBitstream};
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    enum Expr {
+        Literal(u64),
+        Negate(Box<Expr>),
+        Sum(Vec<Expr>),
+    }
+
+    fn depth(expr: &Expr) -> u32 {
+        match expr {
+            Expr::Literal(value) => {
+                assert!(*value < 10);
+                0
+            }
+            Expr::Negate(inner) => 1 + depth(inner),
+            Expr::Sum(terms) => 1 + terms.iter().map(depth).max().unwrap_or(0),
+        }
+    }
+
+    fn expr(context: &mut Context<'_>) -> Expr {
+        let literal = |context: &mut Context<'_>| Expr::Literal(context.gen_range(10));
+        context.recurse(literal, |context| {
+            let grammar = Choice::new(vec![
+                Box::new(literal),
+                Box::new(|context: &mut Context<'_>| Expr::Negate(Box::new(expr(context)))),
+                Box::new(|context: &mut Context<'_>| {
+                    Expr::Sum(context.generate(&Repeat::new(expr, 2.0).with_max_len(4)))
+                }),
+            ]);
+            context.generate(&grammar)
+        })
+    }
+
+    #[test]
+    fn grammars_respect_depth_and_length_limits() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let mut context = Context::new(&mut bitstream, 4);
+        let mut max_depth = 0;
+        for _ in 0..1000 {
+            max_depth = max_depth.max(depth(&expr(&mut context)));
+            assert_eq!(context.depth_remaining(), 4);
+        }
+        assert_eq!(max_depth, 4);
+
+        let lengths: usize = (0..10000)
+            .map(|_| {
+                context
+                    .generate(&Repeat::new(|_: &mut Context<'_>| (), 3.0))
+                    .len()
+            })
+            .sum();
+        assert!((lengths as f64 / 10000.0 - 3.0).abs() < 0.1);
+        let present = (0..10000)
+            .filter(|_| {
+                context
+                    .generate(&Optional::new(|_: &mut Context<'_>| (), 0.25))
+                    .is_some()
+            })
+            .count();
+        assert!((present as f64 / 10000.0 - 0.25).abs() < 0.02);
+    }
+}
@@ -0,0 +1,282 @@
+//! Random graphs for benchmark workloads. Graphs are edge lists over the
+//! nodes `0..n`, each edge `(u, v)` with `u < v`, with no self-loops or
+//! repeated edges.
+
+use crate::geom::in_box;
+use crate::{Bernoulli, Bitstream, BitstreamExt, Distribution, Geometric};
+use std::collections::{HashMap, HashSet};
+
+fn edge(u: usize, v: usize) -> (usize, usize) {
+    (u.min(v), u.max(v))
+}
+
+/// The Erdős–Rényi graph `G(n, p)`: each possible edge is present
+/// independently with probability `p`. Skips over absent edges with
+/// geometric gaps (Batagelj and Brandes), so the time is proportional to
+/// the number of edges rather than `n^2`.
+///
+/// # Panics
+///
+/// If `p` isn't in `[0, 1]`.
+pub fn erdos_renyi<B: Bitstream + ?Sized>(
+    bitstream: &mut B,
+    n: usize,
+    p: f64,
+) -> Vec<(usize, usize)> {
+    assert!((0.0..=1.0).contains(&p), "probability must be in [0, 1]");
+    let mut edges = Vec::new();
+    if p == 0.0 {
+        return edges;
+    }
+    let gap = Geometric::new(p);
+    // Walks the pairs (w, v) with w < v in order of v, then w.
+    let (mut v, mut w) = (1, 0);
+    while v < n {
+        w += bitstream.sample(gap) as usize;
+        while w >= v && v < n {
+            w -= v;
+            v += 1;
+        }
+        if v < n {
+            edges.push((w, v));
+            w += 1;
+        }
+    }
+    edges
+}
+
+/// The Barabási–Albert preferential attachment graph: starting from `m`
+/// unconnected nodes, each new node connects to `m` distinct existing nodes
+/// chosen with probability proportional to their degree, which gives a
+/// power-law degree distribution with a few large hubs.
+///
+/// # Panics
+///
+/// If `m` is 0 or `m >= n`.
+pub fn barabasi_albert<B: Bitstream + ?Sized>(
+    bitstream: &mut B,
+    n: usize,
+    m: usize,
+) -> Vec<(usize, usize)> {
+    assert!(m > 0 && m < n, "need 0 < m < n");
+    let mut edges = Vec::with_capacity(m * (n - m));
+    // Each node appears once per edge it has, so a uniform entry is a
+    // degree-weighted node.
+    let mut endpoints = Vec::with_capacity(2 * m * (n - m));
+    let mut targets: Vec<usize> = (0..m).collect();
+    for source in m..n {
+        for &target in &targets {
+            edges.push((target, source));
+            endpoints.push(target);
+            endpoints.push(source);
+        }
+        let mut chosen = HashSet::with_capacity(m);
+        targets.clear();
+        while targets.len() < m {
+            let target = endpoints[bitstream.gen_range(endpoints.len() as u64) as usize];
+            if chosen.insert(target) {
+                targets.push(target);
+            }
+        }
+    }
+    edges
+}
+
+/// The Watts–Strogatz small-world graph: a ring where each node connects
+/// to its `k / 2` nearest neighbors on each side, then each edge's far end
+/// is rewired with probability `beta` to a uniform node it isn't already
+/// connected to. Small `beta` keeps the ring's clustering while adding the
+/// shortcuts that make paths short.
+///
+/// # Panics
+///
+/// If `k` is odd or at least `n`, or `beta` isn't in `[0, 1]`.
+pub fn watts_strogatz<B: Bitstream + ?Sized>(
+    bitstream: &mut B,
+    n: usize,
+    k: usize,
+    beta: f64,
+) -> Vec<(usize, usize)> {
+    assert!(
+        k.is_multiple_of(2) && k < n,
+        "k must be even and less than n"
+    );
+    let rewire = Bernoulli::new(beta);
+    let mut edges: Vec<(usize, usize)> = (1..=k / 2)
+        .flat_map(|offset| (0..n).map(move |u| edge(u, (u + offset) % n)))
+        .collect();
+    let mut present: HashSet<(usize, usize)> = edges.iter().copied().collect();
+    let mut degrees = vec![k; n];
+    for slot in &mut edges {
+        if !rewire.sample(bitstream) {
+            continue;
+        }
+        let (u, old) = *slot;
+        // A node already joined to everyone has nowhere to rewire to.
+        if degrees[u] >= n - 1 {
+            continue;
+        }
+        let new = loop {
+            let candidate = bitstream.gen_range(n as u64) as usize;
+            if candidate != u && !present.contains(&edge(u, candidate)) {
+                break candidate;
+            }
+        };
+        present.remove(&edge(u, old));
+        present.insert(edge(u, new));
+        degrees[old] -= 1;
+        degrees[new] += 1;
+        *slot = edge(u, new);
+    }
+    edges
+}
+
+/// `n` uniform points in the unit cube `[0, 1)^N`, e.g. as input for
+/// spatial indexes.
+pub fn point_cloud<B: Bitstream + ?Sized, const N: usize>(
+    bitstream: &mut B,
+    n: usize,
+) -> Vec<[f64; N]> {
+    (0..n)
+        .map(|_| in_box(bitstream, [0.0; N], [1.0; N]))
+        .collect()
+}
+
+/// A random geometric graph: `n` uniform points in the unit cube, with an
+/// edge between each pair less than `radius` apart. Points are hashed into
+/// cells of width `radius`, so only neighboring cells are compared. The
+/// edges come out sorted.
+///
+/// # Panics
+///
+/// If `N` is 0 or `radius` isn't positive and finite.
+pub fn random_geometric<B: Bitstream + ?Sized, const N: usize>(
+    bitstream: &mut B,
+    n: usize,
+    radius: f64,
+) -> (Vec<[f64; N]>, Vec<(usize, usize)>) {
+    assert!(N > 0, "points need at least one dimension");
+    assert!(
+        radius > 0.0 && radius.is_finite(),
+        "radius must be positive and finite"
+    );
+    let points: Vec<[f64; N]> = point_cloud(bitstream, n);
+    let cell_of = |point: &[f64; N]| {
+        let mut cell = [0; N];
+        for (index, x) in cell.iter_mut().zip(point) {
+            *index = (x / radius) as i64;
+        }
+        cell
+    };
+    let mut cells: HashMap<[i64; N], Vec<usize>> = HashMap::new();
+    let mut edges = Vec::new();
+    for (index, point) in points.iter().enumerate() {
+        let center = cell_of(point);
+        // Visit the 3^N cells around this one like an odometer.
+        let mut offset = [-1; N];
+        'cells: loop {
+            let mut cell = center;
+            for (index, delta) in cell.iter_mut().zip(&offset) {
+                *index += delta;
+            }
+            for &other in cells.get(&cell).into_iter().flatten() {
+                let distance_squared: f64 = point
+                    .iter()
+                    .zip(&points[other])
+                    .map(|(a, b)| (a - b) * (a - b))
+                    .sum();
+                if distance_squared < radius * radius {
+                    edges.push((other, index));
+                }
+            }
+            for delta in &mut offset {
+                if *delta < 1 {
+                    *delta += 1;
+                    continue 'cells;
+                }
+                *delta = -1;
+            }
+            break;
+        }
+        cells.entry(center).or_default().push(index);
+    }
+    edges.sort_unstable();
+    (points, edges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RngBitstream;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    fn assert_simple(edges: &[(usize, usize)], n: usize) {
+        let unique: HashSet<_> = edges.iter().collect();
+        assert_eq!(unique.len(), edges.len());
+        assert!(edges.iter().all(|&(u, v)| u < v && v < n));
+    }
+
+    fn degrees(edges: &[(usize, usize)], n: usize) -> Vec<usize> {
+        let mut degrees = vec![0; n];
+        for &(u, v) in edges {
+            degrees[u] += 1;
+            degrees[v] += 1;
+        }
+        degrees
+    }
+
+    #[test]
+    fn graphs_have_their_models_shapes() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+
+        let edges = erdos_renyi(&mut bitstream, 1000, 0.01);
+        assert_simple(&edges, 1000);
+        let expected = 0.01 * 1000.0 * 999.0 / 2.0;
+        assert!((edges.len() as f64 - expected).abs() < 4.0 * expected.sqrt());
+        assert_eq!(erdos_renyi(&mut bitstream, 10, 1.0).len(), 45);
+        assert!(erdos_renyi(&mut bitstream, 10, 0.0).is_empty());
+
+        let edges = barabasi_albert(&mut bitstream, 2000, 3);
+        assert_simple(&edges, 2000);
+        assert_eq!(edges.len(), 3 * 1997);
+        let attached = degrees(&edges, 2000);
+        assert!(attached[3..].iter().all(|&degree| degree >= 3));
+        // Hubs: far above the mean degree of about 6.
+        assert!(*attached.iter().max().unwrap() > 50);
+
+        let ring = watts_strogatz(&mut bitstream, 100, 4, 0.0);
+        assert_simple(&ring, 100);
+        assert!(degrees(&ring, 100).iter().all(|&degree| degree == 4));
+        let edges = watts_strogatz(&mut bitstream, 100, 4, 0.2);
+        assert_simple(&edges, 100);
+        assert_eq!(edges.len(), 200);
+        let rewired = edges.iter().filter(|edge| !ring.contains(edge)).count();
+        assert!((20..=60).contains(&rewired), "{}", rewired);
+        assert_eq!(watts_strogatz(&mut bitstream, 5, 4, 1.0).len(), 10);
+    }
+
+    #[test]
+    fn geometric_graphs_join_exactly_the_close_pairs() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let (points, edges) = random_geometric::<_, 2>(&mut bitstream, 500, 0.07);
+        assert_simple(&edges, 500);
+        let mut expected = Vec::new();
+        for j in 0..points.len() {
+            for i in 0..j {
+                let (a, b) = (points[i], points[j]);
+                if (a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) < 0.07 * 0.07 {
+                    expected.push((i, j));
+                }
+            }
+        }
+        expected.sort_unstable();
+        assert_eq!(edges, expected);
+        assert!(!edges.is_empty());
+
+        let cloud: Vec<[f64; 3]> = point_cloud(&mut bitstream, 1000);
+        assert!(cloud.iter().flatten().all(|x| (0.0..1.0).contains(x)));
+        let (_, edges) = random_geometric::<_, 3>(&mut bitstream, 300, 2.0);
+        assert_eq!(edges.len(), 300 * 299 / 2);
+    }
+}
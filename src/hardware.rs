@@ -0,0 +1,140 @@
+use crate::error::check_num_bits;
+use crate::source::{BitOrder, LsbFirst};
+use crate::{Bitstream, Error, TryBitstream};
+use std::arch::x86_64::{_rdrand64_step, _rdseed64_step};
+use std::io;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Instruction {
+    /// The output of the CPU's DRBG, reseeded by its entropy source.
+    Rdrand,
+    /// Conditioned output straight from the entropy source. Slower, and
+    /// more likely to need retries under load.
+    Rdseed,
+}
+
+/// A bitstream over the x86-64 `RDRAND` or `RDSEED` instruction. Each
+/// instruction can transiently fail; it's retried up to `max_retries` times
+/// (with a spin-loop hint between tries) before the draw fails with
+/// `Error::Io`, or a panic through the `Bitstream` impl.
+pub struct HardwareBitstream {
+    instruction: Instruction,
+    max_retries: u32,
+    bit_buffer: u64,
+    unused_bits: u32,
+}
+
+impl HardwareBitstream {
+    /// Fails with an `Unsupported` I/O error if CPUID says the CPU lacks the
+    /// instruction.
+    pub fn new(instruction: Instruction) -> Result<Self, Error> {
+        let supported = match instruction {
+            Instruction::Rdrand => is_x86_feature_detected!("rdrand"),
+            Instruction::Rdseed => is_x86_feature_detected!("rdseed"),
+        };
+        if !supported {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("this CPU doesn't support {:?}", instruction),
+            )
+            .into());
+        }
+        Ok(HardwareBitstream {
+            instruction,
+            // Intel's guidance: 10 retries make an RDRAND failure practically
+            // impossible on a working CPU. RDSEED can legitimately run dry for
+            // a while, so give it longer.
+            max_retries: match instruction {
+                Instruction::Rdrand => 10,
+                Instruction::Rdseed => 1000,
+            },
+            bit_buffer: 0,
+            unused_bits: 0,
+        })
+    }
+
+    pub fn with_max_retries(self, max_retries: u32) -> Self {
+        HardwareBitstream {
+            max_retries,
+            ..self
+        }
+    }
+
+    fn gen_word(&mut self) -> Result<u64, Error> {
+        for _ in 0..=self.max_retries {
+            let mut word = 0;
+            // Safety: `new` checked that the CPU supports the instruction.
+            let succeeded = unsafe {
+                match self.instruction {
+                    Instruction::Rdrand => rdrand(&mut word),
+                    Instruction::Rdseed => rdseed(&mut word),
+                }
+            };
+            if succeeded {
+                return Ok(word);
+            }
+            std::hint::spin_loop();
+        }
+        Err(io::Error::other(format!(
+            "{:?} failed {} times in a row",
+            self.instruction,
+            self.max_retries + 1
+        ))
+        .into())
+    }
+}
+
+#[target_feature(enable = "rdrand")]
+unsafe fn rdrand(word: &mut u64) -> bool {
+    _rdrand64_step(word) == 1
+}
+
+#[target_feature(enable = "rdseed")]
+unsafe fn rdseed(word: &mut u64) -> bool {
+    _rdseed64_step(word) == 1
+}
+
+impl TryBitstream for HardwareBitstream {
+    fn try_gen_bits(&mut self, num_bits: u32) -> Result<u64, Error> {
+        check_num_bits(num_bits)?;
+        if num_bits <= self.unused_bits {
+            self.unused_bits -= num_bits;
+            return Ok(LsbFirst::take(&mut self.bit_buffer, num_bits));
+        }
+        let fresh = self.gen_word()?;
+        let result =
+            LsbFirst::take_refilled(&mut self.bit_buffer, self.unused_bits, fresh, num_bits);
+        self.unused_bits = 64 - (num_bits - self.unused_bits);
+        Ok(result)
+    }
+}
+
+impl Bitstream for HardwareBitstream {
+    fn gen_bits(&mut self, num_bits: u32) -> u64 {
+        self.try_gen_bits(num_bits)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{HardwareBitstream, Instruction, TryBitstream};
+
+    #[test]
+    fn hardware_sources_work_where_supported() {
+        for &instruction in &[Instruction::Rdrand, Instruction::Rdseed] {
+            let mut bitstream = match HardwareBitstream::new(instruction) {
+                Ok(bitstream) => bitstream,
+                Err(error) => {
+                    eprintln!("skipping {:?}: {}", instruction, error);
+                    continue;
+                }
+            };
+            let words: Vec<u64> = (0..8)
+                .map(|_| bitstream.try_gen_bits(64).unwrap())
+                .collect();
+            assert!(words.windows(2).any(|pair| pair[0] != pair[1]));
+            assert!(bitstream.try_gen_bits(3).unwrap() < 8);
+        }
+    }
+}
@@ -0,0 +1,226 @@
+//! Random Latin squares and completed Sudoku grids, for experimental design
+//! and puzzle generation.
+
+use crate::seq::shuffle;
+use crate::{Bitstream, BitstreamExt};
+
+/// The incidence cube of a Latin square: `cells[(row * n + column) * n +
+/// symbol]` is 1 if `symbol` is at `(row, column)`. Jacobson and Matthews'
+/// moves pass through "improper" cubes with a single -1 entry.
+struct IncidenceCube {
+    n: usize,
+    cells: Vec<i8>,
+}
+
+impl IncidenceCube {
+    fn get(&self, row: usize, column: usize, symbol: usize) -> i8 {
+        self.cells[(row * self.n + column) * self.n + symbol]
+    }
+
+    fn add(&mut self, row: usize, column: usize, symbol: usize, delta: i8) {
+        self.cells[(row * self.n + column) * self.n + symbol] += delta;
+    }
+
+    /// A random index along a line of the cube (given by `position`) where
+    /// the entry is 1. Lines have one such entry, or two if they pass
+    /// through the -1 entry.
+    fn ones<B: Bitstream + ?Sized>(
+        &self,
+        bitstream: &mut B,
+        mut position: impl FnMut(usize) -> (usize, usize, usize),
+    ) -> usize {
+        let ones: Vec<usize> = (0..self.n)
+            .filter(|&index| {
+                let (row, column, symbol) = position(index);
+                self.get(row, column, symbol) == 1
+            })
+            .collect();
+        ones[bitstream.gen_range(ones.len() as u64) as usize]
+    }
+
+    /// One Jacobson-Matthews move from `(row, column, symbol)`, which is a 0
+    /// entry of a proper cube or the -1 entry of an improper one. Returns the
+    /// new -1 entry, if any.
+    fn step<B: Bitstream + ?Sized>(
+        &mut self,
+        bitstream: &mut B,
+        (row, column, symbol): (usize, usize, usize),
+    ) -> Option<(usize, usize, usize)> {
+        let other_row = self.ones(bitstream, |index| (index, column, symbol));
+        let other_column = self.ones(bitstream, |index| (row, index, symbol));
+        let other_symbol = self.ones(bitstream, |index| (row, column, index));
+        self.add(row, column, symbol, 1);
+        self.add(row, other_column, other_symbol, 1);
+        self.add(other_row, column, other_symbol, 1);
+        self.add(other_row, other_column, symbol, 1);
+        self.add(row, column, other_symbol, -1);
+        self.add(row, other_column, symbol, -1);
+        self.add(other_row, column, symbol, -1);
+        self.add(other_row, other_column, other_symbol, -1);
+        if self.get(other_row, other_column, other_symbol) == -1 {
+            Some((other_row, other_column, other_symbol))
+        } else {
+            None
+        }
+    }
+}
+
+/// A practically uniform random `n` by `n` Latin square, with symbols
+/// `0..n`, indexed `[row][column]`. It's the state of Jacobson and
+/// Matthews' Markov chain, whose stationary distribution is uniform, after
+/// `n^3` moves from a cyclic square.
+pub fn latin_square<B: Bitstream + ?Sized>(bitstream: &mut B, n: usize) -> Vec<Vec<usize>> {
+    let mut cube = IncidenceCube {
+        n,
+        cells: vec![0; n * n * n],
+    };
+    for row in 0..n {
+        for column in 0..n {
+            cube.add(row, column, (row + column) % n, 1);
+        }
+    }
+    if n > 1 {
+        for _ in 0..n * n * n {
+            // Start from a uniformly random 0 entry of the proper cube.
+            let start = loop {
+                let row = bitstream.gen_range(n as u64) as usize;
+                let column = bitstream.gen_range(n as u64) as usize;
+                let symbol = bitstream.gen_range(n as u64) as usize;
+                if cube.get(row, column, symbol) == 0 {
+                    break (row, column, symbol);
+                }
+            };
+            let mut improper = cube.step(bitstream, start);
+            while let Some(entry) = improper {
+                improper = cube.step(bitstream, entry);
+            }
+        }
+    }
+    (0..n)
+        .map(|row| {
+            (0..n)
+                .map(|column| {
+                    (0..n)
+                        .find(|&symbol| cube.get(row, column, symbol) == 1)
+                        .unwrap()
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// A random completed Sudoku grid, with digits 1 to 9, indexed
+/// `[row][column]`. A randomized backtracking search finds a grid, which is
+/// then scrambled by a random symmetry of Sudoku: relabeling the digits,
+/// permuting bands, stacks, and the rows and columns within them, and
+/// transposing. The result is close to uniform but not exactly so.
+pub fn sudoku<B: Bitstream + ?Sized>(bitstream: &mut B) -> [[u8; 9]; 9] {
+    fn fill<B: Bitstream + ?Sized>(
+        bitstream: &mut B,
+        grid: &mut [[u8; 9]; 9],
+        cell: usize,
+    ) -> bool {
+        if cell == 81 {
+            return true;
+        }
+        let (row, column) = (cell / 9, cell % 9);
+        let mut digits = [1, 2, 3, 4, 5, 6, 7, 8, 9];
+        shuffle(bitstream, &mut digits);
+        for &digit in &digits {
+            let (box_row, box_column) = (row / 3 * 3, column / 3 * 3);
+            let conflicts = (0..9).any(|index| {
+                grid[row][index] == digit
+                    || grid[index][column] == digit
+                    || grid[box_row + index / 3][box_column + index % 3] == digit
+            });
+            if !conflicts {
+                grid[row][column] = digit;
+                if fill(bitstream, grid, cell + 1) {
+                    return true;
+                }
+                grid[row][column] = 0;
+            }
+        }
+        false
+    }
+
+    let mut grid = [[0; 9]; 9];
+    fill(bitstream, &mut grid, 0);
+
+    let mut order = || {
+        let mut groups = [0, 1, 2];
+        shuffle(bitstream, &mut groups);
+        let mut lines = [0; 9];
+        for (index, group) in groups.iter().enumerate() {
+            let mut within = [0, 1, 2];
+            shuffle(bitstream, &mut within);
+            for (offset, line) in within.iter().enumerate() {
+                lines[index * 3 + offset] = group * 3 + line;
+            }
+        }
+        lines
+    };
+    let rows = order();
+    let columns = order();
+    let mut digits = [1, 2, 3, 4, 5, 6, 7, 8, 9];
+    shuffle(bitstream, &mut digits);
+    let transpose = bitstream.gen_bit();
+    let mut scrambled = [[0; 9]; 9];
+    for (row, &source_row) in rows.iter().enumerate() {
+        for (column, &source_column) in columns.iter().enumerate() {
+            let digit = digits[grid[source_row][source_column] as usize - 1];
+            if transpose {
+                scrambled[column][row] = digit;
+            } else {
+                scrambled[row][column] = digit;
+            }
+        }
+    }
+    scrambled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RngBitstream;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+    use std::collections::HashMap;
+
+    #[test]
+    fn squares_and_grids_are_valid_and_varied() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        // There are 12 Latin squares of order 3.
+        let mut counts = HashMap::new();
+        for _ in 0..12000 {
+            *counts.entry(latin_square(&mut bitstream, 3)).or_insert(0) += 1;
+        }
+        assert_eq!(counts.len(), 12);
+        assert!(counts.values().all(|&count| count > 850 && count < 1150));
+
+        let square = latin_square(&mut bitstream, 10);
+        for index in 0..10 {
+            let mut row: Vec<usize> = square[index].clone();
+            let mut column: Vec<usize> = square.iter().map(|row| row[index]).collect();
+            row.sort_unstable();
+            column.sort_unstable();
+            assert!(row.into_iter().eq(0..10) && column.into_iter().eq(0..10));
+        }
+
+        let first = sudoku(&mut bitstream);
+        assert_ne!(first, sudoku(&mut bitstream));
+        for index in 0..9 {
+            let mut row = first[index];
+            let mut column = [0; 9];
+            let mut block = [0; 9];
+            for other in 0..9 {
+                column[other] = first[other][index];
+                block[other] = first[index / 3 * 3 + other / 3][index % 3 * 3 + other % 3];
+            }
+            for group in [&mut row, &mut column, &mut block].iter_mut() {
+                group.sort_unstable();
+                assert_eq!(**group, [1, 2, 3, 4, 5, 6, 7, 8, 9]);
+            }
+        }
+    }
+}
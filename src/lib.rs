@@ -1,183 +1,572 @@
-use rand::Rng;
+use range::gen_range_throughput;
+use std::marker::PhantomData;
+
+mod backoff;
+mod bag;
+#[cfg(feature = "bench-util")]
+pub mod bench_util;
+mod bernoulli;
+#[cfg(feature = "num-bigint")]
+mod big;
+#[cfg(feature = "bitvec")]
+mod bit_slice;
+mod bounded;
+pub mod cards;
+mod chacha;
+mod circular;
+pub mod coding;
+mod collection;
+pub mod color;
+mod combination;
+mod combine;
+mod continuous;
+mod determinism;
+pub mod diagnostics;
+mod discrete;
+pub mod dist;
+pub mod dp;
+#[cfg(feature = "embedded-hal")]
+mod embedded;
+mod error;
+#[cfg(feature = "fairness")]
+pub mod fairness;
+mod fast;
+#[cfg(feature = "fastrand")]
+mod fastrand_bitstream;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod field;
+pub mod ga;
+pub mod geom;
+pub mod grammar;
+pub mod graph;
+#[cfg(all(feature = "rdrand", target_arch = "x86_64"))]
+mod hardware;
+pub mod latin;
+mod logits;
+pub mod loot;
+mod markov;
+pub mod matrix;
+pub mod mcmc;
+mod measure;
+#[cfg(feature = "memmap2")]
+mod mmap;
+mod names;
+pub mod net;
+pub mod noise;
+#[cfg(feature = "rayon")]
+mod parallel;
+#[cfg(feature = "pool")]
+mod pool;
+mod process;
+#[cfg(feature = "proptest")]
+mod property;
+#[cfg(feature = "python")]
+mod python;
+pub mod quasi;
+pub mod range;
+mod reader;
+pub mod record;
+mod rejection;
+pub mod sat;
+pub mod seq;
+pub mod source;
+#[cfg(feature = "async")]
+mod stream;
+pub mod text;
+#[cfg(feature = "std")]
+mod thread;
+mod variance;
+#[cfg(feature = "wasm")]
+mod wasm;
+mod weighted;
+
+pub use backoff::{Backoff, Jitter};
+pub use bag::{HistoryAvoiding, ShuffleBag};
+pub use bernoulli::Bernoulli;
+#[cfg(feature = "num-bigint")]
+pub use big::CurveOrder;
+#[cfg(feature = "bitvec")]
+pub use bit_slice::{gen_bitvec, BitSliceBitstream};
+pub use bounded::{Bounded, BoundedSample};
+pub use chacha::{ChaCha20, ChaChaBitstream};
+pub use circular::{UniformAngle, VonMises};
+pub use collection::{sample_map, sample_vec};
+pub use combine::{demultiplex, DemultiplexedBitstream, InterleavedBitstream, XorBitstream};
+pub use continuous::{
+    sorted_uniforms, Beta, ChiSquared, Dirichlet, Exponential, FisherF, Gamma, Normal,
+    OrderStatistic, Pert, StickBreaking, StudentT, Triangular,
+};
+pub use determinism::{Deterministic, Reproducible};
+pub use discrete::{multinomial, Binomial, Geometric};
+pub use dist::{Constant, Distribution, Samples};
+#[cfg(feature = "embedded-hal")]
+pub use embedded::HalRngBitstream;
+pub use error::Error;
+pub use fast::{SplitMix64, SplitMixBitstream, WyRand, WyRandBitstream};
+#[cfg(feature = "fastrand")]
+pub use fastrand_bitstream::{FastrandBitstream, FastrandThreadBitstream};
+pub use field::RandomField;
+#[cfg(all(feature = "rdrand", target_arch = "x86_64"))]
+pub use hardware::{HardwareBitstream, Instruction};
+pub use logits::LogitSampler;
+pub use markov::{MarkovChain, Walk};
+pub use mcmc::accept_metropolis;
+pub use measure::{measure, Measurement};
+#[cfg(feature = "memmap2")]
+pub use mmap::MmapBitstream;
+pub use names::NameGenerator;
+#[cfg(feature = "pool")]
+pub use pool::EntropyPool;
+pub use process::{ArrivalTimes, BrownianBridge, InhomogeneousPoisson, RandomWalk};
+#[cfg(feature = "proptest")]
+pub use property::{replay_rng, test_rng, test_runner, DistributionStrategy};
+pub use quasi::{Halton, QuasiBitstream, QuasiSequence, Sobol};
+pub use range::{ConstRange, EdgeBiasedRange, RangePlan, Strategy, UniformRange, Values};
+pub use reader::BitstreamReader;
+pub use record::{Draw, RecordingBitstream, ReplayBitstream, Tape};
+pub use rejection::{rejection_sample, RejectionStats};
+pub use source::{
+    BitOrder, Bits, BudgetedBitstream, CountingRngBitstream, DefaultRng, LsbFirst, MsbFirst,
+    PeekableBitstream, RngBitstream,
+};
+#[cfg(feature = "async")]
+pub use stream::SampleStream;
+#[cfg(feature = "std")]
+pub use thread::{gen_bits, gen_range, shuffle, thread_bitstream, ThreadBitstream};
+pub use variance::{Antithetic, CommonRandomNumbers};
+pub use weighted::{DyadicIndex, ExactWeightedIndex, IntervalHalvingIndex, WeightedIndex};
+
+pub mod prelude {
+    pub use crate::dist::Distribution;
+    pub use crate::range::{ConstRange, Strategy, UniformRange};
+    pub use crate::source::{CountingRngBitstream, DefaultRng, RngBitstream};
+    pub use crate::{Bitstream, BitstreamExt, PeekBitstream, TryBitstream, TryBitstreamExt};
+}
 
 pub trait Bitstream {
+    /// Returns `num_bits` random bits in the low bits of the result, with
+    /// the rest zero. `gen_bits(0)` returns 0 without drawing anything from
+    /// the underlying source.
+    ///
+    /// # Panics
+    ///
+    /// If `num_bits > 64`.
     fn gen_bits(&mut self, num_bits: u32) -> u64;
+
+    /// One fair coin flip. Counts as exactly one bit wherever bits are
+    /// counted, since the default goes through `gen_bits(1)`.
+    fn gen_bit(&mut self) -> bool {
+        self.gen_bits(1) == 1
+    }
+
+    /// Returns the precomputed sampling parameters for `gen_range(size)`.
+    /// Sources may override this to cache plans for recently used sizes.
+    fn range_plan(&mut self, size: u64) -> RangePlan {
+        RangePlan::new(size)
+    }
 }
 
-pub struct RngBitstream<T> {
-    rng: T,
-    bit_buffer: u64,
-    unused_bits: u32,
+/// A bitstream that can show its next bits without consuming them, for
+/// callers that need lookahead to decide what to sample next.
+pub trait PeekBitstream: Bitstream {
+    /// Returns what `gen_bits(num_bits)` would return, without advancing.
+    fn peek_bits(&mut self, num_bits: u32) -> u64;
 }
 
-pub struct CountingRngBitstream<T> {
-    bitstream: RngBitstream<T>,
-    count: u64,
+/// A bitstream whose draws can fail, e.g. because a finite source has run
+/// out of bits or a device read failed.
+pub trait TryBitstream {
+    fn try_gen_bits(&mut self, num_bits: u32) -> Result<u64, Error>;
 }
 
-impl<T> RngBitstream<T> {
-    pub fn new(rng: T) -> Self {
-        RngBitstream {
-            rng,
-            bit_buffer: 0,
-            unused_bits: 0,
-        }
-    }
+pub trait TryBitstreamExt {
+    fn try_gen_range(&mut self, size: u64) -> Result<u64, Error>;
 }
 
-impl<T: Rng> Bitstream for RngBitstream<T> {
-    fn gen_bits(&mut self, num_bits: u32) -> u64 {
-        let mut result = 0;
-        if self.unused_bits > 0 {
-            result |= self.bit_buffer >> (64 - self.unused_bits);
-            if num_bits < 64 {
-                result &= (1 << num_bits) - 1;
-            }
-        }
-        if num_bits <= self.unused_bits {
-            self.unused_bits -= num_bits;
-        } else {
-            let extra_bits = num_bits - self.unused_bits;
-            self.bit_buffer = self.rng.gen();
-            result |= self.bit_buffer << self.unused_bits;
-            if num_bits < 64 {
-                result &= (1 << num_bits) - 1;
-            }
-            self.unused_bits = 64 - extra_bits;
-        }
-        result
+impl<B: TryBitstream + ?Sized> TryBitstreamExt for B {
+    fn try_gen_range(&mut self, size: u64) -> Result<u64, Error> {
+        error::check_size(size)?;
+        RangePlan::new(size).try_sample(self)
     }
 }
 
-impl<T: Rng> Bitstream for CountingRngBitstream<T> {
-    fn gen_bits(&mut self, num_bits: u32) -> u64 {
-        self.count += num_bits as u64;
-        self.bitstream.gen_bits(num_bits)
+pub(crate) const fn low_bits_mask(num_bits: u32) -> u64 {
+    match u64::MAX.checked_shr(64 - num_bits) {
+        Some(mask) => mask,
+        None => 0,
     }
 }
 
 pub trait BitstreamExt {
     fn gen_range(&mut self, size: u64) -> u64;
+    fn gen_range_with(&mut self, size: u64, strategy: Strategy) -> u64;
+    /// `gen_range` for 32-bit sizes, computed in 32-bit arithmetic for
+    /// 32-bit targets such as wasm and microcontrollers. Returns the same
+    /// values as `gen_range`.
+    ///
+    /// # Panics
+    ///
+    /// If `size` is 0.
+    fn gen_range_u32(&mut self, size: u32) -> u32;
+    /// `gen_range_u32` for 16-bit sizes.
+    ///
+    /// # Panics
+    ///
+    /// If `size` is 0.
+    fn gen_range_u16(&mut self, size: u16) -> u16;
+    /// A uniform value in `low..high`.
+    ///
+    /// # Panics
+    ///
+    /// If `low >= high`.
+    fn gen_range_between(&mut self, low: u64, high: u64) -> u64;
+    /// A uniform value in `low..=high`, including full-width ranges like
+    /// `0..=u64::MAX` that have no `u64` size.
+    ///
+    /// # Panics
+    ///
+    /// If `low > high`.
+    fn gen_range_inclusive(&mut self, low: u64, high: u64) -> u64;
+    fn sample<T, D: Distribution<T>>(&mut self, distribution: D) -> T;
+    /// A uniform value of a `BoundedSample` type, like `sample(Bounded)`.
+    fn gen_bounded<T: BoundedSample>(&mut self) -> T;
+    /// `num_bools` independent coin flips as a bitmask: bit `i` of the
+    /// result is flip `i`.
+    ///
+    /// # Panics
+    ///
+    /// If `num_bools > 64`.
+    fn gen_bools(&mut self, num_bools: u32) -> u64;
+    /// A uniformly random `width`-bit word with exactly `weight` bits set,
+    /// using about `log2(width choose weight)` bits.
+    ///
+    /// # Panics
+    ///
+    /// If `width > 64` or `weight > width`.
+    fn gen_bits_with_weight(&mut self, width: u32, weight: u32) -> u64;
+    /// A level for a new skip-list node, from 1 to `max_level`: each level
+    /// above 1 is reached with probability `1 / 2^log2_branching` from the
+    /// one below. For branching factors 2 and 4, this takes 2 bits on
+    /// average.
+    ///
+    /// # Panics
+    ///
+    /// If `max_level` is 0 or `log2_branching` isn't in `1..=64`.
+    fn gen_skip_list_level(&mut self, log2_branching: u32, max_level: u32) -> u32;
+    /// A uniform integer of `num_bits` bits.
+    #[cfg(feature = "num-bigint")]
+    fn gen_biguint(&mut self, num_bits: u64) -> num_bigint::BigUint;
+    /// A uniform integer in `0..size`, by the same entropy-frugal rejection
+    /// sampling as `gen_range`.
+    ///
+    /// # Panics
+    ///
+    /// If `size` is 0.
+    #[cfg(feature = "num-bigint")]
+    fn gen_biguint_range(&mut self, size: &num_bigint::BigUint) -> num_bigint::BigUint;
+    /// A random odd prime of exactly `num_bits` bits. The Miller–Rabin bases
+    /// come from this stream too, so key generation replays exactly.
+    ///
+    /// # Panics
+    ///
+    /// If `num_bits` is less than 2.
+    #[cfg(feature = "num-bigint")]
+    fn gen_prime(&mut self, num_bits: u64) -> num_bigint::BigUint;
+    /// A uniform residue in `0..modulus`, without the bias of reducing a
+    /// fixed number of random bits mod `modulus`.
+    ///
+    /// # Panics
+    ///
+    /// If `modulus` is 0.
+    #[cfg(feature = "num-bigint")]
+    fn gen_below_modulus(&mut self, modulus: &num_bigint::BigUint) -> num_bigint::BigUint;
+    /// A uniform nonzero scalar mod the curve order, as 32 big-endian bytes
+    /// (reverse them for Ed25519, which is little-endian).
+    #[cfg(feature = "num-bigint")]
+    fn gen_scalar(&mut self, order: &CurveOrder) -> [u8; 32];
+    /// `gen_scalar` for secret keys: a fixed number of bits per attempt and
+    /// no branches on the candidate's value, at the cost of discarding whole
+    /// attempts instead of recycling their entropy.
+    #[cfg(feature = "num-bigint")]
+    fn gen_scalar_constant_time(&mut self, order: &CurveOrder) -> [u8; 32];
+    /// A priority for a new treap node.
+    fn gen_treap_priority(&mut self) -> u64;
+    /// `k` independent 64-bit seeds, e.g. for the hash functions of a Bloom
+    /// filter or Count-Min sketch.
+    fn derive_seeds(&mut self, k: usize) -> Vec<u64>;
+    /// One seed per label, from only 64 bits of the stream. A label's seed
+    /// doesn't depend on the other labels or their order, so hash functions
+    /// can be added or removed without disturbing the rest.
+    fn derive_labeled_seeds<L: AsRef<[u8]>>(&mut self, labels: &[L]) -> Vec<u64>;
+    /// Fills `bytes` with the next bits of the stream, 8 bits per byte,
+    /// lowest bits first.
+    fn fill_bytes(&mut self, bytes: &mut [u8]);
+    /// Shuffles `slice` uniformly (Fisher-Yates).
+    fn shuffle<T>(&mut self, slice: &mut [T]);
+    /// Shuffles `items` with heavier items tending to come first; see
+    /// `seq::weighted_shuffle`.
+    ///
+    /// # Panics
+    ///
+    /// If `weights` isn't as long as `items`, or a weight is negative or
+    /// not finite.
+    fn weighted_shuffle<T>(&mut self, items: &mut [T], weights: &[f64]);
+    /// Rounds `x` down or up to an integer, up with probability equal to its
+    /// fractional part, using 2 bits on average.
+    fn stochastic_round(&mut self, x: f64) -> i64;
+    /// Like `stochastic_round`, for the fixed-point value
+    /// `value / 2^fraction_bits`.
+    fn stochastic_round_fixed(&mut self, value: i64, fraction_bits: u32) -> i64;
+    /// Fills a slice of plain-old-data values with random bytes, as if by
+    /// `fill_bytes` on its in-memory representation.
+    #[cfg(feature = "bytemuck")]
+    fn fill_slice<T: bytemuck::Pod>(&mut self, values: &mut [T]);
+
+    /// An endless iterator over single bits.
+    fn bits(&mut self) -> Bits<'_, Self>;
+    /// An endless iterator over values in `0..size`.
+    fn values(&mut self, size: u64) -> Values<'_, Self>;
+    /// An endless iterator over samples of `distribution`.
+    fn samples<T, D: Distribution<T>>(&mut self, distribution: D) -> Samples<'_, Self, D, T>;
 }
 
-impl<B: Bitstream> BitstreamExt for B {
+impl<B: Bitstream + ?Sized> BitstreamExt for B {
     fn gen_range(&mut self, size: u64) -> u64 {
-        let size_leading_zeros = (size - 1).leading_zeros();
-        let bits_needed = 64 - size_leading_zeros;
-        let mut leftover: u64 = self.gen_bits(bits_needed);
-        if leftover < size {
-            return leftover;
+        self.range_plan(size).sample(self)
+    }
+
+    fn gen_range_with(&mut self, size: u64, strategy: Strategy) -> u64 {
+        match strategy {
+            Strategy::EntropyOptimal => self.gen_range(size),
+            Strategy::Throughput => gen_range_throughput(self, size),
         }
-        leftover -= size;
-        let mut leftover_size: u64 = (1 << bits_needed) - size;
-        loop {
-            // We need to increase leftover_size to >= size, by adding bits.
-            // We could do some fancy leading_zeros thing for this,
-            // but the expected value of bits needed given that we reach this code
-            // is only something like 2, so the loop is faster.
-            let mut bits_needed = 1;
-            while (leftover_size << bits_needed) < size {
-                bits_needed += 1;
-            }
-            leftover += self.gen_bits(bits_needed) * leftover_size;
-            if leftover < size {
-                return leftover;
-            }
-            leftover_size <<= bits_needed;
-            leftover -= size;
-            leftover_size -= size;
+    }
+
+    fn gen_range_u32(&mut self, size: u32) -> u32 {
+        range::gen_range_u32(self, size)
+    }
+
+    fn gen_range_u16(&mut self, size: u16) -> u16 {
+        range::gen_range_u32(self, size as u32) as u16
+    }
+
+    fn gen_range_between(&mut self, low: u64, high: u64) -> u64 {
+        assert!(low < high, "can't sample from an empty range");
+        low + self.gen_range(high - low)
+    }
+
+    fn gen_range_inclusive(&mut self, low: u64, high: u64) -> u64 {
+        range::gen_range_inclusive(self, low, high)
+    }
+
+    fn sample<T, D: Distribution<T>>(&mut self, distribution: D) -> T {
+        distribution.sample(self)
+    }
+
+    fn gen_bounded<T: BoundedSample>(&mut self) -> T {
+        Bounded.sample(self)
+    }
+
+    fn gen_bools(&mut self, num_bools: u32) -> u64 {
+        assert!(num_bools <= 64, "can't fit more than 64 bools in a u64");
+        self.gen_bits(num_bools)
+    }
+
+    fn gen_bits_with_weight(&mut self, width: u32, weight: u32) -> u64 {
+        combination::gen_bits_with_weight(self, width, weight)
+    }
+
+    fn gen_skip_list_level(&mut self, log2_branching: u32, max_level: u32) -> u32 {
+        assert!(max_level > 0, "max_level must be positive");
+        assert!(
+            (1..=64).contains(&log2_branching),
+            "log2_branching must be in 1..=64"
+        );
+        let promote = Bernoulli::dyadic(1, log2_branching);
+        let mut level = 1;
+        while level < max_level && promote.sample(self) {
+            level += 1;
+        }
+        level
+    }
+
+    #[cfg(feature = "num-bigint")]
+    fn gen_biguint(&mut self, num_bits: u64) -> num_bigint::BigUint {
+        big::gen_biguint(self, num_bits)
+    }
+
+    #[cfg(feature = "num-bigint")]
+    fn gen_biguint_range(&mut self, size: &num_bigint::BigUint) -> num_bigint::BigUint {
+        big::gen_biguint_range(self, size)
+    }
+
+    #[cfg(feature = "num-bigint")]
+    fn gen_prime(&mut self, num_bits: u64) -> num_bigint::BigUint {
+        big::gen_prime(self, num_bits)
+    }
+
+    #[cfg(feature = "num-bigint")]
+    fn gen_below_modulus(&mut self, modulus: &num_bigint::BigUint) -> num_bigint::BigUint {
+        big::gen_biguint_range(self, modulus)
+    }
+
+    #[cfg(feature = "num-bigint")]
+    fn gen_scalar(&mut self, order: &CurveOrder) -> [u8; 32] {
+        big::gen_scalar(self, order)
+    }
+
+    #[cfg(feature = "num-bigint")]
+    fn gen_scalar_constant_time(&mut self, order: &CurveOrder) -> [u8; 32] {
+        big::gen_scalar_constant_time(self, order)
+    }
+
+    fn gen_treap_priority(&mut self) -> u64 {
+        self.gen_bits(64)
+    }
+
+    fn derive_seeds(&mut self, k: usize) -> Vec<u64> {
+        (0..k).map(|_| self.gen_bits(64)).collect()
+    }
+
+    fn derive_labeled_seeds<L: AsRef<[u8]>>(&mut self, labels: &[L]) -> Vec<u64> {
+        let master = self.gen_bits(64);
+        labels
+            .iter()
+            .map(|label| source::mix64(master ^ source::mix64(source::stable_hash(label.as_ref()))))
+            .collect()
+    }
+
+    fn fill_bytes(&mut self, bytes: &mut [u8]) {
+        let mut chunks = bytes.chunks_exact_mut(8);
+        for chunk in &mut chunks {
+            chunk.copy_from_slice(&self.gen_bits(64).to_le_bytes());
+        }
+        for byte in chunks.into_remainder() {
+            *byte = self.gen_bits(8) as u8;
+        }
+    }
+
+    fn shuffle<T>(&mut self, slice: &mut [T]) {
+        seq::shuffle(self, slice)
+    }
+
+    fn weighted_shuffle<T>(&mut self, items: &mut [T], weights: &[f64]) {
+        seq::weighted_shuffle(self, items, weights)
+    }
+
+    fn stochastic_round(&mut self, x: f64) -> i64 {
+        bernoulli::stochastic_round(self, x)
+    }
+
+    fn stochastic_round_fixed(&mut self, value: i64, fraction_bits: u32) -> i64 {
+        bernoulli::stochastic_round_fixed(self, value, fraction_bits)
+    }
+
+    #[cfg(feature = "bytemuck")]
+    fn fill_slice<T: bytemuck::Pod>(&mut self, values: &mut [T]) {
+        self.fill_bytes(bytemuck::cast_slice_mut(values));
+    }
+
+    fn bits(&mut self) -> Bits<'_, Self> {
+        Bits { bitstream: self }
+    }
+
+    fn values(&mut self, size: u64) -> Values<'_, Self> {
+        Values {
+            plan: self.range_plan(size),
+            bitstream: self,
+        }
+    }
+
+    fn samples<T, D: Distribution<T>>(&mut self, distribution: D) -> Samples<'_, Self, D, T> {
+        Samples {
+            bitstream: self,
+            distribution,
+            _marker: PhantomData,
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{Bitstream, BitstreamExt, CountingRngBitstream, RngBitstream};
-    use rand::{Rng, SeedableRng};
+    use crate::prelude::*;
+    use rand::SeedableRng;
     use rand_chacha::ChaChaRng;
 
     #[test]
-    fn gen_bits_gens_reasonably_distributed_bits() {
-        let mut rng = ChaChaRng::seed_from_u64(0);
-        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
-        let mut buckets: Vec<_> = (0..=64).map(|_| Vec::new()).collect();
-        for _ in 0..1000000 {
-            let num_bits = rng.gen_range(0..=64);
-            let bits = bitstream.gen_bits(num_bits as u32);
-            let mask = if num_bits == 64 {
-                u64::MAX
-            } else {
-                (1 << num_bits) - 1
-            };
-            assert_eq!(
-                bits & mask,
-                bits,
-                "Bits spilled over ({}):\n{:b}\n{:b}",
-                num_bits,
-                mask,
-                bits
-            );
-            buckets[num_bits as usize].push(bits);
+    fn iterators_match_direct_calls() {
+        let mut direct = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let mut iterated = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let bits: Vec<bool> = iterated.bits().take(100).collect();
+        let values: Vec<u64> = iterated.values(6).take(1000).collect();
+        let samples: Vec<u64> = iterated.samples(ConstRange::<10>).take(1000).collect();
+        assert!(bits.iter().all(|&bit| bit == (direct.gen_bits(1) == 1)));
+        assert!(values.iter().all(|&value| value == direct.gen_range(6)));
+        assert!(samples.iter().all(|&sample| sample == direct.gen_range(10)));
+    }
+
+    #[test]
+    fn gen_bit_counts_as_one_bit() {
+        let mut counting = CountingRngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let mut direct = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        for _ in 0..100 {
+            assert_eq!(counting.gen_bit(), direct.gen_bits(1) == 1);
         }
-        for (num_bits, bucket) in buckets.into_iter().enumerate() {
-            for bit_index in 0..num_bits {
-                let count = bucket
-                    .iter()
-                    .filter(|&&b| (b & (1 << bit_index)) != 0)
-                    .count();
-                let frequency = count as f64 / bucket.len() as f64;
-                assert!(
-                    frequency > 0.4 && frequency < 0.6,
-                    "extreme frequency {} at bit index {}/{}",
-                    frequency,
-                    bit_index,
-                    num_bits
-                );
+        assert_eq!(counting.count(), 100);
+        assert_eq!(counting.gen_bools(40), direct.gen_bits(40));
+        assert_eq!(counting.count(), 140);
+    }
+
+    #[test]
+    fn skip_list_levels_are_geometric_and_cheap() {
+        let mut bitstream = CountingRngBitstream::new(ChaChaRng::seed_from_u64(0));
+        for &log2_branching in &[1, 2] {
+            let num_samples = 100000;
+            let before = bitstream.count();
+            let mut counts = [0; 5];
+            for _ in 0..num_samples {
+                counts[bitstream.gen_skip_list_level(log2_branching, 4) as usize] += 1;
+            }
+            let bits = (bitstream.count() - before) as f64 / num_samples as f64;
+            assert!(bits < 2.0, "{}", bits);
+            let p = 0.5f64.powi(log2_branching as i32);
+            let expected = [0.0, 1.0 - p, p * (1.0 - p), p * p * (1.0 - p), p * p * p];
+            for (count, expected) in counts.iter().zip(&expected) {
+                assert!((*count as f64 / num_samples as f64 - expected).abs() < 0.01);
             }
         }
+        assert_eq!(bitstream.gen_skip_list_level(1, 1), 1);
     }
 
     #[test]
-    fn gen_range_gens_reasonably_distributed_values() {
-        let mut rng = ChaChaRng::seed_from_u64(0);
+    fn derived_seeds_are_reproducible() {
+        let mut bitstream = CountingRngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let seeds = bitstream.derive_seeds(4);
+        assert_eq!(bitstream.count(), 256);
+        let mut again = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        assert_eq!(again.derive_seeds(4), seeds);
+
         let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
-        let mut buckets: Vec<Vec<u64>> = (0..18)
-            .map(|range_size| (0..range_size).map(|_| 0).collect())
-            .collect();
-        for _ in 0..1000000 {
-            let range_size = rng.gen_range(1..18);
-            let value = bitstream.gen_range(range_size as u64);
-            assert!(value < range_size);
-            buckets[range_size as usize][value as usize] += 1;
-        }
-        dbg!(&buckets);
-        for (range_size, bucket) in buckets.into_iter().enumerate() {
-            let total_count = bucket.iter().sum::<u64>();
-            for (value, &count) in bucket.iter().enumerate() {
-                let share = count as f64 * range_size as f64 / total_count as f64;
-                assert!(
-                    share > 0.9 && share < 1.1,
-                    "extreme frequency {} at value {}/{}",
-                    share,
-                    value,
-                    range_size
-                );
-            }
-        }
+        let labeled = bitstream.derive_labeled_seeds(&["bloom", "count-min", "table"]);
+        let mut again = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let reordered = again.derive_labeled_seeds(&["table", "bloom"]);
+        assert_eq!(reordered, [labeled[2], labeled[0]]);
+        assert!(labeled[0] != labeled[1] && labeled[1] != labeled[2]);
     }
 
+    #[cfg(feature = "bytemuck")]
     #[test]
-    fn gen_range_uses_reasonable_bit_counts() {
-        for range_size in 1..=17 {
-            let mut bitstream = CountingRngBitstream {
-                bitstream: RngBitstream::new(ChaChaRng::seed_from_u64(0)),
-                count: 0,
-            };
-            for _ in 0..10000 {
-                bitstream.gen_range(range_size as u64);
-            }
-            dbg!((range_size, bitstream.count));
-            assert!(bitstream.count as f64 <= (range_size as f64).log2() * 2.0 * 10000.0,);
-        }
+    fn fill_slice_matches_fill_bytes() {
+        let mut direct = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let mut typed = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let mut bytes = [0u8; 16 * 5];
+        let mut blocks = [[0u8; 16]; 5];
+        direct.fill_bytes(&mut bytes);
+        typed.fill_slice(&mut blocks);
+        assert_eq!(&bytes[..], blocks.concat());
+        let mut words = [0u32; 7];
+        typed.fill_slice(&mut words);
+        assert!(words.iter().any(|&word| word != 0));
     }
 }
@@ -0,0 +1,182 @@
+use crate::dist::open_unit_f64;
+use crate::Bitstream;
+
+/// Samples an index from a slice of logits, as softmax sampling does in
+/// language model decoding, with optional temperature and top-k/top-p
+/// truncation.
+///
+/// Sampling uses the Gumbel-max trick: each remaining candidate, in index
+/// order, gets one 52-bit uniform draw, so the number of bits used depends
+/// only on how many candidates survive truncation.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct LogitSampler {
+    temperature: f64,
+    top_k: Option<usize>,
+    top_p: Option<f64>,
+}
+
+impl Default for LogitSampler {
+    fn default() -> Self {
+        LogitSampler::new()
+    }
+}
+
+impl LogitSampler {
+    /// Plain softmax sampling: temperature 1, no truncation.
+    pub fn new() -> Self {
+        LogitSampler {
+            temperature: 1.0,
+            top_k: None,
+            top_p: None,
+        }
+    }
+
+    /// Divides the logits by `temperature` before sampling. Temperature 0
+    /// always picks the (first) largest logit, without using any bits.
+    ///
+    /// # Panics
+    ///
+    /// If `temperature` is negative or NaN.
+    pub fn with_temperature(self, temperature: f64) -> Self {
+        assert!(temperature >= 0.0, "temperature must be nonnegative");
+        LogitSampler {
+            temperature,
+            ..self
+        }
+    }
+
+    /// Only samples among the `k` largest logits.
+    ///
+    /// # Panics
+    ///
+    /// If `k` is 0.
+    pub fn with_top_k(self, k: usize) -> Self {
+        assert!(k > 0, "top_k must be positive");
+        LogitSampler {
+            top_k: Some(k),
+            ..self
+        }
+    }
+
+    /// Only samples among the most likely logits whose probabilities (after
+    /// temperature and top-k) add up to at least `p`.
+    ///
+    /// # Panics
+    ///
+    /// If `p` isn't in `(0, 1]`.
+    pub fn with_top_p(self, p: f64) -> Self {
+        assert!(p > 0.0 && p <= 1.0, "top_p must be in (0, 1]");
+        LogitSampler {
+            top_p: Some(p),
+            ..self
+        }
+    }
+
+    /// Returns the index of the chosen logit. Logits of negative infinity are
+    /// never chosen unless all of them are; NaN logits aren't supported.
+    ///
+    /// # Panics
+    ///
+    /// If `logits` is empty.
+    pub fn sample<B: Bitstream + ?Sized, L: Copy + Into<f64>>(
+        &self,
+        bitstream: &mut B,
+        logits: &[L],
+    ) -> usize {
+        assert!(!logits.is_empty(), "can't sample from no logits");
+        if self.temperature == 0.0 {
+            return argmax(logits.iter().map(|&logit| logit.into()).enumerate());
+        }
+        let mut candidates: Vec<(usize, f64)> = logits
+            .iter()
+            .map(|&logit| logit.into() / self.temperature)
+            .enumerate()
+            .collect();
+        if self.top_k.is_some() || self.top_p.is_some() {
+            // Ties go to the lower index, so truncation is deterministic.
+            candidates.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+            if let Some(k) = self.top_k {
+                candidates.truncate(k);
+            }
+            if let Some(p) = self.top_p {
+                let max = candidates[0].1;
+                let weights: Vec<f64> = candidates.iter().map(|&(_, l)| (l - max).exp()).collect();
+                let threshold = p * weights.iter().sum::<f64>();
+                let mut total = 0.0;
+                let kept = weights
+                    .iter()
+                    .take_while(|&&weight| {
+                        let keep = total < threshold;
+                        total += weight;
+                        keep
+                    })
+                    .count();
+                candidates.truncate(kept.max(1));
+            }
+            candidates.sort_by_key(|&(index, _)| index);
+        }
+        argmax(candidates.into_iter().map(|(index, logit)| {
+            let gumbel = -(-open_unit_f64(bitstream).ln()).ln();
+            (index, logit + gumbel)
+        }))
+    }
+}
+
+/// The index of the first largest value.
+fn argmax(values: impl Iterator<Item = (usize, f64)>) -> usize {
+    values
+        .fold((0, f64::NEG_INFINITY), |best, (index, value)| {
+            if value > best.1 {
+                (index, value)
+            } else {
+                best
+            }
+        })
+        .0
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{LogitSampler, RngBitstream};
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    fn frequencies(sampler: LogitSampler, logits: &[f32]) -> Vec<f64> {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let mut counts = vec![0; logits.len()];
+        for _ in 0..60000 {
+            counts[sampler.sample(&mut bitstream, logits)] += 1;
+        }
+        counts.iter().map(|&count| count as f64 / 60000.0).collect()
+    }
+
+    #[test]
+    fn samples_softmax_with_truncation() {
+        let logits = [1f32.ln(), 2f32.ln(), 3f32.ln(), f32::NEG_INFINITY];
+        let check = |sampler, expected: &[f64]| {
+            for (frequency, expected) in frequencies(sampler, &logits).iter().zip(expected) {
+                assert!(
+                    (frequency - expected).abs() < 0.01,
+                    "{} vs {}",
+                    frequency,
+                    expected
+                );
+            }
+        };
+        check(LogitSampler::new(), &[1. / 6., 2. / 6., 3. / 6., 0.]);
+        check(
+            LogitSampler::new().with_temperature(0.5),
+            &[1. / 14., 4. / 14., 9. / 14., 0.],
+        );
+        check(LogitSampler::new().with_temperature(0.0), &[0., 0., 1., 0.]);
+        check(
+            LogitSampler::new().with_top_k(2),
+            &[0., 2. / 5., 3. / 5., 0.],
+        );
+        check(LogitSampler::new().with_top_p(0.5), &[0., 0., 1., 0.]);
+        check(
+            LogitSampler::new().with_top_p(0.6),
+            &[0., 2. / 5., 3. / 5., 0.],
+        );
+    }
+}
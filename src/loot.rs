@@ -0,0 +1,247 @@
+//! Loot tables for games: weighted entries with exact integer weights,
+//! nested tables, guaranteed drops and pity timers, reporting how many random
+//! bits each roll used.
+
+use crate::source::BitMeter;
+use crate::{Bitstream, Distribution, WeightedIndex};
+
+pub enum Entry<T> {
+    Item(T),
+    /// Rolls once on a nested table.
+    Table(LootTable<T>),
+    Nothing,
+}
+
+/// A table of entries picked with probability `weight / total_weight`.
+pub struct LootTable<T> {
+    entries: Vec<Entry<T>>,
+    weights: Vec<u64>,
+    // Rebuilt as entries are added; None until the first one.
+    index: Option<WeightedIndex>,
+    // Per entry: guarantee it after this many picks without it.
+    pity: Vec<Option<u32>>,
+    guaranteed: Vec<T>,
+    picks: u32,
+}
+
+/// What a roll dropped, and how many bits it took.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Roll<'a, T> {
+    pub items: Vec<&'a T>,
+    pub bits_used: u64,
+}
+
+impl<T> Default for LootTable<T> {
+    fn default() -> Self {
+        LootTable::new()
+    }
+}
+
+impl<T> LootTable<T> {
+    pub fn new() -> Self {
+        LootTable {
+            entries: Vec::new(),
+            weights: Vec::new(),
+            index: None,
+            pity: Vec::new(),
+            guaranteed: Vec::new(),
+            picks: 1,
+        }
+    }
+
+    /// # Panics
+    ///
+    /// If the total weight overflows a `u64`.
+    pub fn with_entry(mut self, weight: u64, entry: Entry<T>) -> Self {
+        self.entries.push(entry);
+        self.weights.push(weight);
+        self.pity.push(None);
+        if self.weights.iter().any(|&weight| weight > 0) {
+            self.index = Some(WeightedIndex::new(&self.weights));
+        }
+        self
+    }
+
+    pub fn with_item(self, weight: u64, item: T) -> Self {
+        self.with_entry(weight, Entry::Item(item))
+    }
+
+    pub fn with_table(self, weight: u64, table: LootTable<T>) -> Self {
+        self.with_entry(weight, Entry::Table(table))
+    }
+
+    pub fn with_nothing(self, weight: u64) -> Self {
+        self.with_entry(weight, Entry::Nothing)
+    }
+
+    /// Makes the most recently added entry a sure thing once it has failed to
+    /// come up `after` times in a row. Pity only applies when rolling through
+    /// a `Looter`, which remembers the misses.
+    ///
+    /// # Panics
+    ///
+    /// If there are no entries yet, or `after` is 0.
+    pub fn with_pity(mut self, after: u32) -> Self {
+        assert!(after > 0, "pity must take at least one miss");
+        *self.pity.last_mut().expect("no entry to add pity to") = Some(after);
+        self
+    }
+
+    /// An item dropped by every roll, in addition to the picks.
+    pub fn with_guaranteed(mut self, item: T) -> Self {
+        self.guaranteed.push(item);
+        self
+    }
+
+    /// How many entries each roll picks, independently (default 1).
+    pub fn with_picks(self, picks: u32) -> Self {
+        LootTable { picks, ..self }
+    }
+
+    /// # Panics
+    ///
+    /// If the table (or a nested table that comes up) has no entries with
+    /// positive weight, but needs to pick one.
+    pub fn roll<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> Roll<'_, T> {
+        let mut counting = BitMeter::new(bitstream);
+        let mut items = Vec::new();
+        self.roll_into(&mut counting, &mut |_| None, &mut items);
+        Roll {
+            items,
+            bits_used: counting.bits(),
+        }
+    }
+
+    /// Tracks pity timers across rolls of this table.
+    pub fn looter(&self) -> Looter<'_, T> {
+        Looter {
+            misses: vec![0; self.entries.len()],
+            table: self,
+        }
+    }
+
+    fn roll_into<'a, B: Bitstream + ?Sized>(
+        &'a self,
+        bitstream: &mut B,
+        pick: &mut dyn FnMut(&mut B) -> Option<usize>,
+        items: &mut Vec<&'a T>,
+    ) {
+        items.extend(&self.guaranteed);
+        for _ in 0..self.picks {
+            let index = match pick(bitstream) {
+                Some(index) => index,
+                None => self
+                    .index
+                    .as_ref()
+                    .expect("loot table has no entries with positive weight")
+                    .sample(bitstream),
+            };
+            match &self.entries[index] {
+                Entry::Item(item) => items.push(item),
+                Entry::Table(table) => table.roll_into(bitstream, &mut |_| None, items),
+                Entry::Nothing => {}
+            }
+        }
+    }
+}
+
+/// Rolls a table while keeping its pity timers.
+pub struct Looter<'a, T> {
+    table: &'a LootTable<T>,
+    misses: Vec<u32>,
+}
+
+impl<'a, T> Looter<'a, T> {
+    pub fn roll<B: Bitstream + ?Sized>(&mut self, bitstream: &mut B) -> Roll<'a, T> {
+        let table = self.table;
+        let misses = &mut self.misses;
+        let mut counting = BitMeter::new(bitstream);
+        let mut items = Vec::new();
+        let mut pick = |bitstream: &mut BitMeter<'_, B>| {
+            let forced = table
+                .pity
+                .iter()
+                .zip(misses.iter())
+                .position(|(pity, &missed)| matches!(pity, Some(after) if missed >= *after));
+            let index = forced.unwrap_or_else(|| {
+                table
+                    .index
+                    .as_ref()
+                    .expect("loot table has no entries with positive weight")
+                    .sample(bitstream)
+            });
+            for (entry, missed) in misses.iter_mut().enumerate() {
+                *missed = if entry == index { 0 } else { *missed + 1 };
+            }
+            Some(index)
+        };
+        table.roll_into(&mut counting, &mut pick, &mut items);
+        Roll {
+            items,
+            bits_used: counting.bits(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CountingRngBitstream;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn loot_tables_drop_by_weight_with_pity() {
+        let gems = LootTable::new()
+            .with_item(1, "ruby")
+            .with_item(3, "emerald");
+        let table = LootTable::new()
+            .with_guaranteed("gold")
+            .with_nothing(5)
+            .with_table(2, gems)
+            .with_item(1, "sword")
+            .with_pity(20)
+            .with_picks(2);
+
+        let mut bitstream = CountingRngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let mut counts = std::collections::HashMap::new();
+        let mut bits_used = 0;
+        let num_rolls = 40000;
+        for _ in 0..num_rolls {
+            let roll = table.roll(&mut bitstream);
+            assert_eq!(roll.items[0], &"gold");
+            for &&item in &roll.items[1..] {
+                *counts.entry(item).or_insert(0) += 1;
+            }
+            bits_used += roll.bits_used;
+        }
+        assert_eq!(bits_used, bitstream.count());
+        let picks = (2 * num_rolls) as f64;
+        for &(item, probability) in &[
+            ("sword", 1.0 / 8.0),
+            ("ruby", 1.0 / 16.0),
+            ("emerald", 3.0 / 16.0),
+        ] {
+            let frequency = counts[item] as f64 / picks;
+            assert!(
+                (frequency - probability).abs() < 0.01,
+                "{} {}",
+                item,
+                frequency
+            );
+        }
+
+        let mut looter = table.looter();
+        let mut since_sword = 0;
+        for _ in 0..num_rolls {
+            for &&item in &looter.roll(&mut bitstream).items[1..] {
+                if item == "sword" {
+                    since_sword = 0;
+                } else {
+                    since_sword += 1;
+                }
+            }
+            assert!(since_sword <= 20);
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use crate::{Bitstream, Distribution, WeightedIndex};
+
+/// A finite Markov chain: from each state, the next state is picked with
+/// probability proportional to that state's row of transition weights.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct MarkovChain {
+    transitions: Vec<WeightedIndex>,
+    state: usize,
+}
+
+impl MarkovChain {
+    /// `transitions[from][to]` is the weight of moving from `from` to `to`;
+    /// each row is normalized separately, so rows of integer weights give
+    /// exact rational probabilities.
+    ///
+    /// # Panics
+    ///
+    /// If the matrix isn't square, a row is all zeros, or `initial_state` is
+    /// out of range.
+    pub fn new<R: AsRef<[u64]>>(transitions: &[R], initial_state: usize) -> Self {
+        MarkovChain::from_rows(
+            transitions.iter().map(|row| {
+                assert_eq!(
+                    row.as_ref().len(),
+                    transitions.len(),
+                    "transition matrix must be square"
+                );
+                WeightedIndex::new(row.as_ref())
+            }),
+            initial_state,
+        )
+    }
+
+    /// Like `new`, with float weights as in `WeightedIndex::from_f64`.
+    pub fn from_f64<R: AsRef<[f64]>>(transitions: &[R], initial_state: usize) -> Self {
+        MarkovChain::from_rows(
+            transitions.iter().map(|row| {
+                assert_eq!(
+                    row.as_ref().len(),
+                    transitions.len(),
+                    "transition matrix must be square"
+                );
+                WeightedIndex::from_f64(row.as_ref())
+            }),
+            initial_state,
+        )
+    }
+
+    fn from_rows(rows: impl Iterator<Item = WeightedIndex>, initial_state: usize) -> Self {
+        let transitions: Vec<WeightedIndex> = rows.collect();
+        assert!(
+            initial_state < transitions.len(),
+            "initial state out of range"
+        );
+        MarkovChain {
+            transitions,
+            state: initial_state,
+        }
+    }
+
+    pub fn num_states(&self) -> usize {
+        self.transitions.len()
+    }
+
+    pub fn state(&self) -> usize {
+        self.state
+    }
+
+    /// # Panics
+    ///
+    /// If `state` is out of range.
+    pub fn set_state(&mut self, state: usize) {
+        assert!(state < self.num_states(), "state out of range");
+        self.state = state;
+    }
+
+    /// Moves to and returns the next state.
+    pub fn step<B: Bitstream + ?Sized>(&mut self, bitstream: &mut B) -> usize {
+        self.state = self.transitions[self.state].sample(bitstream);
+        self.state
+    }
+
+    /// An endless iterator over the states the chain steps to.
+    pub fn walk<'a, B: Bitstream + ?Sized>(&'a mut self, bitstream: &'a mut B) -> Walk<'a, B> {
+        Walk {
+            chain: self,
+            bitstream,
+        }
+    }
+}
+
+pub struct Walk<'a, B: ?Sized> {
+    chain: &'a mut MarkovChain,
+    bitstream: &'a mut B,
+}
+
+impl<'a, B: Bitstream + ?Sized> Iterator for Walk<'a, B> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        Some(self.chain.step(self.bitstream))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{MarkovChain, RngBitstream};
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn walk_reaches_the_stationary_distribution() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        // Stationary distribution: 2/3 in state 0, 1/3 in state 1.
+        let mut chain = MarkovChain::new(&[[3, 1], [1, 1]], 1);
+        let mut counts = [0; 2];
+        for state in chain.walk(&mut bitstream).take(90000) {
+            counts[state] += 1;
+        }
+        assert!((counts[0] - 60000i32).abs() < 1500, "{:?}", counts);
+        let mut deterministic = MarkovChain::from_f64(&[[0.0, 1.0], [1.0, 0.0]], 0);
+        assert_eq!(deterministic.step(&mut bitstream), 1);
+        assert_eq!(deterministic.step(&mut bitstream), 0);
+    }
+}
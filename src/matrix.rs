@@ -0,0 +1,215 @@
+//! Random matrices for testing numerical code, as `Vec`s of rows.
+
+use crate::continuous::standard_normal;
+use crate::{Beta, Bitstream, Dirichlet, Distribution};
+
+/// An `n` by `n` row-stochastic matrix: each row is an independent
+/// symmetric Dirichlet sample with the given concentration, so rows are
+/// nonnegative and sum to 1. Concentrations below 1 give sparse-looking
+/// rows, and large ones rows close to uniform.
+///
+/// # Panics
+///
+/// If `concentration` isn't positive and finite.
+pub fn stochastic_matrix<B: Bitstream + ?Sized>(
+    bitstream: &mut B,
+    n: usize,
+    concentration: f64,
+) -> Vec<Vec<f64>> {
+    assert!(
+        concentration > 0.0 && concentration.is_finite(),
+        "concentration must be positive and finite"
+    );
+    if n == 0 {
+        return Vec::new();
+    }
+    let row = Dirichlet::symmetric(concentration, n);
+    (0..n).map(|_| row.sample(bitstream)).collect()
+}
+
+/// A `dimension` by `dimension` correlation matrix from the LKJ
+/// distribution, with density proportional to `det(R)^(eta - 1)`, by the
+/// onion method of Lewandowski, Kurowicka and Joe. `eta = 1` is uniform over
+/// correlation matrices; larger `eta` favors matrices closer to the
+/// identity.
+///
+/// # Panics
+///
+/// If `eta` isn't positive and finite.
+pub fn correlation_matrix<B: Bitstream + ?Sized>(
+    bitstream: &mut B,
+    dimension: usize,
+    eta: f64,
+) -> Vec<Vec<f64>> {
+    assert!(
+        eta > 0.0 && eta.is_finite(),
+        "eta must be positive and finite"
+    );
+    let mut matrix = vec![vec![0.0; dimension]; dimension];
+    for (index, row) in matrix.iter_mut().enumerate() {
+        row[index] = 1.0;
+    }
+    if dimension < 2 {
+        return matrix;
+    }
+    let mut beta = eta + (dimension - 2) as f64 / 2.0;
+    let r = 2.0 * Beta::new(beta, beta).sample(bitstream) - 1.0;
+    matrix[0][1] = r;
+    matrix[1][0] = r;
+    for k in 2..dimension {
+        beta -= 0.5;
+        // Extend the k by k matrix so far with a new row and column: a
+        // random direction scaled by a Beta-distributed radius, mapped
+        // through the Cholesky factor of the existing block.
+        let radius = Beta::new(k as f64 / 2.0, beta).sample(bitstream).sqrt();
+        let direction = loop {
+            let direction: Vec<f64> = (0..k).map(|_| standard_normal(bitstream)).collect();
+            let norm = direction.iter().map(|x| x * x).sum::<f64>().sqrt();
+            if norm > 0.0 {
+                break direction
+                    .into_iter()
+                    .map(|x| x * radius / norm)
+                    .collect::<Vec<_>>();
+            }
+        };
+        let factor = cholesky(&matrix, k);
+        for i in 0..k {
+            let z: f64 = (0..=i).map(|j| factor[i][j] * direction[j]).sum();
+            matrix[i][k] = z;
+            matrix[k][i] = z;
+        }
+    }
+    matrix
+}
+
+/// A random `n` by `n` orthogonal matrix from the Haar measure, i.e.
+/// uniformly distributed over all rotations and reflections: the Q factor
+/// of a matrix of independent standard normals, with each column's sign
+/// chosen to make R's diagonal positive so that Q doesn't inherit a bias
+/// from the decomposition's sign conventions.
+#[cfg(feature = "nalgebra")]
+pub fn haar_orthogonal<B: Bitstream + ?Sized>(
+    bitstream: &mut B,
+    n: usize,
+) -> nalgebra::DMatrix<f64> {
+    let gaussian = nalgebra::DMatrix::from_fn(n, n, |_, _| standard_normal(bitstream));
+    let (mut q, r) = gaussian.qr().unpack();
+    for (mut column, &diagonal) in q.column_iter_mut().zip(r.diagonal().iter()) {
+        if diagonal < 0.0 {
+            column.neg_mut();
+        }
+    }
+    q
+}
+
+/// A random `n` by `n` unitary matrix from the Haar measure, as
+/// `haar_orthogonal` with complex normals, and each column rotated by the
+/// phase of R's diagonal entry.
+#[cfg(feature = "nalgebra")]
+pub fn haar_unitary<B: Bitstream + ?Sized>(
+    bitstream: &mut B,
+    n: usize,
+) -> nalgebra::DMatrix<nalgebra::Complex<f64>> {
+    let gaussian = nalgebra::DMatrix::from_fn(n, n, |_, _| {
+        let re = standard_normal(bitstream);
+        nalgebra::Complex::new(re, standard_normal(bitstream))
+    });
+    let (mut q, r) = gaussian.qr().unpack();
+    for (mut column, diagonal) in q.column_iter_mut().zip(r.diagonal().iter()) {
+        let norm = diagonal.norm();
+        if norm > 0.0 {
+            column *= diagonal / norm;
+        }
+    }
+    q
+}
+
+/// The lower Cholesky factor of the leading `size` by `size` block of a
+/// positive definite `matrix`.
+fn cholesky(matrix: &[Vec<f64>], size: usize) -> Vec<Vec<f64>> {
+    let mut factor = vec![vec![0.0; size]; size];
+    for i in 0..size {
+        for j in 0..=i {
+            let dot: f64 = (0..j).map(|k| factor[i][k] * factor[j][k]).sum();
+            factor[i][j] = if i == j {
+                (matrix[i][i] - dot).max(0.0).sqrt()
+            } else {
+                (matrix[i][j] - dot) / factor[j][j]
+            };
+        }
+    }
+    factor
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RngBitstream;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn random_matrices_have_the_right_structure() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let stochastic = stochastic_matrix(&mut bitstream, 5, 0.5);
+        for row in &stochastic {
+            assert_eq!(row.len(), 5);
+            assert!(row.iter().all(|&x| x >= 0.0));
+            assert!((row.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        }
+
+        // With eta = 1, each correlation is 2 * Beta(d/2, d/2) - 1, with
+        // variance 1 / (d + 1).
+        let num_samples = 5000;
+        let mut sum_squares = 0.0;
+        for _ in 0..num_samples {
+            let matrix = correlation_matrix(&mut bitstream, 4, 1.0);
+            for (i, row) in matrix.iter().enumerate() {
+                assert_eq!(row[i], 1.0);
+                for (j, &value) in row.iter().enumerate() {
+                    assert_eq!(value, matrix[j][i]);
+                    assert!(value.abs() <= 1.0);
+                }
+            }
+            let factor = cholesky(&matrix, 4);
+            assert!(factor.iter().enumerate().all(|(i, row)| row[i] > 0.0));
+            sum_squares += matrix[0][3] * matrix[0][3];
+        }
+        assert!((sum_squares / num_samples as f64 - 0.2).abs() < 0.01);
+    }
+
+    #[test]
+    #[should_panic(expected = "concentration must be positive and finite")]
+    fn empty_stochastic_matrix_still_checks_concentration() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        stochastic_matrix(&mut bitstream, 0, -1.0);
+    }
+
+    #[cfg(feature = "nalgebra")]
+    #[test]
+    fn haar_matrices_are_orthogonal_and_unbiased() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let num_samples = 5000;
+        let mut trace_squares = 0.0;
+        let mut corner = 0.0;
+        for _ in 0..num_samples {
+            let q = haar_orthogonal(&mut bitstream, 4);
+            let identity = nalgebra::DMatrix::<f64>::identity(4, 4);
+            assert!((q.transpose() * &q - identity).abs().max() < 1e-12);
+            // Under Haar measure, E[tr(Q)^2] = 1 and E[Q_00] = 0.
+            trace_squares += q.trace() * q.trace();
+            corner += q[(0, 0)];
+        }
+        assert!((trace_squares / num_samples as f64 - 1.0).abs() < 0.05);
+        assert!((corner / num_samples as f64).abs() < 0.02);
+
+        let u = haar_unitary(&mut bitstream, 3);
+        let product = u.adjoint() * &u;
+        for i in 0..3 {
+            for j in 0..3 {
+                let expected = if i == j { 1.0 } else { 0.0 };
+                assert!((product[(i, j)] - nalgebra::Complex::new(expected, 0.0)).norm() < 1e-12);
+            }
+        }
+    }
+}
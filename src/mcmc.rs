@@ -0,0 +1,147 @@
+use crate::{Bernoulli, Bitstream, Distribution};
+
+/// The Metropolis acceptance test: returns `true` with probability
+/// `min(1, e^(-beta * delta_energy))`.
+///
+/// Instead of comparing against a full-precision uniform `f64`, this compares
+/// random bits against the binary expansion of the acceptance probability one
+/// at a time, stopping at the first difference. That uses 2 bits on average
+/// rather than 53 or 64, and no bits at all for downhill moves. A NaN product
+/// is rejected.
+pub fn accept_metropolis<B: Bitstream + ?Sized>(
+    bitstream: &mut B,
+    delta_energy: f64,
+    beta: f64,
+) -> bool {
+    let exponent = -beta * delta_energy;
+    if exponent >= 0.0 {
+        return true;
+    }
+    if exponent.is_nan() {
+        return false;
+    }
+    Bernoulli::new(exponent.exp()).sample(bitstream)
+}
+
+/// How the temperature falls over an annealing run of `num_steps` steps.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Schedule {
+    /// From `start` to `end` in equal steps.
+    Linear { start: f64, end: f64 },
+    /// From `start` to `end` by a constant factor per step.
+    Exponential { start: f64, end: f64 },
+    /// `start / (1 + ln(1 + step))`, the slow cooling of the classic
+    /// convergence proofs.
+    Logarithmic { start: f64 },
+}
+
+impl Schedule {
+    pub fn temperature(&self, step: usize, num_steps: usize) -> f64 {
+        let progress = if num_steps > 1 {
+            step as f64 / (num_steps - 1) as f64
+        } else {
+            0.0
+        };
+        match *self {
+            Schedule::Linear { start, end } => start + (end - start) * progress,
+            Schedule::Exponential { start, end } => start * (end / start).powf(progress),
+            Schedule::Logarithmic { start } => start / (1.0 + (1.0 + step as f64).ln()),
+        }
+    }
+}
+
+/// Simulated annealing: starting from `initial`, proposes `num_steps` moves
+/// with `neighbor` and accepts each by `accept_metropolis` at the schedule's
+/// temperature. Returns the lowest-energy state seen and its energy.
+///
+/// All randomness, including `neighbor`'s, comes from `bitstream`, so
+/// running on a `RecordingBitstream` captures everything needed to replay a
+/// lucky run.
+pub fn anneal<B, S, E, N>(
+    bitstream: &mut B,
+    initial: S,
+    num_steps: usize,
+    schedule: Schedule,
+    mut energy: E,
+    mut neighbor: N,
+) -> (S, f64)
+where
+    B: Bitstream + ?Sized,
+    S: Clone,
+    E: FnMut(&S) -> f64,
+    N: FnMut(&mut B, &S) -> S,
+{
+    let mut current_energy = energy(&initial);
+    let mut best = (initial.clone(), current_energy);
+    let mut current = initial;
+    for step in 0..num_steps {
+        let candidate = neighbor(bitstream, &current);
+        let candidate_energy = energy(&candidate);
+        let beta = 1.0 / schedule.temperature(step, num_steps);
+        if accept_metropolis(bitstream, candidate_energy - current_energy, beta) {
+            current = candidate;
+            current_energy = candidate_energy;
+            if current_energy < best.1 {
+                best = (current.clone(), current_energy);
+            }
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mcmc::{anneal, Schedule};
+    use crate::{accept_metropolis, Bitstream, BitstreamExt, CountingRngBitstream};
+    use crate::{RecordingBitstream, ReplayBitstream};
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn accepts_with_boltzmann_probability() {
+        let mut bitstream = CountingRngBitstream::new(ChaChaRng::seed_from_u64(0));
+        assert!(accept_metropolis(&mut bitstream, -3.0, 2.0));
+        assert_eq!(bitstream.count(), 0);
+        let num_trials = 100000;
+        let accepted = (0..num_trials)
+            .filter(|_| accept_metropolis(&mut bitstream, 1.0, 0.5))
+            .count();
+        let frequency = accepted as f64 / num_trials as f64;
+        assert!((frequency - (-0.5f64).exp()).abs() < 0.01);
+        assert!(bitstream.count() < 3 * num_trials as u64);
+    }
+
+    #[test]
+    fn annealing_finds_the_minimum_reproducibly() {
+        // A bumpy landscape on 0..200 with its global minimum at 137.
+        fn energy(&x: &u64) -> f64 {
+            let bump = if x % 7 == 4 { 0.0 } else { 5.0 };
+            (x as f64 - 137.0).powi(2) / 100.0 + bump
+        }
+        fn neighbor<B: Bitstream + ?Sized>(bitstream: &mut B, &x: &u64) -> u64 {
+            (x + 200 - 5 + bitstream.gen_range(11)) % 200
+        }
+        let inner = CountingRngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let mut bitstream = RecordingBitstream::new(inner);
+        let schedule = Schedule::Exponential {
+            start: 100.0,
+            end: 0.1,
+        };
+        let (best, best_energy) = anneal(&mut bitstream, 0, 5000, schedule, energy, neighbor);
+        assert_eq!((best, best_energy), (137, 0.0));
+
+        let (_, tape) = bitstream.into_parts();
+        let mut replay = ReplayBitstream::new(tape);
+        let replayed = anneal(&mut replay, 0, 5000, schedule, energy, neighbor);
+        assert_eq!(replayed, (best, best_energy));
+        assert!(replay.is_finished());
+
+        let linear = Schedule::Linear {
+            start: 2.0,
+            end: 1.0,
+        };
+        assert_eq!(linear.temperature(0, 3), 2.0);
+        assert_eq!(linear.temperature(1, 3), 1.5);
+        assert_eq!(schedule.temperature(4999, 5000), 0.1);
+    }
+}
@@ -0,0 +1,96 @@
+//! Entropy use of a sampler as data, for asserting on in tests and CI rather
+//! than reading off a benchmark report.
+
+use crate::source::BitMeter;
+use crate::Bitstream;
+
+/// How many bits a sampler drew over a run, next to the information content
+/// of what it produced.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Measurement {
+    pub samples: u64,
+    pub total_bits: u64,
+    pub min_bits: u64,
+    pub max_bits: u64,
+    /// The entropy of one sample in bits, as given to `measure`: the least
+    /// any exact sampler could use on average.
+    pub entropy: f64,
+}
+
+impl Measurement {
+    pub fn mean_bits(&self) -> f64 {
+        self.total_bits as f64 / self.samples as f64
+    }
+
+    /// Mean bits used beyond the entropy.
+    pub fn overhead(&self) -> f64 {
+        self.mean_bits() - self.entropy
+    }
+
+    /// The entropy as a fraction of the mean bits used; 1 is optimal.
+    pub fn efficiency(&self) -> f64 {
+        self.entropy / self.mean_bits()
+    }
+}
+
+/// Runs `sampler` `num_samples` times on `bitstream`, counting the bits each
+/// run draws. `entropy` is the entropy of the distribution being sampled,
+/// e.g. `log2(52!)` for a shuffled deck.
+///
+/// # Panics
+///
+/// If `num_samples` is 0.
+pub fn measure<B, T, S>(
+    bitstream: &mut B,
+    num_samples: u64,
+    entropy: f64,
+    mut sampler: S,
+) -> Measurement
+where
+    B: Bitstream + ?Sized,
+    S: FnMut(&mut dyn Bitstream) -> T,
+{
+    assert!(num_samples > 0, "can't measure zero samples");
+    let mut meter = BitMeter::new(bitstream);
+    let mut measurement = Measurement {
+        samples: num_samples,
+        total_bits: 0,
+        min_bits: u64::MAX,
+        max_bits: 0,
+        entropy,
+    };
+    for _ in 0..num_samples {
+        let before = meter.bits();
+        sampler(&mut meter);
+        let bits = meter.bits() - before;
+        measurement.min_bits = measurement.min_bits.min(bits);
+        measurement.max_bits = measurement.max_bits.max(bits);
+    }
+    measurement.total_bits = meter.bits();
+    measurement
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cards::Deck;
+    use crate::{measure, RngBitstream};
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn measures_bits_per_sample() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let log2_52_factorial: f64 = (2..=52).map(|n| (n as f64).log2()).sum();
+        let mut deck = Deck::standard();
+        let shuffle = measure(&mut bitstream, 1000, log2_52_factorial, |b| {
+            deck.shuffle_factorial(b)
+        });
+        assert!(shuffle.mean_bits() < 240.0);
+        assert!(shuffle.overhead() > 0.0 && shuffle.efficiency() < 1.0);
+        assert!(shuffle.min_bits <= shuffle.max_bits);
+
+        let bits = measure(&mut bitstream, 10, 5.0, |b| b.gen_bits(5));
+        assert_eq!((bits.total_bits, bits.min_bits, bits.max_bits), (50, 5, 5));
+        assert_eq!(bits.efficiency(), 1.0);
+    }
+}
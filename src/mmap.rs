@@ -0,0 +1,122 @@
+use crate::error::check_num_bits;
+use crate::{low_bits_mask, Bitstream, Error, TryBitstream};
+use memmap2::Mmap;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+/// A bitstream that consumes a memory-mapped file of pre-generated entropy,
+/// e.g. harvested from a hardware TRNG. Bits are read in file order, each
+/// byte lowest bit first, which is how `BitstreamExt::fill_bytes` writes
+/// them; so a file written by `fill_bytes` replays the original draws.
+///
+/// Bits are never reused: asking for more than remain is a
+/// `SourceExhausted` error, or a panic through the `Bitstream` impl. Save
+/// `position()` to resume a later run where this one left off.
+pub struct MmapBitstream {
+    map: Mmap,
+    position: u64,
+}
+
+impl MmapBitstream {
+    /// Maps the file at `path`, starting from its first bit.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated while it's mapped, as with
+    /// `memmap2::Mmap::map`.
+    pub unsafe fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::open(path)?;
+        Ok(MmapBitstream::new(Mmap::map(&file)?))
+    }
+
+    pub fn new(map: Mmap) -> Self {
+        MmapBitstream { map, position: 0 }
+    }
+
+    /// Skips ahead to bit `position` of the file (clamped to its length).
+    pub fn with_position(self, position: u64) -> Self {
+        let position = position.min(self.len_bits());
+        MmapBitstream { position, ..self }
+    }
+
+    /// How many bits have been consumed from the start of the file.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    pub fn remaining_bits(&self) -> u64 {
+        self.len_bits() - self.position
+    }
+
+    fn len_bits(&self) -> u64 {
+        self.map.len() as u64 * 8
+    }
+}
+
+impl TryBitstream for MmapBitstream {
+    fn try_gen_bits(&mut self, num_bits: u32) -> Result<u64, Error> {
+        check_num_bits(num_bits)?;
+        if num_bits as u64 > self.remaining_bits() {
+            return Err(Error::SourceExhausted {
+                requested: num_bits as u64,
+                available: self.remaining_bits(),
+            });
+        }
+        // The bits span at most 9 bytes.
+        let start = (self.position / 8) as usize;
+        let end = ((self.position + num_bits as u64).div_ceil(8) as usize).min(start + 9);
+        let mut bytes = [0; 16];
+        bytes[..end - start].copy_from_slice(&self.map[start..end]);
+        let window = u128::from_le_bytes(bytes) >> (self.position % 8);
+        self.position += num_bits as u64;
+        Ok(window as u64 & low_bits_mask(num_bits))
+    }
+}
+
+impl Bitstream for MmapBitstream {
+    fn gen_bits(&mut self, num_bits: u32) -> u64 {
+        self.try_gen_bits(num_bits)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Bitstream, BitstreamExt, Error, MmapBitstream, RngBitstream, TryBitstream};
+    use std::io::Write;
+
+    #[test]
+    fn mmap_bitstream_replays_a_fill_bytes_file() {
+        let mut bytes = [0; 1001];
+        RngBitstream::seeded(0).fill_bytes(&mut bytes);
+        let path = std::env::temp_dir().join(format!("rng-nibbler-mmap-{}", std::process::id()));
+        std::fs::File::create(&path)
+            .unwrap()
+            .write_all(&bytes)
+            .unwrap();
+        let mut mapped = unsafe { MmapBitstream::open(&path).unwrap() };
+        let mut original = RngBitstream::seeded(0);
+        for _ in 0..125 {
+            assert_eq!(mapped.gen_bits(64), original.gen_bits(64));
+        }
+        let mut mapped = mapped.with_position(3);
+        assert_eq!(mapped.gen_bits(5), bytes[0] as u64 >> 3);
+        assert_eq!(
+            mapped.gen_bits(13),
+            (bytes[1] as u64) | (bytes[2] as u64 & 0x1f) << 8
+        );
+        let mut mapped = mapped.with_position(1001 * 8 - 4);
+        assert!(matches!(
+            mapped.try_gen_bits(5),
+            Err(Error::SourceExhausted {
+                requested: 5,
+                available: 4
+            })
+        ));
+        assert_eq!(mapped.try_gen_bits(4).unwrap(), bytes[1000] as u64 >> 4);
+        assert_eq!(mapped.remaining_bits(), 0);
+        drop(mapped);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
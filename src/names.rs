@@ -0,0 +1,135 @@
+use crate::{Bitstream, Distribution, IntervalHalvingIndex};
+use std::collections::BTreeMap;
+
+/// Marks the start and end of a word in contexts.
+const BOUNDARY: char = '\0';
+
+/// Random names or words from a letter-level Markov model: each letter is
+/// drawn given the `order` letters before it, with the frequencies seen in
+/// the training words. Letters with only one possible successor cost no
+/// bits, and the rest cost less than their entropy plus 2, since each draw
+/// is an `IntervalHalvingIndex` over integer counts.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct NameGenerator {
+    order: usize,
+    // Successors of each context in a fixed order, so sampling is
+    // reproducible, with their weights.
+    contexts: BTreeMap<Vec<char>, (Vec<char>, IntervalHalvingIndex)>,
+}
+
+impl NameGenerator {
+    /// Trains on `words`; order 2 or 3 is typical. Higher orders copy the
+    /// training words more closely.
+    ///
+    /// # Panics
+    ///
+    /// If `order` is 0, there are no words, or a word contains `'\0'`.
+    pub fn train<S: AsRef<str>>(words: &[S], order: usize) -> Self {
+        assert!(order > 0, "order must be positive");
+        assert!(!words.is_empty(), "need at least one word to train on");
+        let mut counts: BTreeMap<Vec<char>, BTreeMap<char, u64>> = BTreeMap::new();
+        for word in words {
+            assert!(
+                !word.as_ref().contains(BOUNDARY),
+                "words can't contain '\\0'"
+            );
+            let mut context = vec![BOUNDARY; order];
+            for letter in word.as_ref().chars().chain(Some(BOUNDARY)) {
+                *counts
+                    .entry(context.clone())
+                    .or_default()
+                    .entry(letter)
+                    .or_default() += 1;
+                context.remove(0);
+                context.push(letter);
+            }
+        }
+        let contexts = counts
+            .into_iter()
+            .map(|(context, successors)| {
+                let (letters, weights): (Vec<char>, Vec<u64>) = successors.into_iter().unzip();
+                (context, (letters, IntervalHalvingIndex::new(&weights)))
+            })
+            .collect();
+        NameGenerator { order, contexts }
+    }
+
+    pub fn order(&self) -> usize {
+        self.order
+    }
+}
+
+impl Distribution<String> for NameGenerator {
+    fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> String {
+        let mut name = String::new();
+        let mut context = vec![BOUNDARY; self.order];
+        loop {
+            // Every context reached was followed by something in training.
+            let (letters, weights) = &self.contexts[&context];
+            let letter = letters[weights.sample(bitstream)];
+            if letter == BOUNDARY {
+                return name;
+            }
+            name.push(letter);
+            context.remove(0);
+            context.push(letter);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BitstreamExt, CountingRngBitstream};
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn names_follow_the_training_letters() {
+        let mut bitstream = CountingRngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let single = NameGenerator::train(&["Aldric"], 2);
+        assert_eq!(bitstream.sample(&single), "Aldric");
+        assert_eq!(bitstream.count(), 0);
+        // Repeats weigh the letters without adding choices.
+        assert_eq!(
+            bitstream.sample(NameGenerator::train(&["Ann", "Ann"], 2)),
+            "Ann"
+        );
+        assert_eq!(bitstream.count(), 0);
+        // After "A", "b" is 15 times as likely as "c": 0.34 bits of entropy,
+        let mut skewed = vec!["Ab"; 15];
+        skewed.push("Ac");
+        let skewed = NameGenerator::train(&skewed, 1);
+        let count = bitstream.count();
+        for _ in 0..10000 {
+            bitstream.sample(&skewed);
+        }
+        let mean_bits = (bitstream.count() - count) as f64 / 10000.0;
+        // Within the entropy plus 2, where `gen_range(16)` would take 4.
+        assert!(mean_bits < 2.0);
+
+        let words = [
+            "Aldric", "Brenna", "Cedric", "Darian", "Elric", "Fenna", "Garrick", "Halden",
+            "Isolde", "Jorah", "Kendric", "Lorna", "Maren", "Orrin", "Rowena", "Tamsin",
+        ];
+        let generator = NameGenerator::train(&words, 2);
+        let padded: Vec<String> = words.iter().map(|word| format!("\0\0{}\0", word)).collect();
+        let mut novel = 0;
+        for _ in 0..1000 {
+            let name = bitstream.sample(&generator);
+            // Every three-letter window, boundaries included, was seen in
+            // training.
+            let letters: Vec<char> = format!("\0\0{}\0", name).chars().collect();
+            for window in letters.windows(3) {
+                let window: String = window.iter().collect();
+                assert!(
+                    padded.iter().any(|word| word.contains(&window)),
+                    "{:?}",
+                    name
+                );
+            }
+            novel += !words.contains(&name.as_str()) as u32;
+        }
+        assert!(novel > 100);
+    }
+}
@@ -0,0 +1,104 @@
+//! Addresses and ports for network test data.
+
+use crate::{low_bits_mask, Bitstream};
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+/// A uniform address in the block `network/prefix_len`; the host bits of
+/// `network` are ignored.
+///
+/// # Panics
+///
+/// If `prefix_len > 32`.
+pub fn ipv4_in<B: Bitstream + ?Sized>(
+    bitstream: &mut B,
+    network: Ipv4Addr,
+    prefix_len: u32,
+) -> Ipv4Addr {
+    assert!(prefix_len <= 32, "IPv4 prefixes are at most 32 bits");
+    let host_bits = 32 - prefix_len;
+    let host_mask = low_bits_mask(host_bits) as u32;
+    let host = bitstream.gen_bits(host_bits) as u32;
+    Ipv4Addr::from((u32::from(network) & !host_mask) | host)
+}
+
+/// A uniform address in the block `network/prefix_len`; the host bits of
+/// `network` are ignored.
+///
+/// # Panics
+///
+/// If `prefix_len > 128`.
+pub fn ipv6_in<B: Bitstream + ?Sized>(
+    bitstream: &mut B,
+    network: Ipv6Addr,
+    prefix_len: u32,
+) -> Ipv6Addr {
+    assert!(prefix_len <= 128, "IPv6 prefixes are at most 128 bits");
+    let host_bits = 128 - prefix_len;
+    let low_bits = host_bits.min(64);
+    let host = bitstream.gen_bits(low_bits) as u128
+        | (bitstream.gen_bits(host_bits - low_bits) as u128) << 64;
+    let host_mask = u128::MAX.checked_shr(prefix_len).unwrap_or(0);
+    Ipv6Addr::from((u128::from(network) & !host_mask) | host)
+}
+
+/// A uniform IPv6 address, from 128 bits.
+pub fn ipv6<B: Bitstream + ?Sized>(bitstream: &mut B) -> Ipv6Addr {
+    ipv6_in(bitstream, Ipv6Addr::UNSPECIFIED, 0)
+}
+
+/// A uniform port in the IANA dynamic range 49152 to 65535, from 14 bits.
+pub fn ephemeral_port<B: Bitstream + ?Sized>(bitstream: &mut B) -> u16 {
+    49152 + bitstream.gen_bits(14) as u16
+}
+
+/// A uniform MAC address with the locally administered bit set and the
+/// multicast bit clear, so it can't collide with a manufacturer-assigned
+/// one. Uses 46 bits.
+pub fn local_mac<B: Bitstream + ?Sized>(bitstream: &mut B) -> [u8; 6] {
+    let bits = bitstream.gen_bits(46);
+    let mut mac = [0; 6];
+    mac[0] = ((bits & 0x3f) << 2) as u8 | 0b10;
+    for (index, octet) in mac[1..].iter_mut().enumerate() {
+        *octet = (bits >> (6 + 8 * index)) as u8;
+    }
+    mac
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CountingRngBitstream;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn addresses_stay_in_their_blocks() {
+        let mut bitstream = CountingRngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let network = Ipv4Addr::new(10, 20, 30, 99);
+        let mut seen = [false; 16];
+        for _ in 0..1000 {
+            let address = ipv4_in(&mut bitstream, network, 28);
+            assert_eq!(u32::from(address) >> 4, u32::from(network) >> 4);
+            seen[(u32::from(address) & 15) as usize] = true;
+        }
+        assert!(seen.iter().all(|&seen| seen));
+        assert_eq!(bitstream.count(), 4000);
+        assert_eq!(ipv4_in(&mut bitstream, network, 32), network);
+
+        let network: Ipv6Addr = "2001:db8:1234::".parse().unwrap();
+        for &prefix_len in &[0, 48, 64, 100, 128] {
+            let address = ipv6_in(&mut bitstream, network, prefix_len);
+            let mask = u128::MAX.checked_shl(128 - prefix_len).unwrap_or(0);
+            assert_eq!(u128::from(address) & mask, u128::from(network) & mask);
+        }
+        assert_ne!(ipv6(&mut bitstream), ipv6(&mut bitstream));
+
+        let before = bitstream.count();
+        for _ in 0..1000 {
+            assert!(ephemeral_port(&mut bitstream) >= 49152);
+            let mac = local_mac(&mut bitstream);
+            assert_eq!(mac[0] & 0b11, 0b10);
+        }
+        assert_eq!(bitstream.count() - before, 1000 * (14 + 46));
+    }
+}
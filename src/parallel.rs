@@ -0,0 +1,40 @@
+use crate::{BitstreamExt, RngBitstream};
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+
+// Each chunk gets its own forked stream, so the output depends only on the
+// seed and the slice length, not on how rayon schedules the chunks.
+const CHUNK_SIZE: usize = 1 << 16;
+
+impl<T: Rng + SeedableRng + Send> RngBitstream<T> {
+    /// Fills `output` with values in `0..size`, in parallel.
+    pub fn par_fill_range(&mut self, size: u64, output: &mut [u64]) {
+        let chunks: Vec<_> = output
+            .chunks_mut(CHUNK_SIZE)
+            .map(|chunk| (chunk, self.fork()))
+            .collect();
+        chunks.into_par_iter().for_each(|(chunk, mut bitstream)| {
+            for value in chunk {
+                *value = bitstream.gen_range(size);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::RngBitstream;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn par_fill_range_is_deterministic() {
+        let mut first = vec![0; 200000];
+        let mut second = vec![0; 200000];
+        RngBitstream::new(ChaChaRng::seed_from_u64(0)).par_fill_range(7, &mut first);
+        RngBitstream::new(ChaChaRng::seed_from_u64(0)).par_fill_range(7, &mut second);
+        assert_eq!(first, second);
+        assert!(first.iter().all(|&value| value < 7));
+        assert!((0..7).all(|value| first.contains(&value)));
+    }
+}
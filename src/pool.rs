@@ -0,0 +1,186 @@
+use crate::error::check_num_bits;
+use crate::{Bitstream, ChaCha20, Error, RngBitstream, TryBitstream};
+use rand::SeedableRng;
+use sha2::{Digest, Sha256};
+use std::time::{Duration, Instant};
+
+const NUM_POOLS: usize = 32;
+// Pool `index` is drained every `1 << index` reseeds, counted in a `u64`.
+const _: () = assert!(NUM_POOLS <= 64);
+/// How many bytes of events pool 0 needs before a reseed.
+const MIN_POOL_BYTES: u64 = 64;
+
+/// A Fortuna-style entropy accumulator: events from any number of sources
+/// are hashed into 32 pools, and a ChaCha20 generator is reseeded from them
+/// as pool 0 fills. Pool `i` is used on every `2^i`-th reseed, so even if an
+/// attacker can predict or inject most events, some pool eventually gathers
+/// enough unknown entropy to recover from a compromised state.
+///
+/// Unlike full Fortuna, the generator isn't rekeyed after every request.
+/// Until the first reseed there's no seed at all, and draws fail with
+/// `SourceExhausted` (or panic, through the `Bitstream` impl).
+pub struct EntropyPool {
+    pools: Vec<Sha256>,
+    pool_zero_bytes: u64,
+    // Per source, which pool its next event goes to.
+    next_pools: [u8; 256],
+    key: [u8; 32],
+    reseeds: u64,
+    last_reseed: Option<Instant>,
+    min_reseed_interval: Duration,
+    created: Instant,
+    generator: Option<RngBitstream<ChaCha20>>,
+}
+
+impl Default for EntropyPool {
+    fn default() -> Self {
+        EntropyPool::new()
+    }
+}
+
+impl EntropyPool {
+    pub fn new() -> Self {
+        EntropyPool {
+            pools: vec![Sha256::new(); NUM_POOLS],
+            pool_zero_bytes: 0,
+            next_pools: [0; 256],
+            key: [0; 32],
+            reseeds: 0,
+            last_reseed: None,
+            min_reseed_interval: Duration::from_millis(100),
+            created: Instant::now(),
+            generator: None,
+        }
+    }
+
+    /// The least time between reseeds (default 100ms), which stops a flood
+    /// of events from reseeding so often that no pool builds up entropy.
+    pub fn with_min_reseed_interval(self, min_reseed_interval: Duration) -> Self {
+        EntropyPool {
+            min_reseed_interval,
+            ..self
+        }
+    }
+
+    /// Adds an event from `source`. Each source's events are spread over
+    /// the pools in turn. Events over 255 bytes are hashed down first.
+    pub fn add_event(&mut self, source: u8, data: &[u8]) {
+        let hashed;
+        let data = if data.len() > 255 {
+            hashed = Sha256::digest(data);
+            &hashed[..]
+        } else {
+            data
+        };
+        let pool = self.next_pools[source as usize] as usize;
+        self.next_pools[source as usize] = ((pool + 1) % NUM_POOLS) as u8;
+        self.pools[pool].update([source, data.len() as u8]);
+        self.pools[pool].update(data);
+        if pool == 0 {
+            self.pool_zero_bytes += 2 + data.len() as u64;
+        }
+    }
+
+    /// Adds the time since this pool was created, in nanoseconds, as an
+    /// event from `source`, e.g. on each interrupt or network packet.
+    pub fn add_timing(&mut self, source: u8) {
+        let nanos = self.created.elapsed().as_nanos() as u64;
+        self.add_event(source, &nanos.to_le_bytes());
+    }
+
+    /// How many times the generator has been reseeded.
+    pub fn reseeds(&self) -> u64 {
+        self.reseeds
+    }
+
+    fn maybe_reseed(&mut self) {
+        if self.pool_zero_bytes < MIN_POOL_BYTES {
+            return;
+        }
+        let now = Instant::now();
+        if self
+            .last_reseed
+            .is_some_and(|last| now.duration_since(last) < self.min_reseed_interval)
+        {
+            return;
+        }
+        self.reseeds += 1;
+        let mut hasher = Sha256::new();
+        hasher.update(self.key);
+        for (index, pool) in self.pools.iter_mut().enumerate() {
+            if self.reseeds.is_multiple_of(1 << index) {
+                hasher.update(std::mem::replace(pool, Sha256::new()).finalize());
+            }
+        }
+        self.key = hasher.finalize().into();
+        self.pool_zero_bytes = 0;
+        self.last_reseed = Some(now);
+        self.generator = Some(RngBitstream::new(ChaCha20::from_seed(self.key)));
+    }
+}
+
+impl TryBitstream for EntropyPool {
+    fn try_gen_bits(&mut self, num_bits: u32) -> Result<u64, Error> {
+        check_num_bits(num_bits)?;
+        self.maybe_reseed();
+        match &mut self.generator {
+            Some(generator) => Ok(generator.gen_bits(num_bits)),
+            None => Err(Error::SourceExhausted {
+                requested: num_bits as u64,
+                available: 0,
+            }),
+        }
+    }
+}
+
+impl Bitstream for EntropyPool {
+    fn gen_bits(&mut self, num_bits: u32) -> u64 {
+        self.try_gen_bits(num_bits)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Bitstream, BitstreamExt, EntropyPool, Error, TryBitstream};
+    use std::time::Duration;
+
+    #[test]
+    fn reseeds_only_from_enough_events() {
+        let mut pool = EntropyPool::new().with_min_reseed_interval(Duration::ZERO);
+        assert!(matches!(
+            pool.try_gen_bits(8),
+            Err(Error::SourceExhausted { .. })
+        ));
+        // Pool 0 gets every 32nd event from a source, and needs 64 bytes.
+        for i in 0..32 * 12u32 {
+            pool.add_event(1, &i.to_le_bytes());
+        }
+        assert!(pool.try_gen_bits(8).is_ok());
+        assert_eq!(pool.reseeds(), 1);
+        let before = pool.gen_range(1 << 40);
+
+        // The same events give the same output, and a different one doesn't.
+        let mut same = EntropyPool::new().with_min_reseed_interval(Duration::ZERO);
+        let mut different = EntropyPool::new().with_min_reseed_interval(Duration::ZERO);
+        for i in 0..32 * 12u32 {
+            same.add_event(1, &i.to_le_bytes());
+            different.add_event(1, &(i ^ (i == 96) as u32).to_le_bytes());
+        }
+        same.gen_bits(8);
+        different.gen_bits(8);
+        assert_eq!(same.gen_range(1 << 40), before);
+        assert_ne!(different.gen_range(1 << 40), before);
+
+        // A long interval holds off the next reseed.
+        let mut slow = EntropyPool::new().with_min_reseed_interval(Duration::from_secs(3600));
+        for _ in 0..2 {
+            for i in 0..32 * 12u32 {
+                slow.add_timing(2);
+                slow.add_event(3, &i.to_le_bytes());
+            }
+            slow.gen_bits(1);
+        }
+        assert_eq!(slow.reseeds(), 1);
+    }
+}
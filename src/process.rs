@@ -0,0 +1,264 @@
+//! Stochastic processes: point processes on the positive real line, for
+//! arrival generation in queueing and network simulations, and random walks
+//! and bridges.
+
+use crate::continuous::standard_normal;
+use crate::{Bernoulli, Bitstream, Distribution, Exponential};
+
+/// The event times of a renewal process: the running sums of independent
+/// inter-arrival times drawn from `D`, starting from time 0. For the
+/// inter-arrival times themselves, use `BitstreamExt::samples`.
+pub struct ArrivalTimes<'a, B: ?Sized, D> {
+    bitstream: &'a mut B,
+    inter_arrival: D,
+    time: f64,
+}
+
+impl<'a, B: Bitstream + ?Sized, D: Distribution<f64>> ArrivalTimes<'a, B, D> {
+    pub fn new(bitstream: &'a mut B, inter_arrival: D) -> Self {
+        ArrivalTimes {
+            bitstream,
+            inter_arrival,
+            time: 0.0,
+        }
+    }
+}
+
+impl<'a, B: Bitstream + ?Sized> ArrivalTimes<'a, B, Exponential> {
+    /// A homogeneous Poisson process with `rate` events per unit time.
+    pub fn poisson(bitstream: &'a mut B, rate: f64) -> Self {
+        ArrivalTimes::new(bitstream, Exponential::new(rate))
+    }
+}
+
+impl<'a, B: Bitstream + ?Sized, D: Distribution<f64>> Iterator for ArrivalTimes<'a, B, D> {
+    type Item = f64;
+
+    fn next(&mut self) -> Option<f64> {
+        self.time += self.inter_arrival.sample(self.bitstream);
+        Some(self.time)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+/// The event times of a Poisson process whose rate varies over time, by
+/// thinning: candidate events arrive at `max_rate`, and each is kept with
+/// probability `rate(time) / max_rate`.
+pub struct InhomogeneousPoisson<'a, B: ?Sized, F> {
+    candidates: ArrivalTimes<'a, B, Exponential>,
+    max_rate: f64,
+    rate: F,
+}
+
+impl<'a, B: Bitstream + ?Sized, F: FnMut(f64) -> f64> InhomogeneousPoisson<'a, B, F> {
+    /// Iterating panics if `rate` returns a value outside `[0, max_rate]`.
+    pub fn new(bitstream: &'a mut B, max_rate: f64, rate: F) -> Self {
+        InhomogeneousPoisson {
+            candidates: ArrivalTimes::poisson(bitstream, max_rate),
+            max_rate,
+            rate,
+        }
+    }
+}
+
+impl<'a, B: Bitstream + ?Sized, F: FnMut(f64) -> f64> Iterator for InhomogeneousPoisson<'a, B, F> {
+    type Item = f64;
+
+    fn next(&mut self) -> Option<f64> {
+        loop {
+            let time = self.candidates.next()?;
+            let rate = (self.rate)(time);
+            assert!(
+                (0.0..=self.max_rate).contains(&rate),
+                "rate {} at time {} is outside [0, {}]",
+                rate,
+                time,
+                self.max_rate
+            );
+            let keep = Bernoulli::new(rate / self.max_rate);
+            if keep.sample(self.candidates.bitstream) {
+                return Some(time);
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+/// The positions of a simple random walk on the integers after each step.
+/// Each step is +1 or -1 with equal probability, costing 1 bit; a lazy walk
+/// also stays put with probability 1/2, costing 1.5 bits per step on average.
+pub struct RandomWalk<'a, B: ?Sized> {
+    bitstream: &'a mut B,
+    position: i64,
+    lazy: bool,
+}
+
+impl<'a, B: Bitstream + ?Sized> RandomWalk<'a, B> {
+    pub fn new(bitstream: &'a mut B, start: i64) -> Self {
+        RandomWalk {
+            bitstream,
+            position: start,
+            lazy: false,
+        }
+    }
+
+    pub fn lazy(bitstream: &'a mut B, start: i64) -> Self {
+        RandomWalk {
+            lazy: true,
+            ..RandomWalk::new(bitstream, start)
+        }
+    }
+}
+
+impl<'a, B: Bitstream + ?Sized> Iterator for RandomWalk<'a, B> {
+    type Item = i64;
+
+    fn next(&mut self) -> Option<i64> {
+        if !(self.lazy && self.bitstream.gen_bit()) {
+            self.position += if self.bitstream.gen_bit() { 1 } else { -1 };
+        }
+        Some(self.position)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+/// A Brownian motion from `start` at time 0, conditioned to be at `end` at
+/// time `duration`, observed at `num_steps` evenly spaced times after 0. The
+/// last value is exactly `end`.
+pub struct BrownianBridge<'a, B: ?Sized> {
+    bitstream: &'a mut B,
+    position: f64,
+    end: f64,
+    step_duration: f64,
+    volatility: f64,
+    steps_remaining: usize,
+}
+
+impl<'a, B: Bitstream + ?Sized> BrownianBridge<'a, B> {
+    /// # Panics
+    ///
+    /// If `duration` isn't positive and finite.
+    pub fn new(
+        bitstream: &'a mut B,
+        start: f64,
+        end: f64,
+        duration: f64,
+        num_steps: usize,
+    ) -> Self {
+        assert!(
+            duration > 0.0 && duration.is_finite(),
+            "duration must be positive and finite"
+        );
+        BrownianBridge {
+            bitstream,
+            position: start,
+            end,
+            step_duration: duration / num_steps as f64,
+            volatility: 1.0,
+            steps_remaining: num_steps,
+        }
+    }
+
+    /// Scales the motion's standard deviation per unit of time (default 1).
+    pub fn with_volatility(self, volatility: f64) -> Self {
+        BrownianBridge { volatility, ..self }
+    }
+}
+
+impl<'a, B: Bitstream + ?Sized> Iterator for BrownianBridge<'a, B> {
+    type Item = f64;
+
+    fn next(&mut self) -> Option<f64> {
+        if self.steps_remaining == 0 {
+            return None;
+        }
+        // Given the current position with time t left, the position one step
+        // of length dt later is normal with mean moving a fraction dt/t of the
+        // way to the end, and variance dt (t - dt) / t.
+        let fraction = 1.0 / self.steps_remaining as f64;
+        let mean = self.position + (self.end - self.position) * fraction;
+        let variance = self.step_duration * (1.0 - fraction);
+        self.steps_remaining -= 1;
+        self.position = if self.steps_remaining == 0 {
+            self.end
+        } else {
+            mean + self.volatility * variance.sqrt() * standard_normal(self.bitstream)
+        };
+        Some(self.position)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.steps_remaining, Some(self.steps_remaining))
+    }
+}
+
+impl<'a, B: Bitstream + ?Sized> ExactSizeIterator for BrownianBridge<'a, B> {}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ArrivalTimes, BrownianBridge, CountingRngBitstream, InhomogeneousPoisson};
+    use crate::{RandomWalk, RngBitstream};
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn event_counts_match_the_rate() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let num_runs = 2000;
+        let mut homogeneous = 0;
+        let mut inhomogeneous = 0;
+        for _ in 0..num_runs {
+            homogeneous += ArrivalTimes::poisson(&mut bitstream, 2.0)
+                .take_while(|&time| time < 10.0)
+                .count();
+            // Rate t on [0, 10] gives 50 events on average.
+            inhomogeneous += InhomogeneousPoisson::new(&mut bitstream, 10.0, |time| time.min(10.0))
+                .take_while(|&time| time < 10.0)
+                .count();
+        }
+        let homogeneous = homogeneous as f64 / num_runs as f64;
+        let inhomogeneous = inhomogeneous as f64 / num_runs as f64;
+        assert!((homogeneous - 20.0).abs() < 0.5, "{}", homogeneous);
+        assert!((inhomogeneous - 50.0).abs() < 0.5, "{}", inhomogeneous);
+    }
+
+    #[test]
+    fn walks_and_bridges_have_the_right_spread() {
+        let mut bitstream = CountingRngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let num_runs = 2000;
+        let mut squared = 0.0;
+        let mut lazy_squared = 0.0;
+        for _ in 0..num_runs {
+            let position = RandomWalk::new(&mut bitstream, 5).nth(99).unwrap();
+            squared += ((position - 5) * (position - 5)) as f64;
+            let position = RandomWalk::lazy(&mut bitstream, 0).nth(99).unwrap();
+            lazy_squared += (position * position) as f64;
+        }
+        // Variance 1 per step, or 1/2 for the lazy walk.
+        assert!((squared / num_runs as f64 - 100.0).abs() < 10.0);
+        assert!((lazy_squared / num_runs as f64 - 50.0).abs() < 5.0);
+        assert!(bitstream.count() < num_runs * 260);
+
+        // At the midpoint, a unit bridge has variance 1/4.
+        let mut midpoint_sum = 0.0;
+        let mut midpoint_squared = 0.0;
+        for _ in 0..num_runs {
+            let path: Vec<f64> = BrownianBridge::new(&mut bitstream, 1.0, 3.0, 1.0, 10).collect();
+            assert_eq!(path.len(), 10);
+            assert_eq!(path[9], 3.0);
+            midpoint_sum += path[4];
+            midpoint_squared += (path[4] - 2.0) * (path[4] - 2.0);
+        }
+        assert!((midpoint_sum / num_runs as f64 - 2.0).abs() < 0.05);
+        assert!((midpoint_squared / num_runs as f64 - 0.25).abs() < 0.03);
+    }
+}
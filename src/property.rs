@@ -0,0 +1,98 @@
+use crate::{Bitstream, BitstreamExt, Distribution, RngBitstream};
+use proptest::strategy::{Just, NewTree, Strategy};
+use proptest::test_runner::{Config, RngAlgorithm, TestRng, TestRunner};
+use std::fmt::{self, Debug};
+use std::marker::PhantomData;
+
+/// A proptest RNG that serves `num_bytes` bytes drawn from `bitstream`, then
+/// zeros. Drawing through a `RecordingBitstream` or `CountingRngBitstream`
+/// records or counts the entropy given to proptest.
+pub fn test_rng<B: Bitstream + ?Sized>(bitstream: &mut B, num_bytes: usize) -> TestRng {
+    let mut bytes = vec![0; num_bytes];
+    bitstream.fill_bytes(&mut bytes);
+    replay_rng(&bytes)
+}
+
+/// A proptest RNG that serves `bytes`, then zeros. Since a shortened or
+/// zeroed-out byte tape still drives generation, this allows shrinking by
+/// editing the tape and replaying it.
+pub fn replay_rng(bytes: &[u8]) -> TestRng {
+    TestRng::from_seed(RngAlgorithm::PassThrough, bytes)
+}
+
+/// A `TestRunner` whose RNG is `test_rng(bitstream, num_bytes)`.
+pub fn test_runner<B: Bitstream + ?Sized>(
+    config: Config,
+    bitstream: &mut B,
+    num_bytes: usize,
+) -> TestRunner {
+    TestRunner::new_with_rng(config, test_rng(bitstream, num_bytes))
+}
+
+/// A proptest strategy that samples from a `Distribution`, drawing from the
+/// runner's RNG. Values don't shrink.
+pub struct DistributionStrategy<D, T> {
+    distribution: D,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<D, T> DistributionStrategy<D, T> {
+    pub fn new(distribution: D) -> Self {
+        DistributionStrategy {
+            distribution,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<D: Debug, T> Debug for DistributionStrategy<D, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("DistributionStrategy")
+            .field(&self.distribution)
+            .finish()
+    }
+}
+
+impl<D: Distribution<T> + Debug, T: Clone + Debug> Strategy for DistributionStrategy<D, T> {
+    type Tree = Just<T>;
+    type Value = T;
+
+    fn new_tree(&self, runner: &mut TestRunner) -> NewTree<Self> {
+        let mut bitstream = RngBitstream::new(runner.rng());
+        Ok(Just(self.distribution.sample(&mut bitstream)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CountingRngBitstream, UniformRange};
+    use proptest::strategy::ValueTree;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    fn generate(runner: &mut TestRunner) -> Vec<(u64, u32)> {
+        let strategy = (DistributionStrategy::new(UniformRange::new(10)), 0..1000u32);
+        (0..20)
+            .map(|_| strategy.new_tree(runner).unwrap().current())
+            .collect()
+    }
+
+    #[test]
+    fn runners_draw_from_the_bitstream() {
+        let mut bitstream = CountingRngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let first = generate(&mut test_runner(Config::default(), &mut bitstream, 4096));
+        assert_eq!(bitstream.count(), 4096 * 8);
+        let mut bitstream = CountingRngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let second = generate(&mut test_runner(Config::default(), &mut bitstream, 4096));
+        assert_eq!(first, second);
+        assert!(first
+            .iter()
+            .all(|&(digit, number)| digit < 10 && number < 1000));
+
+        let mut empty_tape = TestRunner::new_with_rng(Config::default(), replay_rng(&[]));
+        assert!(generate(&mut empty_tape)
+            .iter()
+            .all(|&value| value == (0, 0)));
+    }
+}
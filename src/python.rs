@@ -0,0 +1,136 @@
+use crate::error::{check_num_bits, check_size};
+use crate::{
+    Bitstream, BitstreamExt, CountingRngBitstream, DefaultRng, Distribution, Error, RngBitstream,
+    Strategy, UniformRange,
+};
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use rand::SeedableRng;
+
+impl From<Error> for PyErr {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::InvalidParameter(_) => PyValueError::new_err(error.to_string()),
+            Error::Io(error) => error.into(),
+            _ => PyRuntimeError::new_err(error.to_string()),
+        }
+    }
+}
+
+/// A ChaCha20-backed RngBitstream.
+#[pyclass(name = "RngBitstream")]
+struct PyRngBitstream(RngBitstream<DefaultRng>);
+
+#[pymethods]
+impl PyRngBitstream {
+    #[new]
+    fn new(seed: u64) -> Self {
+        PyRngBitstream(RngBitstream::seeded(seed))
+    }
+
+    fn gen_bits(&mut self, num_bits: u32) -> PyResult<u64> {
+        check_num_bits(num_bits)?;
+        Ok(self.0.gen_bits(num_bits))
+    }
+
+    fn gen_range(&mut self, size: u64) -> PyResult<u64> {
+        check_size(size)?;
+        Ok(self.0.gen_range(size))
+    }
+
+    fn gen_bytes<'py>(&mut self, py: Python<'py>, len: usize) -> Bound<'py, PyBytes> {
+        PyBytes::new_with(py, len, |bytes| {
+            self.0.fill_bytes(bytes);
+            Ok(())
+        })
+        .expect("filling bytes can't fail")
+    }
+}
+
+/// A ChaCha20-backed CountingRngBitstream.
+#[pyclass(name = "CountingRngBitstream")]
+struct PyCountingRngBitstream(CountingRngBitstream<DefaultRng>);
+
+#[pymethods]
+impl PyCountingRngBitstream {
+    #[new]
+    fn new(seed: u64) -> Self {
+        PyCountingRngBitstream(CountingRngBitstream::new(DefaultRng::seed_from_u64(seed)))
+    }
+
+    #[getter]
+    fn count(&self) -> u64 {
+        self.0.count()
+    }
+
+    fn gen_bits(&mut self, num_bits: u32) -> PyResult<u64> {
+        check_num_bits(num_bits)?;
+        Ok(self.0.gen_bits(num_bits))
+    }
+
+    fn gen_range(&mut self, size: u64) -> PyResult<u64> {
+        check_size(size)?;
+        Ok(self.0.gen_range(size))
+    }
+
+    fn gen_bytes<'py>(&mut self, py: Python<'py>, len: usize) -> Bound<'py, PyBytes> {
+        PyBytes::new_with(py, len, |bytes| {
+            self.0.fill_bytes(bytes);
+            Ok(())
+        })
+        .expect("filling bytes can't fail")
+    }
+}
+
+#[derive(FromPyObject)]
+enum AnyBitstream<'py> {
+    Plain(PyRefMut<'py, PyRngBitstream>),
+    Counting(PyRefMut<'py, PyCountingRngBitstream>),
+}
+
+#[pyclass(name = "UniformRange")]
+struct PyUniformRange(UniformRange);
+
+#[pymethods]
+impl PyUniformRange {
+    /// `strategy` is "entropy_optimal" (the default) or "throughput".
+    #[new]
+    #[pyo3(signature = (size, strategy = "entropy_optimal"))]
+    fn new(size: u64, strategy: &str) -> PyResult<Self> {
+        check_size(size)?;
+        let strategy = match strategy {
+            "entropy_optimal" => Strategy::EntropyOptimal,
+            "throughput" => Strategy::Throughput,
+            _ => {
+                return Err(Error::InvalidParameter(
+                    "strategy must be \"entropy_optimal\" or \"throughput\"",
+                )
+                .into())
+            }
+        };
+        Ok(PyUniformRange(
+            UniformRange::new(size).with_strategy(strategy),
+        ))
+    }
+
+    #[getter]
+    fn size(&self) -> u64 {
+        self.0.size()
+    }
+
+    fn sample(&self, bitstream: AnyBitstream) -> u64 {
+        match bitstream {
+            AnyBitstream::Plain(mut bitstream) => self.0.sample(&mut bitstream.0),
+            AnyBitstream::Counting(mut bitstream) => self.0.sample(&mut bitstream.0),
+        }
+    }
+}
+
+#[pymodule]
+fn rng_nibbler(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyRngBitstream>()?;
+    module.add_class::<PyCountingRngBitstream>()?;
+    module.add_class::<PyUniformRange>()?;
+    Ok(())
+}
@@ -0,0 +1,235 @@
+//! Low-discrepancy sequences, for Monte Carlo integration that converges
+//! faster than with independent random points.
+
+use crate::error::assert_num_bits;
+use crate::Bitstream;
+
+/// A deterministic sequence of points in the unit hypercube.
+pub trait QuasiSequence {
+    fn dimensions(&self) -> usize;
+    /// Writes the next point's coordinates as 64-bit binary fractions, i.e.
+    /// coordinate `x` in `[0, 1)` is written as `floor(x * 2^64)`.
+    fn next_point(&mut self, point: &mut [u64]);
+}
+
+/// Primitive polynomials and initial direction numbers for dimensions 2 and
+/// up, from Joe and Kuo's `new-joe-kuo-6.21201` table, as `(degree,
+/// coefficients, initial m values)`.
+const SOBOL_PARAMETERS: [(u32, u32, &[u64]); 15] = [
+    (1, 0, &[1]),
+    (2, 1, &[1, 3]),
+    (3, 1, &[1, 3, 1]),
+    (3, 2, &[1, 1, 1]),
+    (4, 1, &[1, 1, 3, 3]),
+    (4, 4, &[1, 3, 5, 13]),
+    (5, 2, &[1, 1, 5, 5, 17]),
+    (5, 4, &[1, 1, 5, 5, 5]),
+    (5, 7, &[1, 1, 7, 11, 19]),
+    (5, 11, &[1, 1, 5, 1, 1]),
+    (5, 13, &[1, 1, 1, 3, 11]),
+    (5, 14, &[1, 3, 5, 5, 31]),
+    (6, 1, &[1, 3, 3, 9, 7, 49]),
+    (6, 13, &[1, 1, 1, 15, 21, 21]),
+    (6, 16, &[1, 3, 1, 13, 27, 49]),
+];
+
+/// The Sobol sequence in Gray-code order, starting at the origin.
+pub struct Sobol {
+    directions: Vec<[u64; 64]>,
+    point: Vec<u64>,
+    index: u64,
+}
+
+impl Sobol {
+    pub const MAX_DIMENSIONS: usize = SOBOL_PARAMETERS.len() + 1;
+
+    /// # Panics
+    ///
+    /// If `dimensions` is 0 or more than `MAX_DIMENSIONS`.
+    pub fn new(dimensions: usize) -> Self {
+        assert!(
+            (1..=Self::MAX_DIMENSIONS).contains(&dimensions),
+            "Sobol supports 1 to {} dimensions",
+            Self::MAX_DIMENSIONS
+        );
+        let mut directions = vec![[0; 64]; dimensions];
+        for (k, direction) in directions[0].iter_mut().enumerate() {
+            *direction = 1 << (63 - k);
+        }
+        for (directions, &(degree, coefficients, initial)) in
+            directions[1..].iter_mut().zip(&SOBOL_PARAMETERS)
+        {
+            let degree = degree as usize;
+            for (k, &m) in initial.iter().enumerate() {
+                directions[k] = m << (63 - k);
+            }
+            for k in degree..64 {
+                let mut direction = directions[k - degree] ^ (directions[k - degree] >> degree);
+                for j in 1..degree {
+                    if (coefficients >> (degree - 1 - j)) & 1 == 1 {
+                        direction ^= directions[k - j];
+                    }
+                }
+                directions[k] = direction;
+            }
+        }
+        Sobol {
+            directions,
+            point: vec![0; dimensions],
+            index: 0,
+        }
+    }
+}
+
+impl QuasiSequence for Sobol {
+    fn dimensions(&self) -> usize {
+        self.point.len()
+    }
+
+    fn next_point(&mut self, point: &mut [u64]) {
+        point.copy_from_slice(&self.point);
+        let changed_bit = self.index.trailing_ones() as usize % 64;
+        for (coordinate, directions) in self.point.iter_mut().zip(&self.directions) {
+            *coordinate ^= directions[changed_bit];
+        }
+        self.index = self.index.wrapping_add(1);
+    }
+}
+
+/// The Halton sequence, using the first `dimensions` primes as bases and
+/// starting at the origin.
+pub struct Halton {
+    bases: Vec<u64>,
+    index: u64,
+}
+
+impl Halton {
+    /// # Panics
+    ///
+    /// If `dimensions` is 0.
+    pub fn new(dimensions: usize) -> Self {
+        assert!(dimensions > 0, "Halton needs at least 1 dimension");
+        let mut bases: Vec<u64> = Vec::with_capacity(dimensions);
+        let mut candidate = 2;
+        while bases.len() < dimensions {
+            if bases.iter().all(|&prime| candidate % prime != 0) {
+                bases.push(candidate);
+            }
+            candidate += 1;
+        }
+        Halton { bases, index: 0 }
+    }
+}
+
+/// `floor(x * 2^64)`, where `x` is `index` with its base-`base` digits
+/// mirrored around the radix point.
+fn radical_inverse(mut index: u64, base: u64) -> u64 {
+    let mut digits = [0; 64];
+    let mut num_digits = 0;
+    while index > 0 {
+        digits[num_digits] = index % base;
+        index /= base;
+        num_digits += 1;
+    }
+    // Horner's rule from the last digit inward. Flooring at each step gives
+    // the same result as flooring once at the end.
+    let mut fraction: u128 = 0;
+    for &digit in digits[..num_digits].iter().rev() {
+        fraction = (((digit as u128) << 64) + fraction) / base as u128;
+    }
+    fraction as u64
+}
+
+impl QuasiSequence for Halton {
+    fn dimensions(&self) -> usize {
+        self.bases.len()
+    }
+
+    fn next_point(&mut self, point: &mut [u64]) {
+        for (coordinate, &base) in point.iter_mut().zip(&self.bases) {
+            *coordinate = radical_inverse(self.index, base);
+        }
+        self.index = self.index.wrapping_add(1);
+    }
+}
+
+/// Presents a `QuasiSequence` through the `Bitstream` API. Each `gen_bits`
+/// call, including `gen_bits(0)`, uses up one coordinate, and returns its
+/// highest `num_bits` bits; so a sampler making one `gen_bits` call per
+/// dimension sees consecutive calls land on the same point.
+///
+/// Operations that may retry, like `gen_range` on sizes that aren't powers of
+/// two, use up a variable number of coordinates and lose the alignment.
+pub struct QuasiBitstream<S> {
+    sequence: S,
+    point: Vec<u64>,
+    next_dimension: usize,
+}
+
+impl<S: QuasiSequence> QuasiBitstream<S> {
+    pub fn new(sequence: S) -> Self {
+        QuasiBitstream {
+            point: vec![0; sequence.dimensions()],
+            sequence,
+            next_dimension: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.sequence
+    }
+}
+
+impl<S: QuasiSequence> Bitstream for QuasiBitstream<S> {
+    fn gen_bits(&mut self, num_bits: u32) -> u64 {
+        assert_num_bits(num_bits);
+        if self.next_dimension == 0 {
+            self.sequence.next_point(&mut self.point);
+        }
+        let coordinate = self.point[self.next_dimension];
+        self.next_dimension = (self.next_dimension + 1) % self.point.len();
+        coordinate.checked_shr(64 - num_bits).unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The first 2^m points of each Sobol dimension hit every interval
+    // [k/2^m, (k+1)/2^m) once, and likewise the first b^m points of Halton
+    // dimension b hit every interval [k/b^m, (k+1)/b^m) once.
+    #[test]
+    fn sequences_are_stratified() {
+        let mut bitstream = QuasiBitstream::new(Sobol::new(Sobol::MAX_DIMENSIONS));
+        let mut seen = vec![[false; 256]; Sobol::MAX_DIMENSIONS];
+        for _ in 0..256 {
+            for seen in &mut seen {
+                let interval = bitstream.gen_bits(8) as usize;
+                assert!(!seen[interval]);
+                seen[interval] = true;
+            }
+        }
+
+        let mut halton = Halton::new(4);
+        let mut point = [0; 4];
+        let points: Vec<[u64; 4]> = (0..7u64.pow(3))
+            .map(|_| {
+                halton.next_point(&mut point);
+                point
+            })
+            .collect();
+        for (dimension, &base) in [2u64, 3, 5, 7].iter().enumerate() {
+            let num_intervals = base.pow(3);
+            let mut seen = vec![false; num_intervals as usize];
+            for point in &points[..num_intervals as usize] {
+                // These points lie exactly on multiples of 1/b^3, so round
+                // instead of truncating.
+                let scaled = point[dimension] as u128 * num_intervals as u128;
+                let interval = (scaled + (1 << 63)) >> 64;
+                assert!(!seen[interval as usize]);
+                seen[interval as usize] = true;
+            }
+        }
+    }
+}
@@ -0,0 +1,529 @@
+use crate::{low_bits_mask, Bernoulli, Bitstream, Distribution, Error, TryBitstream};
+use std::convert::{Infallible, TryFrom};
+
+// The smallest number of bits that, appended to `leftover_size` possibilities,
+// gives at least `size` possibilities. Can be 64 when leftover_size is 1.
+const fn retry_bits_needed(leftover_size: u64, size: u64) -> u32 {
+    if cfg!(feature = "leading-zeros-retry") {
+        retry_bits_needed_by_leading_zeros(leftover_size, size)
+    } else {
+        retry_bits_needed_by_loop(leftover_size, size)
+    }
+}
+
+// The expected number of iterations is small, but sizes just above a power
+// of two can take many.
+const fn retry_bits_needed_by_loop(leftover_size: u64, size: u64) -> u32 {
+    let mut bits_needed = 1;
+    // `leftover_size << bits_needed < size`, without the shift wrapping.
+    while bits_needed < 64 && leftover_size <= (size - 1) >> bits_needed {
+        bits_needed += 1;
+    }
+    bits_needed
+}
+
+// Shifting leftover_size until its top bit lines up with size's gets within
+// one bit of the answer. Requires leftover_size < size.
+const fn retry_bits_needed_by_leading_zeros(leftover_size: u64, size: u64) -> u32 {
+    let aligned = leftover_size.leading_zeros() - size.leading_zeros();
+    let bits_needed = if aligned < 64 && (leftover_size << aligned) >= size {
+        aligned
+    } else {
+        aligned + 1
+    };
+    if bits_needed == 0 {
+        1
+    } else if bits_needed > 64 {
+        64
+    } else {
+        bits_needed
+    }
+}
+
+/// The parts of `gen_range(size)` that depend only on `size`: how many bits
+/// the first draw takes, and how many the first retry takes if that draw is
+/// rejected.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct RangePlan {
+    size: u64,
+    bits_needed: u32,
+    leftover_size: u64,
+    retry_bits_needed: u32,
+}
+
+impl RangePlan {
+    /// # Panics
+    ///
+    /// If `size` is 0.
+    pub const fn new(size: u64) -> Self {
+        assert!(size > 0, "range size must be nonzero");
+        let bits_needed = 64 - (size - 1).leading_zeros();
+        // 2^bits_needed - size, written so that bits_needed == 64 doesn't overflow.
+        let leftover_size = low_bits_mask(bits_needed) - (size - 1);
+        let retry_bits_needed = if leftover_size > 0 {
+            retry_bits_needed(leftover_size, size)
+        } else {
+            0
+        };
+        RangePlan {
+            size,
+            bits_needed,
+            leftover_size,
+            retry_bits_needed,
+        }
+    }
+
+    pub const fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> u64 {
+        match self.sample_with(|num_bits| Ok::<_, Infallible>(bitstream.gen_bits(num_bits))) {
+            Ok(value) => value,
+            Err(never) => match never {},
+        }
+    }
+
+    pub fn try_sample<B: TryBitstream + ?Sized>(&self, bitstream: &mut B) -> Result<u64, Error> {
+        self.sample_with(|num_bits| bitstream.try_gen_bits(num_bits))
+    }
+
+    fn sample_with<E>(&self, mut gen_bits: impl FnMut(u32) -> Result<u64, E>) -> Result<u64, E> {
+        let size = self.size;
+        let mut leftover: u64 = gen_bits(self.bits_needed)?;
+        if leftover < size {
+            return Ok(leftover);
+        }
+        leftover -= size;
+        let mut leftover_size = self.leftover_size;
+        let mut bits_needed = self.retry_bits_needed;
+        loop {
+            // For sizes above 2^63, leftover_size * 2^bits_needed can pass
+            // 2^64; it's below 2 * size, so it fits again after subtracting size.
+            let value = leftover as u128 + gen_bits(bits_needed)? as u128 * leftover_size as u128;
+            if value < size as u128 {
+                return Ok(value as u64);
+            }
+            leftover_size = (((leftover_size as u128) << bits_needed) - size as u128) as u64;
+            leftover = (value - size as u128) as u64;
+
+            // We need to increase leftover_size to >= size, by adding bits.
+            // The expected value of bits needed given that we reach this code
+            // is only something like 2, so by default this loops; the
+            // leading-zeros-retry feature computes it directly instead.
+            bits_needed = retry_bits_needed(leftover_size, size);
+        }
+    }
+}
+
+/// A uniform range of fixed size, with its `RangePlan` computed at compile time.
+/// `ConstRange::<0>` fails to compile when sampled.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct ConstRange<const SIZE: u64>;
+
+impl<const SIZE: u64> ConstRange<SIZE> {
+    const PLAN: RangePlan = RangePlan::new(SIZE);
+}
+
+impl<const SIZE: u64> Distribution<u64> for ConstRange<SIZE> {
+    fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> u64 {
+        if Self::PLAN.leftover_size == 0 {
+            // Power of two: every draw is accepted.
+            bitstream.gen_bits(Self::PLAN.bits_needed)
+        } else {
+            Self::PLAN.sample(bitstream)
+        }
+    }
+}
+
+/// How to trade entropy use against speed when sampling a range.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum Strategy {
+    /// Take as few bits as possible (the `gen_range` algorithm).
+    #[default]
+    EntropyOptimal,
+    /// Take whole 64-bit words and use Lemire's widening-multiply rejection,
+    /// which is faster per call but wastes most of each word.
+    Throughput,
+}
+
+pub(crate) fn gen_range_throughput<B: Bitstream + ?Sized>(bitstream: &mut B, size: u64) -> u64 {
+    let mut product = bitstream.gen_bits(64) as u128 * size as u128;
+    if (product as u64) < size {
+        let threshold = size.wrapping_neg() % size;
+        while (product as u64) < threshold {
+            product = bitstream.gen_bits(64) as u128 * size as u128;
+        }
+    }
+    (product >> 64) as u64
+}
+
+/// `gen_range` with the arithmetic in 32 bits, for targets where 64-bit
+/// operations are slow. Returns the same values as `gen_range` would, from
+/// the same bits. Sizes above 2^31 could overflow 32-bit intermediates, so
+/// they go through `gen_range`.
+pub(crate) fn gen_range_u32<B: Bitstream + ?Sized>(bitstream: &mut B, size: u32) -> u32 {
+    assert!(size > 0, "can't sample from an empty range");
+    if size > 1 << 31 {
+        return bitstream.range_plan(size as u64).sample(bitstream) as u32;
+    }
+    let bits_needed = 32 - (size - 1).leading_zeros();
+    let mut leftover = bitstream.gen_bits(bits_needed) as u32;
+    if leftover < size {
+        return leftover;
+    }
+    leftover -= size;
+    // bits_needed <= 31 here, and every product below is under 2 * size.
+    let mut leftover_size = (1 << bits_needed) - size;
+    loop {
+        let mut bits_needed = 1;
+        while (leftover_size << bits_needed) < size {
+            bits_needed += 1;
+        }
+        leftover += bitstream.gen_bits(bits_needed) as u32 * leftover_size;
+        if leftover < size {
+            return leftover;
+        }
+        leftover_size = (leftover_size << bits_needed) - size;
+        leftover -= size;
+    }
+}
+
+/// The width `high - low + 1` can be 2^64, one more than a `u64` holds, so
+/// it's computed in `u128`; that width is exactly one full draw.
+pub(crate) fn gen_range_inclusive<B: Bitstream + ?Sized>(
+    bitstream: &mut B,
+    low: u64,
+    high: u64,
+) -> u64 {
+    assert!(low <= high, "can't sample from an empty range");
+    let width = high as u128 - low as u128 + 1;
+    match u64::try_from(width) {
+        Ok(size) => low + bitstream.range_plan(size).sample(bitstream),
+        Err(_) => bitstream.gen_bits(64),
+    }
+}
+
+/// A uniform range sampler with a fixed size and strategy.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct UniformRange {
+    plan: RangePlan,
+    strategy: Strategy,
+}
+
+impl UniformRange {
+    pub fn new(size: u64) -> Self {
+        UniformRange {
+            plan: RangePlan::new(size),
+            strategy: Strategy::default(),
+        }
+    }
+
+    pub fn with_strategy(self, strategy: Strategy) -> Self {
+        UniformRange { strategy, ..self }
+    }
+
+    pub fn size(&self) -> u64 {
+        self.plan.size
+    }
+
+    pub fn strategy(&self) -> Strategy {
+        self.strategy
+    }
+}
+
+impl Distribution<u64> for UniformRange {
+    fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> u64 {
+        match self.strategy {
+            Strategy::EntropyOptimal => self.plan.sample(bitstream),
+            Strategy::Throughput => gen_range_throughput(bitstream, self.plan.size),
+        }
+    }
+}
+
+/// A range sampler for property testing: with probability
+/// `edge_probability`, returns one of the range's boundary values (0, 1,
+/// `size - 1`, and the powers of two and powers of two minus one below
+/// `size`), uniformly chosen; otherwise falls back to uniform sampling.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct EdgeBiasedRange {
+    plan: RangePlan,
+    edges: Vec<u64>,
+    edge_plan: RangePlan,
+    edge: Bernoulli,
+}
+
+impl EdgeBiasedRange {
+    /// # Panics
+    ///
+    /// If `size` is 0 or `edge_probability` isn't in `[0, 1]`.
+    pub fn new(size: u64, edge_probability: f64) -> Self {
+        assert!(size > 0, "range size must be nonzero");
+        let mut edges = vec![0, 1, size - 1];
+        for shift in 1..64 {
+            edges.push(1 << shift);
+            edges.push((1 << shift) - 1);
+        }
+        edges.retain(|&edge| edge < size);
+        edges.sort_unstable();
+        edges.dedup();
+        EdgeBiasedRange {
+            plan: RangePlan::new(size),
+            edge_plan: RangePlan::new(edges.len() as u64),
+            edges,
+            edge: Bernoulli::new(edge_probability),
+        }
+    }
+
+    pub fn size(&self) -> u64 {
+        self.plan.size
+    }
+
+    pub fn edges(&self) -> &[u64] {
+        &self.edges
+    }
+}
+
+impl Distribution<u64> for EdgeBiasedRange {
+    fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> u64 {
+        if self.edge.sample(bitstream) {
+            self.edges[self.edge_plan.sample(bitstream) as usize]
+        } else {
+            self.plan.sample(bitstream)
+        }
+    }
+}
+
+pub struct Values<'a, B: ?Sized> {
+    pub(crate) bitstream: &'a mut B,
+    pub(crate) plan: RangePlan,
+}
+
+impl<'a, B: Bitstream + ?Sized> Iterator for Values<'a, B> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        Some(self.plan.sample(self.bitstream))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Bitstream, BitstreamExt, ConstRange, Distribution, EdgeBiasedRange, RangePlan,
+        RngBitstream, Strategy, UniformRange,
+    };
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn bounded_ranges_match_sized_ones() {
+        let mut bounded = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let mut sized = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        for _ in 0..1000 {
+            assert_eq!(
+                bounded.gen_range_inclusive(10, 20),
+                10 + sized.gen_range(11)
+            );
+            assert_eq!(bounded.gen_range_between(10, 20), 10 + sized.gen_range(10));
+            assert_eq!(bounded.gen_range_inclusive(7, 7), 7);
+            assert_eq!(
+                bounded.gen_range_inclusive(1, u64::MAX),
+                1 + sized.gen_range(u64::MAX)
+            );
+            // Full width: one whole draw, with no size to pass to gen_range.
+            assert_eq!(bounded.gen_range_inclusive(0, u64::MAX), sized.gen_bits(64));
+        }
+    }
+
+    #[test]
+    fn narrow_gen_range_matches_gen_range() {
+        let mut wide = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let mut narrow = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let sizes = [
+            1,
+            2,
+            3,
+            5,
+            100,
+            257,
+            65535,
+            65536,
+            65537,
+            (1 << 31) + 1,
+            u32::MAX,
+        ];
+        for &size in sizes.iter().cycle().take(20000) {
+            assert_eq!(
+                narrow.gen_range_u32(size) as u64,
+                wide.gen_range(size as u64)
+            );
+            if size <= 65535 {
+                let size = size as u16;
+                assert_eq!(
+                    narrow.gen_range_u16(size) as u64,
+                    wide.gen_range(size as u64)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn retry_bits_needed_agrees_with_its_definition() {
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let mut sizes: Vec<u64> = (2..64)
+            .flat_map(|k| [(1 << k) - 1, 1 << k, (1 << k) + 1])
+            .collect();
+        sizes.extend((0..1000).map(|_| {
+            let max = u64::MAX >> rng.gen_range(0..63);
+            rng.gen_range(2..max)
+        }));
+        for &size in &sizes {
+            for leftover_size in [1, size / 2, size - 1, rng.gen_range(1..size)] {
+                if leftover_size == 0 {
+                    continue;
+                }
+                let expected = (1..=64)
+                    .find(|&bits| (leftover_size as u128) << bits >= size as u128)
+                    .unwrap();
+                assert_eq!(
+                    super::retry_bits_needed_by_leading_zeros(leftover_size, size),
+                    expected,
+                    "{} {}",
+                    leftover_size,
+                    size
+                );
+                assert_eq!(
+                    super::retry_bits_needed_by_loop(leftover_size, size),
+                    expected,
+                    "{} {}",
+                    leftover_size,
+                    size
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn gen_range_gens_reasonably_distributed_values() {
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let mut buckets: Vec<Vec<u64>> = (0..18)
+            .map(|range_size| (0..range_size).map(|_| 0).collect())
+            .collect();
+        for _ in 0..1000000 {
+            let range_size = rng.gen_range(1..18);
+            let value = bitstream.gen_range(range_size);
+            assert!(value < range_size);
+            buckets[range_size as usize][value as usize] += 1;
+        }
+        dbg!(&buckets);
+        for (range_size, bucket) in buckets.into_iter().enumerate() {
+            let total_count = bucket.iter().sum::<u64>();
+            for (value, &count) in bucket.iter().enumerate() {
+                let share = count as f64 * range_size as f64 / total_count as f64;
+                assert!(
+                    share > 0.9 && share < 1.1,
+                    "extreme frequency {} at value {}/{}",
+                    share,
+                    value,
+                    range_size
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn sizes_above_2_63_retry_without_overflowing() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        for &size in &[3 << 62, (1 << 63) + (1 << 62) + 1, u64::MAX - 2] {
+            let mut thirds = [0; 3];
+            for _ in 0..30000 {
+                let value = bitstream.gen_range(size);
+                assert!(value < size);
+                thirds[(value / (size / 3 + 1)) as usize] += 1;
+            }
+            assert!(thirds.iter().all(|&count| (9500..10500).contains(&count)));
+        }
+    }
+
+    #[test]
+    fn cached_range_plans_match_fresh_ones() {
+        let sizes = [3, 1 << 40, 5, 3, (1 << 63) + 1, 17, u64::MAX, 1, 5, 3];
+        let mut cached = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let mut uncached = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        for _ in 0..1000 {
+            for &size in &sizes {
+                assert_eq!(
+                    cached.gen_range(size),
+                    RangePlan::new(size).sample(&mut uncached)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn const_range_matches_gen_range() {
+        let mut dynamic = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let mut constant = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        for _ in 0..1000 {
+            assert_eq!(dynamic.gen_range(1), ConstRange::<1>.sample(&mut constant));
+            assert_eq!(dynamic.gen_range(6), ConstRange::<6>.sample(&mut constant));
+            assert_eq!(
+                dynamic.gen_range(16),
+                ConstRange::<16>.sample(&mut constant)
+            );
+            assert_eq!(
+                dynamic.gen_range(u64::MAX),
+                ConstRange::<{ u64::MAX }>.sample(&mut constant)
+            );
+        }
+    }
+
+    #[test]
+    fn throughput_strategy_gens_reasonably_distributed_values() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        for &range_size in &[1, 3, 7, 10] {
+            let sampler = UniformRange::new(range_size).with_strategy(Strategy::Throughput);
+            let mut counts = vec![0u64; range_size as usize];
+            for _ in 0..100000 {
+                counts[sampler.sample(&mut bitstream) as usize] += 1;
+            }
+            for (value, &count) in counts.iter().enumerate() {
+                let share = count as f64 * range_size as f64 / 100000.0;
+                assert!(
+                    share > 0.9 && share < 1.1,
+                    "extreme frequency {} at value {}/{}",
+                    share,
+                    value,
+                    range_size
+                );
+            }
+        }
+        assert!(bitstream.gen_range_with(u64::MAX, Strategy::Throughput) < u64::MAX);
+    }
+
+    #[test]
+    fn edge_biased_range_favors_edges() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let range = EdgeBiasedRange::new(1000, 0.5);
+        assert_eq!(
+            range.edges(),
+            [0, 1, 2, 3, 4, 7, 8, 15, 16, 31, 32, 63, 64, 127, 128, 255, 256, 511, 512, 999]
+        );
+        let mut num_edges = 0;
+        for value in bitstream.samples(&range).take(100000) {
+            assert!(value < 1000);
+            if range.edges().contains(&value) {
+                num_edges += 1;
+            }
+        }
+        // 50% from the edge branch, plus 2% from the uniform one.
+        assert!((num_edges as f64 / 100000.0 - 0.51).abs() < 0.01);
+        assert_eq!(EdgeBiasedRange::new(1, 1.0).sample(&mut bitstream), 0);
+    }
+}
@@ -0,0 +1,52 @@
+use crate::{Bitstream, BitstreamExt};
+use std::io;
+
+/// Serves the bits of a bitstream as an endless `io::Read` byte source.
+pub struct BitstreamReader<B> {
+    bitstream: B,
+}
+
+impl<B> BitstreamReader<B> {
+    pub fn new(bitstream: B) -> Self {
+        BitstreamReader { bitstream }
+    }
+
+    pub fn get_ref(&self) -> &B {
+        &self.bitstream
+    }
+
+    pub fn get_mut(&mut self) -> &mut B {
+        &mut self.bitstream
+    }
+
+    pub fn into_inner(self) -> B {
+        self.bitstream
+    }
+}
+
+impl<B: Bitstream> io::Read for BitstreamReader<B> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.bitstream.fill_bytes(buf);
+        Ok(buf.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Bitstream, BitstreamReader, RngBitstream};
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+    use std::io::Read;
+
+    #[test]
+    fn reader_serves_bytes_in_stream_order() {
+        let mut direct = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let mut reader = BitstreamReader::new(RngBitstream::new(ChaChaRng::seed_from_u64(0)));
+        let mut bytes = Vec::new();
+        (&mut reader).take(1003).read_to_end(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), 1003);
+        for &byte in &bytes {
+            assert_eq!(byte as u64, direct.gen_bits(8));
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use crate::error::check_num_bits;
+use crate::{Bitstream, Error, RangePlan, TryBitstream};
+
+/// One `gen_bits` call: how many bits were asked for, and what came back.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Draw {
+    pub num_bits: u32,
+    pub value: u64,
+}
+
+/// A recording of every draw made from a bitstream, in order. Tapes compare
+/// equal when their draws are, whether or not they're sensitive.
+#[derive(Clone, Debug, Default)]
+pub struct Tape {
+    draws: Vec<Draw>,
+    #[cfg(feature = "zeroize")]
+    sensitive: bool,
+}
+
+impl PartialEq for Tape {
+    fn eq(&self, other: &Self) -> bool {
+        self.draws == other.draws
+    }
+}
+
+impl Eq for Tape {}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for Draw {
+    fn zeroize(&mut self) {
+        self.num_bits.zeroize();
+        self.value.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for Tape {
+    fn zeroize(&mut self) {
+        self.draws.zeroize();
+    }
+}
+
+/// Sensitive tapes are zeroized when dropped.
+#[cfg(feature = "zeroize")]
+impl Drop for Tape {
+    fn drop(&mut self) {
+        if self.sensitive {
+            zeroize::Zeroize::zeroize(self);
+        }
+    }
+}
+
+impl Tape {
+    pub fn new() -> Self {
+        Tape::default()
+    }
+
+    pub fn draws(&self) -> &[Draw] {
+        &self.draws
+    }
+
+    pub fn push(&mut self, draw: Draw) {
+        self.draws.push(draw);
+    }
+
+    /// The total number of bits recorded.
+    pub fn num_bits(&self) -> u64 {
+        self.draws.iter().map(|draw| draw.num_bits as u64).sum()
+    }
+
+    /// Whether to zeroize the draws when the tape is dropped (clones inherit
+    /// the flag). Growing the tape can leave copies of earlier draws behind
+    /// in freed memory, so for key material, record onto a tape created
+    /// with `with_capacity` for all the draws.
+    #[cfg(feature = "zeroize")]
+    pub fn set_sensitive(&mut self, sensitive: bool) {
+        self.sensitive = sensitive;
+    }
+
+    #[cfg(feature = "zeroize")]
+    pub fn is_sensitive(&self) -> bool {
+        self.sensitive
+    }
+
+    pub fn with_capacity(num_draws: usize) -> Self {
+        Tape::from(Vec::with_capacity(num_draws))
+    }
+}
+
+impl From<Vec<Draw>> for Tape {
+    fn from(draws: Vec<Draw>) -> Self {
+        Tape {
+            draws,
+            #[cfg(feature = "zeroize")]
+            sensitive: false,
+        }
+    }
+}
+
+/// Passes draws through from an inner bitstream, recording them on a tape.
+pub struct RecordingBitstream<B> {
+    inner: B,
+    tape: Tape,
+}
+
+impl<B> RecordingBitstream<B> {
+    pub fn new(inner: B) -> Self {
+        RecordingBitstream {
+            inner,
+            tape: Tape::new(),
+        }
+    }
+
+    /// Records onto `tape` instead of a new one, e.g. a sensitive tape with
+    /// preallocated capacity.
+    pub fn with_tape(inner: B, tape: Tape) -> Self {
+        RecordingBitstream { inner, tape }
+    }
+
+    pub fn tape(&self) -> &Tape {
+        &self.tape
+    }
+
+    pub fn into_parts(self) -> (B, Tape) {
+        (self.inner, self.tape)
+    }
+}
+
+impl<B: Bitstream> Bitstream for RecordingBitstream<B> {
+    fn gen_bits(&mut self, num_bits: u32) -> u64 {
+        let value = self.inner.gen_bits(num_bits);
+        self.tape.push(Draw { num_bits, value });
+        value
+    }
+
+    fn range_plan(&mut self, size: u64) -> RangePlan {
+        self.inner.range_plan(size)
+    }
+}
+
+/// Plays back a tape. Asking for a different number of bits than was recorded
+/// is a `ReplayDivergence` error, and reading past the end of the tape is
+/// `SourceExhausted`; the `Bitstream` impl panics on either.
+pub struct ReplayBitstream {
+    tape: Tape,
+    position: usize,
+}
+
+impl ReplayBitstream {
+    pub fn new(tape: Tape) -> Self {
+        ReplayBitstream { tape, position: 0 }
+    }
+
+    /// How many draws have been replayed so far.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.position == self.tape.draws.len()
+    }
+}
+
+impl TryBitstream for ReplayBitstream {
+    fn try_gen_bits(&mut self, num_bits: u32) -> Result<u64, Error> {
+        check_num_bits(num_bits)?;
+        let draw = *self
+            .tape
+            .draws
+            .get(self.position)
+            .ok_or(Error::SourceExhausted {
+                requested: num_bits as u64,
+                available: 0,
+            })?;
+        if draw.num_bits != num_bits {
+            return Err(Error::ReplayDivergence {
+                position: self.position,
+                recorded_bits: draw.num_bits,
+                requested_bits: num_bits,
+            });
+        }
+        self.position += 1;
+        Ok(draw.value)
+    }
+}
+
+impl Bitstream for ReplayBitstream {
+    fn gen_bits(&mut self, num_bits: u32) -> u64 {
+        self.try_gen_bits(num_bits)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        BitstreamExt, Error, RecordingBitstream, ReplayBitstream, RngBitstream, TryBitstreamExt,
+    };
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn replay_reproduces_recording() {
+        let mut recording = RecordingBitstream::new(RngBitstream::new(ChaChaRng::seed_from_u64(0)));
+        let recorded: Vec<u64> = (1..200).map(|size| recording.gen_range(size)).collect();
+        let (_, tape) = recording.into_parts();
+        let mut replay = ReplayBitstream::new(tape);
+        let replayed: Vec<u64> = (1..200).map(|size| replay.gen_range(size)).collect();
+        assert_eq!(recorded, replayed);
+        assert!(replay.is_finished());
+    }
+
+    #[test]
+    #[should_panic(expected = "replay diverged")]
+    fn replay_detects_divergence() {
+        let mut recording = RecordingBitstream::new(RngBitstream::new(ChaChaRng::seed_from_u64(0)));
+        recording.gen_range(6);
+        let (_, tape) = recording.into_parts();
+        ReplayBitstream::new(tape).gen_range(1000);
+    }
+
+    #[test]
+    fn try_replay_reports_errors() {
+        let mut recording = RecordingBitstream::new(RngBitstream::new(ChaChaRng::seed_from_u64(0)));
+        let value = recording.gen_range(8);
+        let (_, tape) = recording.into_parts();
+        let mut replay = ReplayBitstream::new(tape.clone());
+        assert!(matches!(
+            replay.try_gen_range(1000),
+            Err(Error::ReplayDivergence {
+                position: 0,
+                recorded_bits: 3,
+                requested_bits: 10
+            })
+        ));
+        let mut replay = ReplayBitstream::new(tape);
+        assert!(matches!(
+            replay.try_gen_range(0),
+            Err(Error::InvalidParameter(_))
+        ));
+        assert_eq!(replay.try_gen_range(8).unwrap(), value);
+        assert!(matches!(
+            replay.try_gen_range(8),
+            Err(Error::SourceExhausted { .. })
+        ));
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn zeroize_clears_tapes_and_buffers() {
+        use crate::{Bitstream, Tape};
+        use zeroize::Zeroize;
+
+        let mut tape = Tape::with_capacity(10);
+        tape.set_sensitive(true);
+        let mut recording =
+            RecordingBitstream::with_tape(RngBitstream::new(ChaChaRng::seed_from_u64(0)), tape);
+        recording.gen_bits(10);
+        let (mut bitstream, mut tape) = recording.into_parts();
+        assert!(tape.is_sensitive() && tape.num_bits() == 10);
+        assert_eq!(tape, Tape::from(tape.draws().to_vec()));
+        assert_eq!(bitstream.unused_bits(), 54);
+        tape.zeroize();
+        bitstream.zeroize();
+        assert_eq!(tape.num_bits(), 0);
+        assert_eq!(bitstream.unused_bits(), 0);
+    }
+}
@@ -0,0 +1,99 @@
+//! A shared loop for rejection samplers, with the counts needed to tell
+//! whether a proposal is a good fit.
+
+use crate::source::BitMeter;
+use crate::Bitstream;
+use std::ops::AddAssign;
+
+/// What a rejection sampler cost. Add the stats of many samples together to
+/// get rates over a run.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct RejectionStats {
+    pub attempts: u64,
+    pub accepted: u64,
+    /// Bits drawn by both the proposals and the acceptance tests.
+    pub bits: u64,
+}
+
+impl RejectionStats {
+    pub fn acceptance_rate(&self) -> f64 {
+        self.accepted as f64 / self.attempts as f64
+    }
+
+    pub fn bits_per_sample(&self) -> f64 {
+        self.bits as f64 / self.accepted as f64
+    }
+}
+
+impl AddAssign for RejectionStats {
+    fn add_assign(&mut self, other: RejectionStats) {
+        self.attempts += other.attempts;
+        self.accepted += other.accepted;
+        self.bits += other.bits;
+    }
+}
+
+/// Draws from `proposal` until `accept` approves a candidate, and returns it
+/// with what it took. `accept` gets the bitstream too, for the usual
+/// "uniform below target density over envelope" test.
+pub fn rejection_sample<B, T, P, A>(
+    bitstream: &mut B,
+    mut proposal: P,
+    mut accept: A,
+) -> (T, RejectionStats)
+where
+    B: Bitstream + ?Sized,
+    P: FnMut(&mut dyn Bitstream) -> T,
+    A: FnMut(&mut dyn Bitstream, &T) -> bool,
+{
+    let mut meter = BitMeter::new(bitstream);
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let candidate = proposal(&mut meter);
+        if accept(&mut meter, &candidate) {
+            let stats = RejectionStats {
+                attempts,
+                accepted: 1,
+                bits: meter.bits(),
+            };
+            return (candidate, stats);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{rejection_sample, BitstreamExt, RejectionStats, RngBitstream};
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn stats_track_the_rejection_loop() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        // A uniform point in the unit disc from the square around it: pi/4
+        // of proposals land inside, at 2 * 20 bits each.
+        let mut total = RejectionStats::default();
+        for _ in 0..10000 {
+            let ((x, y), stats) = rejection_sample(
+                &mut bitstream,
+                |b| {
+                    let mut coordinate = || b.gen_bits(20) as f64 / (1 << 19) as f64 - 1.0;
+                    (coordinate(), coordinate())
+                },
+                |_, &(x, y)| x * x + y * y < 1.0,
+            );
+            assert!(x * x + y * y < 1.0);
+            assert_eq!(stats.bits, 40 * stats.attempts);
+            total += stats;
+        }
+        assert_eq!(total.accepted, 10000);
+        let pi_over_4 = std::f64::consts::FRAC_PI_4;
+        assert!((total.acceptance_rate() - pi_over_4).abs() < 0.01);
+        assert!((total.bits_per_sample() - 40.0 / pi_over_4).abs() < 0.5);
+
+        // Acceptance tests that draw bits are counted too.
+        let (_, stats) = rejection_sample(&mut bitstream, |b| b.gen_range(6), |b, _| b.gen_bit());
+        assert!(stats.bits >= stats.attempts * 3);
+    }
+}
@@ -0,0 +1,249 @@
+//! Random constraint satisfaction instances, for benchmarking solvers on
+//! reproducible inputs.
+
+use crate::seq::distinct;
+use crate::{Bitstream, Distribution};
+use std::fmt;
+
+/// A formula in conjunctive normal form. Literals are as in DIMACS: variable
+/// `v` (counting from 1) is `v`, and its negation `-v`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Formula {
+    pub num_variables: usize,
+    pub clauses: Vec<Vec<i64>>,
+}
+
+impl Formula {
+    pub fn is_satisfied_by(&self, assignment: &[bool]) -> bool {
+        self.clauses.iter().all(|clause| {
+            clause
+                .iter()
+                .any(|&literal| assignment[literal.unsigned_abs() as usize - 1] == (literal > 0))
+        })
+    }
+}
+
+/// The DIMACS CNF format most SAT solvers read.
+impl fmt::Display for Formula {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "p cnf {} {}", self.num_variables, self.clauses.len())?;
+        for clause in &self.clauses {
+            for literal in clause {
+                write!(f, "{} ", literal)?;
+            }
+            writeln!(f, "0")?;
+        }
+        Ok(())
+    }
+}
+
+/// Uniform random k-SAT: each clause has `k` distinct variables, each negated
+/// with probability 1/2. Random 3-SAT is hardest around 4.27 clauses per
+/// variable.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct RandomKSat {
+    num_variables: usize,
+    k: usize,
+    num_clauses: usize,
+}
+
+impl RandomKSat {
+    /// # Panics
+    ///
+    /// If `k > num_variables`.
+    pub fn new(num_variables: usize, k: usize, num_clauses: usize) -> Self {
+        assert!(k <= num_variables, "k must be at most num_variables");
+        RandomKSat {
+            num_variables,
+            k,
+            num_clauses,
+        }
+    }
+
+    /// Instances with `ratio` clauses per variable, rounded.
+    ///
+    /// # Panics
+    ///
+    /// As `new`, or if `ratio` is negative or not finite.
+    pub fn with_ratio(num_variables: usize, k: usize, ratio: f64) -> Self {
+        assert!(
+            ratio >= 0.0 && ratio.is_finite(),
+            "ratio must be finite and nonnegative"
+        );
+        RandomKSat::new(
+            num_variables,
+            k,
+            (ratio * num_variables as f64).round() as usize,
+        )
+    }
+}
+
+impl Distribution<Formula> for RandomKSat {
+    fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> Formula {
+        let clauses = (0..self.num_clauses)
+            .map(|_| {
+                distinct(bitstream, self.num_variables as u64, self.k as u64)
+                    .collect::<Vec<_>>()
+                    .into_iter()
+                    .map(|variable| {
+                        let literal = variable as i64 + 1;
+                        if bitstream.gen_bit() {
+                            -literal
+                        } else {
+                            literal
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        Formula {
+            num_variables: self.num_variables,
+            clauses,
+        }
+    }
+}
+
+/// A binary constraint: the pairs of values `variables` may not take.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Constraint {
+    pub variables: (usize, usize),
+    pub forbidden: Vec<(usize, usize)>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Csp {
+    pub num_variables: usize,
+    pub domain_size: usize,
+    pub constraints: Vec<Constraint>,
+}
+
+impl Csp {
+    pub fn is_satisfied_by(&self, assignment: &[usize]) -> bool {
+        self.constraints.iter().all(|constraint| {
+            let (first, second) = constraint.variables;
+            !constraint
+                .forbidden
+                .contains(&(assignment[first], assignment[second]))
+        })
+    }
+}
+
+/// Binary constraint satisfaction problems in "model B": exactly
+/// `num_constraints` distinct pairs of variables are constrained, each
+/// forbidding exactly `num_forbidden` distinct pairs of values. The
+/// constraint density and tightness are `num_constraints / C(n, 2)` and
+/// `num_forbidden / domain_size^2`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct RandomCsp {
+    num_variables: usize,
+    domain_size: usize,
+    num_constraints: usize,
+    num_forbidden: usize,
+}
+
+impl RandomCsp {
+    /// # Panics
+    ///
+    /// If there are more constraints than pairs of variables, or more
+    /// forbidden pairs than pairs of values.
+    pub fn new(
+        num_variables: usize,
+        domain_size: usize,
+        num_constraints: usize,
+        num_forbidden: usize,
+    ) -> Self {
+        assert!(
+            num_constraints <= num_variables * num_variables.saturating_sub(1) / 2,
+            "too many constraints for the number of variables"
+        );
+        assert!(
+            num_forbidden <= domain_size * domain_size,
+            "too many forbidden pairs for the domain size"
+        );
+        RandomCsp {
+            num_variables,
+            domain_size,
+            num_constraints,
+            num_forbidden,
+        }
+    }
+}
+
+impl Distribution<Csp> for RandomCsp {
+    fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> Csp {
+        let n = self.num_variables as u64;
+        let d = self.domain_size as u64;
+        let num_pairs = n * n.saturating_sub(1) / 2;
+        let pairs: Vec<u64> = distinct(bitstream, num_pairs, self.num_constraints as u64).collect();
+        let constraints = pairs
+            .into_iter()
+            .map(|index| {
+                // Unrank the index-th pair (first, second) with first < second,
+                // ordered by second.
+                let mut second = 1;
+                while second * (second + 1) / 2 <= index {
+                    second += 1;
+                }
+                let first = index - second * (second - 1) / 2;
+                let forbidden = distinct(bitstream, d * d, self.num_forbidden as u64)
+                    .map(|pair| ((pair / d) as usize, (pair % d) as usize))
+                    .collect();
+                Constraint {
+                    variables: (first as usize, second as usize),
+                    forbidden,
+                }
+            })
+            .collect();
+        Csp {
+            num_variables: self.num_variables,
+            domain_size: self.domain_size,
+            constraints,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BitstreamExt, RngBitstream};
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn instances_have_the_requested_shape() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let formula = bitstream.sample(RandomKSat::with_ratio(20, 3, 4.25));
+        assert_eq!(formula.clauses.len(), 85);
+        let mut negated = 0;
+        for clause in &formula.clauses {
+            let mut variables: Vec<u64> = clause.iter().map(|l| l.unsigned_abs()).collect();
+            variables.sort_unstable();
+            variables.dedup();
+            assert_eq!(variables.len(), 3);
+            assert!(variables.iter().all(|&v| (1..=20).contains(&v)));
+            negated += clause.iter().filter(|&&l| l < 0).count();
+        }
+        assert!((100..155).contains(&negated));
+        assert!(formula.to_string().starts_with("p cnf 20 85\n"));
+        let mut again = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        assert_eq!(again.sample(RandomKSat::with_ratio(20, 3, 4.25)), formula);
+
+        let csp = bitstream.sample(RandomCsp::new(10, 4, 45, 3));
+        let mut pairs: Vec<_> = csp.constraints.iter().map(|c| c.variables).collect();
+        pairs.sort_unstable();
+        pairs.dedup();
+        assert_eq!(pairs.len(), 45);
+        for constraint in &csp.constraints {
+            let (first, second) = constraint.variables;
+            assert!(first < second && second < 10);
+            assert_eq!(constraint.forbidden.len(), 3);
+            assert!(constraint.forbidden.iter().all(|&(a, b)| a < 4 && b < 4));
+        }
+        let assignment = [0; 10];
+        let violated = csp
+            .constraints
+            .iter()
+            .any(|c| c.forbidden.contains(&(0, 0)));
+        assert_eq!(csp.is_satisfied_by(&assignment), !violated);
+    }
+}
@@ -0,0 +1,419 @@
+use crate::dist::open_unit_f64;
+use crate::{Bitstream, BitstreamExt};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashSet};
+
+/// Shuffles `slice` uniformly (Fisher-Yates).
+pub fn shuffle<B: Bitstream + ?Sized, T>(bitstream: &mut B, slice: &mut [T]) {
+    for index in (1..slice.len()).rev() {
+        let other = bitstream.gen_range(index as u64 + 1) as usize;
+        slice.swap(index, other);
+    }
+}
+
+/// Shuffles `items` so that heavier items tend to come first: each next
+/// item is picked from those left with probability proportional to its
+/// weight. Each item gets the key `ln(u) / weight` for a uniform `u`, and
+/// the items are sorted by key, largest first (Efraimidis and Spirakis).
+/// Items of weight 0 go last, in uniformly random order.
+///
+/// # Panics
+///
+/// If `weights` isn't as long as `items`, or a weight is negative or not
+/// finite.
+pub fn weighted_shuffle<B: Bitstream + ?Sized, T>(
+    bitstream: &mut B,
+    items: &mut [T],
+    weights: &[f64],
+) {
+    assert_eq!(
+        items.len(),
+        weights.len(),
+        "need exactly one weight per item"
+    );
+    assert!(
+        weights
+            .iter()
+            .all(|weight| weight.is_finite() && *weight >= 0.0),
+        "weights must be finite and nonnegative"
+    );
+    let mut order: Vec<(f64, usize)> = weights
+        .iter()
+        .enumerate()
+        .map(|(index, &weight)| {
+            let key = if weight > 0.0 {
+                open_unit_f64(bitstream).ln() / weight
+            } else {
+                f64::NEG_INFINITY
+            };
+            (key, index)
+        })
+        .collect();
+    order.sort_by(|a, b| b.0.total_cmp(&a.0));
+    let num_weighted = order.iter().filter(|(key, _)| key.is_finite()).count();
+    shuffle(bitstream, &mut order[num_weighted..]);
+    // Move each item to its rank, one swap at a time.
+    let mut rank = vec![0; items.len()];
+    for (position, &(_, index)) in order.iter().enumerate() {
+        rank[index] = position;
+    }
+    for index in 0..items.len() {
+        while rank[index] != index {
+            let target = rank[index];
+            items.swap(index, target);
+            rank.swap(index, target);
+        }
+    }
+}
+
+/// `k` indices drawn uniformly with replacement from `0..n`, as for one
+/// bootstrap resample of a data set of size `n`.
+///
+/// # Panics
+///
+/// If `n` is 0 and `k` isn't.
+pub fn bootstrap_indices<B: Bitstream + ?Sized>(
+    bitstream: &mut B,
+    n: usize,
+    k: usize,
+) -> Vec<usize> {
+    if k == 0 {
+        return Vec::new();
+    }
+    assert!(n > 0, "can't resample from an empty data set");
+    bitstream
+        .values(n as u64)
+        .take(k)
+        .map(|index| index as usize)
+        .collect()
+}
+
+/// A permutation test of whether `first` and `second` come from the same
+/// distribution. Computes `statistic(first, second)`, then recomputes it
+/// `num_permutations` times with the group labels shuffled, and returns the
+/// p-value: the fraction of statistics (counting the observed one) at least
+/// as large as the observed one. For a two-sided test, make `statistic`
+/// return an absolute difference.
+pub fn permutation_test<B, T, F>(
+    bitstream: &mut B,
+    first: &[T],
+    second: &[T],
+    num_permutations: usize,
+    mut statistic: F,
+) -> f64
+where
+    B: Bitstream + ?Sized,
+    T: Clone,
+    F: FnMut(&[T], &[T]) -> f64,
+{
+    let observed = statistic(first, second);
+    let mut pooled: Vec<T> = first.iter().chain(second).cloned().collect();
+    let mut as_extreme = 1;
+    for _ in 0..num_permutations {
+        shuffle(bitstream, &mut pooled);
+        let (first, second) = pooled.split_at(first.len());
+        if statistic(first, second) >= observed {
+            as_extreme += 1;
+        }
+    }
+    as_extreme as f64 / (num_permutations + 1) as f64
+}
+
+/// `k` distinct values drawn uniformly from `0..n`, by Floyd's algorithm,
+/// which takes one `gen_range` and `O(1)` memory per value, however large `n`
+/// is. Every set of `k` values is equally likely, but the order isn't
+/// uniformly random: shuffle the values if it matters.
+///
+/// # Panics
+///
+/// If `k > n`.
+pub fn distinct<B: Bitstream + ?Sized>(bitstream: &mut B, n: u64, k: u64) -> Distinct<'_, B> {
+    assert!(k <= n, "can't draw more distinct values than there are");
+    Distinct {
+        bitstream,
+        next: n - k,
+        end: n,
+        chosen: HashSet::new(),
+    }
+}
+
+pub struct Distinct<'a, B: ?Sized> {
+    bitstream: &'a mut B,
+    // Floyd's loop variable, running up to end.
+    next: u64,
+    end: u64,
+    chosen: HashSet<u64>,
+}
+
+impl<'a, B: Bitstream + ?Sized> Iterator for Distinct<'a, B> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        if self.next == self.end {
+            return None;
+        }
+        let j = self.next;
+        self.next += 1;
+        let candidate = self.bitstream.gen_range(j + 1);
+        let value = if self.chosen.insert(candidate) {
+            candidate
+        } else {
+            self.chosen.insert(j);
+            j
+        };
+        Some(value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.end - self.next) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a, B: Bitstream + ?Sized> ExactSizeIterator for Distinct<'a, B> {}
+
+/// Weighted reservoir sampling: keeps `k` items of a stream of unknown
+/// length, chosen without replacement with probability proportional to their
+/// weights, as by successively drawing from the weighted whole.
+///
+/// This is Efraimidis and Spirakis' A-ExpJ: rather than drawing a random key
+/// for every item, it draws how much weight to skip before the next item
+/// that enters the reservoir, so a long stream costs random bits only for
+/// the `O(k log(n / k))` items that enter.
+pub struct WeightedReservoir<T> {
+    k: usize,
+    // A min-heap of the kept items by key, where keys are ln(u) / weight.
+    heap: BinaryHeap<Keyed<T>>,
+    // How much more weight to pass over before the next item enters.
+    skip: f64,
+}
+
+struct Keyed<T> {
+    key: f64,
+    item: T,
+}
+
+impl<T> PartialEq for Keyed<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T> Eq for Keyed<T> {}
+
+impl<T> PartialOrd for Keyed<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Keyed<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.key.total_cmp(&self.key)
+    }
+}
+
+impl<T> WeightedReservoir<T> {
+    /// # Panics
+    ///
+    /// If `k` is 0.
+    pub fn new(k: usize) -> Self {
+        assert!(k > 0, "the reservoir must hold at least one item");
+        WeightedReservoir {
+            k,
+            heap: BinaryHeap::with_capacity(k),
+            skip: 0.0,
+        }
+    }
+
+    /// # Panics
+    ///
+    /// If `weight` isn't positive and finite.
+    pub fn offer<B: Bitstream + ?Sized>(&mut self, bitstream: &mut B, item: T, weight: f64) {
+        assert!(
+            weight > 0.0 && weight.is_finite(),
+            "weight must be positive and finite"
+        );
+        if self.heap.len() < self.k {
+            let key = open_unit_f64(bitstream).ln() / weight;
+            self.heap.push(Keyed { key, item });
+            if self.heap.len() == self.k {
+                self.draw_skip(bitstream);
+            }
+            return;
+        }
+        self.skip -= weight;
+        if self.skip > 0.0 {
+            return;
+        }
+        // This item enters with a key conditioned to beat the threshold:
+        // u uniform in (e^(threshold * weight), 1).
+        let threshold = self.heap.peek().unwrap().key;
+        let low = (threshold * weight).exp();
+        let u = low + (1.0 - low) * open_unit_f64(bitstream);
+        let key = (u.ln() / weight).max(threshold);
+        self.heap.pop();
+        self.heap.push(Keyed { key, item });
+        self.draw_skip(bitstream);
+    }
+
+    fn draw_skip<B: Bitstream + ?Sized>(&mut self, bitstream: &mut B) {
+        let threshold = self.heap.peek().unwrap().key;
+        self.skip = open_unit_f64(bitstream).ln() / threshold;
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    /// The kept items, in no particular order.
+    pub fn into_items(self) -> Vec<T> {
+        self.heap.into_iter().map(|keyed| keyed.item).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::seq::{bootstrap_indices, distinct, permutation_test, WeightedReservoir};
+    use crate::CountingRngBitstream;
+    use crate::{BitstreamExt, RngBitstream};
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn shuffle_gens_reasonably_distributed_permutations() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let mut counts = std::collections::HashMap::new();
+        for _ in 0..60000 {
+            let mut values = [0, 1, 2];
+            bitstream.shuffle(&mut values);
+            *counts.entry(values).or_insert(0) += 1;
+        }
+        assert_eq!(counts.len(), 6);
+        for (permutation, &count) in &counts {
+            assert!(
+                count > 9000 && count < 11000,
+                "extreme count {} for {:?}",
+                count,
+                permutation
+            );
+        }
+    }
+
+    #[test]
+    fn weighted_shuffle_picks_in_proportion_to_weight() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let weights = [1.0, 2.0, 0.0, 5.0, 0.0];
+        let mut counts = std::collections::HashMap::new();
+        let mut zeros_first = 0;
+        for _ in 0..80000 {
+            let mut items = ['a', 'b', 'c', 'd', 'e'];
+            bitstream.weighted_shuffle(&mut items, &weights);
+            assert!(items[3] == 'c' || items[3] == 'e');
+            zeros_first += (items[3] == 'c') as u32;
+            *counts.entry([items[0], items[1]]).or_insert(0) += 1;
+        }
+        assert!((zeros_first as f64 / 80000.0 - 0.5).abs() < 0.01);
+        // Each next pick is proportional to the weight left: d then b has
+        // probability 5/8 * 2/3.
+        for &((first, second), probability) in &[
+            (('d', 'b'), 5.0 / 8.0 * 2.0 / 3.0),
+            (('d', 'a'), 5.0 / 8.0 / 3.0),
+            (('b', 'd'), 2.0 / 8.0 * 5.0 / 6.0),
+            (('a', 'd'), 1.0 / 8.0 * 5.0 / 7.0),
+        ] {
+            let count = counts[&[first, second]];
+            assert!(
+                (count as f64 / 80000.0 - probability).abs() < 0.01,
+                "{:?}",
+                counts
+            );
+        }
+    }
+
+    #[test]
+    fn resampling_helpers_work() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let mut counts = [0; 5];
+        for index in bootstrap_indices(&mut bitstream, 5, 50000) {
+            counts[index] += 1;
+        }
+        assert!(counts.iter().all(|&count| count > 9500 && count < 10500));
+
+        let mean_difference = |a: &[f64], b: &[f64]| {
+            let mean = |values: &[f64]| values.iter().sum::<f64>() / values.len() as f64;
+            (mean(a) - mean(b)).abs()
+        };
+        let low = [1.0, 2.0, 3.0, 2.0, 1.5, 2.5, 1.0, 3.0];
+        let high = [7.0, 8.0, 6.5, 9.0, 7.5, 8.0, 6.0, 7.0];
+        let mixed = [7.0, 1.0, 2.5, 8.0, 3.0, 6.0, 1.5, 9.0];
+        let different = permutation_test(&mut bitstream, &low, &high, 999, mean_difference);
+        let similar = permutation_test(&mut bitstream, &mixed, &low, 999, mean_difference);
+        assert!(different < 0.01, "{}", different);
+        assert!(similar > 0.05, "{}", similar);
+    }
+
+    #[test]
+    fn distinct_values_are_distinct_and_uniform() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let huge: Vec<u64> = distinct(&mut bitstream, u64::MAX, 1000).collect();
+        let mut deduplicated = huge.clone();
+        deduplicated.sort_unstable();
+        deduplicated.dedup();
+        assert_eq!(deduplicated.len(), 1000);
+        assert!(huge.iter().filter(|&&value| value < 1 << 63).count() > 400);
+
+        let mut counts = std::collections::HashMap::new();
+        for _ in 0..60000 {
+            let mut pair: Vec<u64> = distinct(&mut bitstream, 4, 2).collect();
+            pair.sort_unstable();
+            *counts.entry(pair).or_insert(0) += 1;
+        }
+        assert_eq!(counts.len(), 6);
+        assert!(counts.values().all(|&count| count > 9500 && count < 10500));
+        assert_eq!(distinct(&mut bitstream, 5, 5).len(), 5);
+    }
+
+    #[test]
+    fn weighted_reservoir_keeps_items_by_weight() {
+        let mut bitstream = CountingRngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let num_trials = 20000;
+        let mut counts = [0; 5];
+        for _ in 0..num_trials {
+            let mut reservoir = WeightedReservoir::new(1);
+            for (index, &weight) in [1.0, 2.0, 3.0, 4.0, 10.0].iter().enumerate() {
+                reservoir.offer(&mut bitstream, index, weight);
+            }
+            counts[reservoir.into_items()[0]] += 1;
+        }
+        for (count, weight) in counts.iter().zip(&[1.0, 2.0, 3.0, 4.0, 10.0]) {
+            assert!((*count as f64 / num_trials as f64 - weight / 20.0).abs() < 0.01);
+        }
+
+        // Two of three equal items, and one heavy one: the heavy one is
+        // almost always kept.
+        let mut kept_heavy = 0;
+        for _ in 0..num_trials {
+            let mut reservoir = WeightedReservoir::new(2);
+            for (index, &weight) in [1.0, 1000.0, 1.0, 1.0].iter().enumerate() {
+                reservoir.offer(&mut bitstream, index, weight);
+            }
+            let items = reservoir.into_items();
+            assert_eq!(items.len(), 2);
+            kept_heavy += items.contains(&1) as usize;
+        }
+        assert!(kept_heavy as f64 / num_trials as f64 > 0.99);
+
+        let before = bitstream.count();
+        let mut reservoir = WeightedReservoir::new(10);
+        for index in 0..100000 {
+            reservoir.offer(&mut bitstream, index, 1.0 + (index % 3) as f64);
+        }
+        assert_eq!(reservoir.len(), 10);
+        assert!(bitstream.count() - before < 52 * 500);
+    }
+}
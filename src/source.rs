@@ -0,0 +1,747 @@
+use crate::error::{assert_num_bits, check_num_bits};
+use crate::{low_bits_mask, Bitstream, Error, PeekBitstream, RangePlan, TryBitstream};
+use rand::{Rng, SeedableRng};
+use std::marker::PhantomData;
+
+const RANGE_CACHE_SIZE: usize = 3;
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// The order in which `RngBitstream` hands out the bits of each RNG word.
+/// Both orders are part of the crate's stable output: for a given RNG, the
+/// same calls always return the same values.
+pub trait BitOrder: sealed::Sealed {
+    /// Takes `num_bits` bits from a buffer holding at least that many.
+    #[doc(hidden)]
+    fn take(buffer: &mut u64, num_bits: u32) -> u64;
+    /// Takes all `unused_bits` buffered bits followed by the first
+    /// `num_bits - unused_bits` bits of `fresh`, leaving the rest of `fresh`
+    /// in the buffer. Requires `unused_bits < num_bits <= 64`.
+    #[doc(hidden)]
+    fn take_refilled(buffer: &mut u64, unused_bits: u32, fresh: u64, num_bits: u32) -> u64;
+    /// Puts `num_bits` bits in front of the buffered bits, so that
+    /// `take(buffer, num_bits)` returns them. Requires that they fit.
+    #[doc(hidden)]
+    fn push(buffer: &mut u64, value: u64, num_bits: u32);
+}
+
+/// The default order: bits are taken from the least significant end of each
+/// word first, and the first bit taken becomes the least significant bit of
+/// the result. `gen_bits(64)` returns each word unchanged.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct LsbFirst;
+
+/// Bits are taken from the most significant end of each word first, and the
+/// first bit taken becomes the most significant bit of the result, as in C
+/// code that does `word >> (64 - n)` then `word <<= n`. `gen_bits(64)` also
+/// returns each word unchanged.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct MsbFirst;
+
+impl sealed::Sealed for LsbFirst {}
+impl sealed::Sealed for MsbFirst {}
+
+impl BitOrder for LsbFirst {
+    // The buffer is kept right-aligned: its low `unused_bits` bits are the
+    // next bits of the stream, and everything above them is zero.
+    fn take(buffer: &mut u64, num_bits: u32) -> u64 {
+        let result = *buffer & low_bits_mask(num_bits);
+        *buffer = buffer.checked_shr(num_bits).unwrap_or(0);
+        result
+    }
+
+    fn take_refilled(buffer: &mut u64, unused_bits: u32, fresh: u64, num_bits: u32) -> u64 {
+        // unused_bits < num_bits <= 64, so neither shift here can overflow.
+        let extra_bits = num_bits - unused_bits;
+        let result = (*buffer | (fresh << unused_bits)) & low_bits_mask(num_bits);
+        *buffer = (fresh >> (extra_bits - 1)) >> 1;
+        result
+    }
+
+    fn push(buffer: &mut u64, value: u64, num_bits: u32) {
+        *buffer = buffer.checked_shl(num_bits).unwrap_or(0) | (value & low_bits_mask(num_bits));
+    }
+}
+
+impl BitOrder for MsbFirst {
+    // The buffer is kept left-aligned: its high `unused_bits` bits are the
+    // next bits of the stream, and everything below them is zero.
+    fn take(buffer: &mut u64, num_bits: u32) -> u64 {
+        let result = buffer.checked_shr(64 - num_bits).unwrap_or(0);
+        *buffer = buffer.checked_shl(num_bits).unwrap_or(0);
+        result
+    }
+
+    fn take_refilled(buffer: &mut u64, unused_bits: u32, fresh: u64, num_bits: u32) -> u64 {
+        let extra_bits = num_bits - unused_bits;
+        let buffered = buffer.checked_shr(64 - unused_bits).unwrap_or(0);
+        let result = buffered.checked_shl(extra_bits).unwrap_or(0) | (fresh >> (64 - extra_bits));
+        *buffer = fresh.checked_shl(extra_bits).unwrap_or(0);
+        result
+    }
+
+    fn push(buffer: &mut u64, value: u64, num_bits: u32) {
+        let value = value & low_bits_mask(num_bits);
+        *buffer = value.checked_shl(64 - num_bits).unwrap_or(0)
+            | buffer.checked_shr(num_bits).unwrap_or(0);
+    }
+}
+
+pub struct RngBitstream<T, O: BitOrder = LsbFirst> {
+    rng: T,
+    bit_buffer: u64,
+    unused_bits: u32,
+    // A word already taken from `rng` by `peek_bits`, to be used by the next refill.
+    peeked_word: Option<u64>,
+    range_cache: [Option<RangePlan>; RANGE_CACHE_SIZE],
+    next_cache_slot: usize,
+    _order: PhantomData<O>,
+}
+
+pub struct CountingRngBitstream<T> {
+    bitstream: RngBitstream<T>,
+    count: u64,
+}
+
+impl<T, O: BitOrder> RngBitstream<T, O> {
+    fn with_order(rng: T) -> Self {
+        RngBitstream {
+            rng,
+            bit_buffer: 0,
+            unused_bits: 0,
+            peeked_word: None,
+            range_cache: [None; RANGE_CACHE_SIZE],
+            next_cache_slot: 0,
+            _order: PhantomData,
+        }
+    }
+
+    /// The number of bits already drawn from the RNG but not yet handed out.
+    pub fn unused_bits(&self) -> u32 {
+        self.unused_bits
+    }
+
+    /// Removes the buffered bits, returning them as `(value, num_bits)` where
+    /// `value` is what `gen_bits(num_bits)` would have returned. Together with
+    /// `push_bits`, this lets leftover entropy move to another bitstream
+    /// instead of being discarded.
+    pub fn drain_buffer(&mut self) -> (u64, u32) {
+        let num_bits = self.unused_bits;
+        self.unused_bits = 0;
+        (O::take(&mut self.bit_buffer, num_bits), num_bits)
+    }
+
+    /// Puts `num_bits` bits in front of the stream, so that the next
+    /// `gen_bits(num_bits)` returns `value` (truncated to `num_bits` bits).
+    ///
+    /// # Panics
+    ///
+    /// If the buffer can't hold them, i.e. `unused_bits() + num_bits > 64`.
+    pub fn push_bits(&mut self, value: u64, num_bits: u32) {
+        assert!(
+            num_bits <= 64 - self.unused_bits,
+            "can't push {} bits onto a buffer holding {}",
+            num_bits,
+            self.unused_bits
+        );
+        O::push(&mut self.bit_buffer, value, num_bits);
+        self.unused_bits += num_bits;
+    }
+}
+
+/// Clears the buffered bits. The RNG's own state isn't touched; wrap a
+/// generator that zeroizes itself if that matters too.
+#[cfg(feature = "zeroize")]
+impl<T, O: BitOrder> zeroize::Zeroize for RngBitstream<T, O> {
+    fn zeroize(&mut self) {
+        self.bit_buffer.zeroize();
+        self.unused_bits.zeroize();
+        self.peeked_word.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<T, O: BitOrder> Drop for RngBitstream<T, O> {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(self);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<T, O: BitOrder> zeroize::ZeroizeOnDrop for RngBitstream<T, O> {}
+
+impl<T> RngBitstream<T> {
+    pub fn new(rng: T) -> Self {
+        RngBitstream::with_order(rng)
+    }
+}
+
+impl<T> RngBitstream<T, MsbFirst> {
+    pub fn new_msb_first(rng: T) -> Self {
+        RngBitstream::with_order(rng)
+    }
+}
+
+/// The generator used by `RngBitstream::seeded` and friends: ChaCha with 20
+/// rounds, from `rand_chacha`.
+pub type DefaultRng = rand_chacha::ChaCha20Rng;
+
+impl RngBitstream<DefaultRng> {
+    /// A bitstream over `DefaultRng` seeded with `seed`.
+    pub fn seeded(seed: u64) -> Self {
+        RngBitstream::new(DefaultRng::seed_from_u64(seed))
+    }
+
+    /// A bitstream over `DefaultRng` for the substream of `seed` named by
+    /// `key`. Different keys give independent streams, and the same seed and
+    /// key always give the same stream.
+    pub fn keyed(seed: u64, key: &[u8]) -> Self {
+        let mut rng = DefaultRng::seed_from_u64(seed);
+        rng.set_stream(stable_hash(key));
+        RngBitstream::new(rng)
+    }
+
+    /// A bitstream over `DefaultRng` seeded from OS entropy.
+    pub fn from_entropy() -> Self {
+        RngBitstream::new(DefaultRng::from_entropy())
+    }
+
+    /// A bitstream over `DefaultRng` seeded from another RNG.
+    pub fn from_rng<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        let mut seed = <DefaultRng as SeedableRng>::Seed::default();
+        rng.fill(&mut seed);
+        RngBitstream::new(DefaultRng::from_seed(seed))
+    }
+}
+
+/// Counts the bits drawn through it, for APIs that report their own
+/// entropy use.
+pub(crate) struct BitMeter<'a, B: ?Sized> {
+    bitstream: &'a mut B,
+    bits: u64,
+}
+
+impl<'a, B: ?Sized> BitMeter<'a, B> {
+    pub(crate) fn new(bitstream: &'a mut B) -> Self {
+        BitMeter { bitstream, bits: 0 }
+    }
+
+    pub(crate) fn bits(&self) -> u64 {
+        self.bits
+    }
+}
+
+impl<B: Bitstream + ?Sized> Bitstream for BitMeter<'_, B> {
+    fn gen_bits(&mut self, num_bits: u32) -> u64 {
+        self.bits += num_bits as u64;
+        self.bitstream.gen_bits(num_bits)
+    }
+
+    fn range_plan(&mut self, size: u64) -> RangePlan {
+        self.bitstream.range_plan(size)
+    }
+}
+
+/// SplitMix64's output function: a bijection on `u64` that scrambles nearby
+/// inputs into unrelated outputs.
+pub(crate) fn mix64(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// 64-bit FNV-1a, which unlike `std`'s hashers is guaranteed not to change
+/// between releases.
+pub(crate) fn stable_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+impl<T> CountingRngBitstream<T> {
+    pub fn new(rng: T) -> Self {
+        CountingRngBitstream {
+            bitstream: RngBitstream::new(rng),
+            count: 0,
+        }
+    }
+
+    /// The total number of bits requested so far.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+}
+
+impl<T: Rng + SeedableRng, O: BitOrder> RngBitstream<T, O> {
+    /// Seeds a new, independent bitstream from this one's RNG.
+    pub fn fork(&mut self) -> Self {
+        RngBitstream::with_order(T::from_rng(&mut self.rng).expect("seeding from an Rng failed"))
+    }
+}
+
+impl<T: Rng, O: BitOrder> Bitstream for RngBitstream<T, O> {
+    fn gen_bits(&mut self, num_bits: u32) -> u64 {
+        if num_bits <= self.unused_bits {
+            self.unused_bits -= num_bits;
+            O::take(&mut self.bit_buffer, num_bits)
+        } else {
+            // Anything over 64 lands here, since unused_bits <= 64.
+            assert_num_bits(num_bits);
+            let fresh = match self.peeked_word.take() {
+                Some(word) => word,
+                None => self.rng.gen(),
+            };
+            let result = O::take_refilled(&mut self.bit_buffer, self.unused_bits, fresh, num_bits);
+            self.unused_bits = 64 - (num_bits - self.unused_bits);
+            result
+        }
+    }
+
+    fn range_plan(&mut self, size: u64) -> RangePlan {
+        if let Some(plan) = self
+            .range_cache
+            .iter()
+            .flatten()
+            .find(|plan| plan.size() == size)
+        {
+            return *plan;
+        }
+        let plan = RangePlan::new(size);
+        self.range_cache[self.next_cache_slot] = Some(plan);
+        self.next_cache_slot = (self.next_cache_slot + 1) % RANGE_CACHE_SIZE;
+        plan
+    }
+}
+
+impl<T: Rng, O: BitOrder> PeekBitstream for RngBitstream<T, O> {
+    fn peek_bits(&mut self, num_bits: u32) -> u64 {
+        assert_num_bits(num_bits);
+        let mut buffer = self.bit_buffer;
+        if num_bits <= self.unused_bits {
+            O::take(&mut buffer, num_bits)
+        } else {
+            let fresh = match self.peeked_word {
+                Some(word) => word,
+                None => {
+                    let word = self.rng.gen();
+                    self.peeked_word = Some(word);
+                    word
+                }
+            };
+            O::take_refilled(&mut buffer, self.unused_bits, fresh, num_bits)
+        }
+    }
+}
+
+impl<T: Rng> Bitstream for CountingRngBitstream<T> {
+    fn gen_bits(&mut self, num_bits: u32) -> u64 {
+        self.count += num_bits as u64;
+        self.bitstream.gen_bits(num_bits)
+    }
+
+    fn range_plan(&mut self, size: u64) -> RangePlan {
+        self.bitstream.range_plan(size)
+    }
+}
+
+/// Peeking doesn't count as using bits.
+impl<T: Rng> PeekBitstream for CountingRngBitstream<T> {
+    fn peek_bits(&mut self, num_bits: u32) -> u64 {
+        self.bitstream.peek_bits(num_bits)
+    }
+}
+
+/// Adds `peek_bits` to any bitstream by holding peeked bits in a buffer.
+/// Only the bits actually peeked are taken from the inner bitstream early.
+pub struct PeekableBitstream<B> {
+    inner: B,
+    bit_buffer: u64,
+    unused_bits: u32,
+}
+
+/// Clears the lookahead buffer, but not the inner bitstream.
+#[cfg(feature = "zeroize")]
+impl<B> zeroize::Zeroize for PeekableBitstream<B> {
+    fn zeroize(&mut self) {
+        self.bit_buffer.zeroize();
+        self.unused_bits.zeroize();
+    }
+}
+
+impl<B> PeekableBitstream<B> {
+    pub fn new(inner: B) -> Self {
+        PeekableBitstream {
+            inner,
+            bit_buffer: 0,
+            unused_bits: 0,
+        }
+    }
+
+    /// Returns the inner bitstream. Any peeked-but-unused bits are lost.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+}
+
+impl<B: Bitstream> Bitstream for PeekableBitstream<B> {
+    fn gen_bits(&mut self, num_bits: u32) -> u64 {
+        if num_bits <= self.unused_bits {
+            let result = self.bit_buffer & low_bits_mask(num_bits);
+            self.bit_buffer = self.bit_buffer.checked_shr(num_bits).unwrap_or(0);
+            self.unused_bits -= num_bits;
+            result
+        } else {
+            let extra_bits = self.inner.gen_bits(num_bits - self.unused_bits);
+            let result = self.bit_buffer | (extra_bits << self.unused_bits);
+            self.bit_buffer = 0;
+            self.unused_bits = 0;
+            result
+        }
+    }
+
+    fn range_plan(&mut self, size: u64) -> RangePlan {
+        self.inner.range_plan(size)
+    }
+}
+
+impl<B: Bitstream> PeekBitstream for PeekableBitstream<B> {
+    fn peek_bits(&mut self, num_bits: u32) -> u64 {
+        if num_bits > self.unused_bits {
+            let extra_bits = self.inner.gen_bits(num_bits - self.unused_bits);
+            self.bit_buffer |= extra_bits << self.unused_bits;
+            self.unused_bits = num_bits;
+        }
+        self.bit_buffer & low_bits_mask(num_bits)
+    }
+}
+
+/// Caps the number of bits drawn from the inner bitstream, for untrusted
+/// callers or loops that might otherwise run away. A draw that would go over
+/// the budget is a `BudgetExceeded` error, and uses none of it; the
+/// `Bitstream` impl panics instead.
+pub struct BudgetedBitstream<B> {
+    inner: B,
+    remaining: u64,
+}
+
+impl<B> BudgetedBitstream<B> {
+    pub fn new(inner: B, budget: u64) -> Self {
+        BudgetedBitstream {
+            inner,
+            remaining: budget,
+        }
+    }
+
+    /// How many bits are left in the budget.
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+}
+
+impl<B: Bitstream> TryBitstream for BudgetedBitstream<B> {
+    fn try_gen_bits(&mut self, num_bits: u32) -> Result<u64, Error> {
+        check_num_bits(num_bits)?;
+        if num_bits as u64 > self.remaining {
+            return Err(Error::BudgetExceeded {
+                budget: self.remaining,
+                requested: num_bits as u64,
+            });
+        }
+        self.remaining -= num_bits as u64;
+        Ok(self.inner.gen_bits(num_bits))
+    }
+}
+
+impl<B: Bitstream> Bitstream for BudgetedBitstream<B> {
+    fn gen_bits(&mut self, num_bits: u32) -> u64 {
+        self.try_gen_bits(num_bits)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    fn range_plan(&mut self, size: u64) -> RangePlan {
+        self.inner.range_plan(size)
+    }
+}
+
+pub struct Bits<'a, B: ?Sized> {
+    pub(crate) bitstream: &'a mut B,
+}
+
+impl<'a, B: Bitstream + ?Sized> Iterator for Bits<'a, B> {
+    type Item = bool;
+
+    fn next(&mut self) -> Option<bool> {
+        Some(self.bitstream.gen_bit())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::low_bits_mask;
+    use crate::{
+        Bitstream, BitstreamExt, BudgetedBitstream, CountingRngBitstream, Error, PeekBitstream,
+        PeekableBitstream, RngBitstream, TryBitstream, TryBitstreamExt,
+    };
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn gen_bits_gens_reasonably_distributed_bits() {
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let mut buckets: Vec<_> = (0..=64).map(|_| Vec::new()).collect();
+        for _ in 0..1000000 {
+            let num_bits = rng.gen_range(0..=64);
+            let bits = bitstream.gen_bits(num_bits as u32);
+            let mask = if num_bits == 64 {
+                u64::MAX
+            } else {
+                (1 << num_bits) - 1
+            };
+            assert_eq!(
+                bits & mask,
+                bits,
+                "Bits spilled over ({}):\n{:b}\n{:b}",
+                num_bits,
+                mask,
+                bits
+            );
+            buckets[num_bits as usize].push(bits);
+        }
+        for (num_bits, bucket) in buckets.into_iter().enumerate() {
+            for bit_index in 0..num_bits {
+                let count = bucket
+                    .iter()
+                    .filter(|&&b| (b & (1 << bit_index)) != 0)
+                    .count();
+                let frequency = count as f64 / bucket.len() as f64;
+                assert!(
+                    frequency > 0.4 && frequency < 0.6,
+                    "extreme frequency {} at bit index {}/{}",
+                    frequency,
+                    bit_index,
+                    num_bits
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn gen_bits_consumes_rng_output_lsb_first() {
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let words: Vec<u64> = (0..100).map(|_| rng.gen()).collect();
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let mut widths = ChaChaRng::seed_from_u64(1);
+        let mut position = 0;
+        while position + 64 <= words.len() * 64 {
+            let num_bits = widths.gen_range(0..=64);
+            let mut expected = 0;
+            for i in 0..num_bits {
+                let bit = (words[(position + i) / 64] >> ((position + i) % 64)) & 1;
+                expected |= bit << i;
+            }
+            assert_eq!(bitstream.gen_bits(num_bits as u32), expected);
+            position += num_bits;
+        }
+    }
+
+    #[test]
+    fn msb_first_consumes_rng_output_msb_first() {
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let words: Vec<u64> = (0..100).map(|_| rng.gen()).collect();
+        let mut bitstream = RngBitstream::new_msb_first(ChaChaRng::seed_from_u64(0));
+        let mut widths = ChaChaRng::seed_from_u64(1);
+        let mut position = 0;
+        while position + 64 <= words.len() * 64 {
+            let num_bits = widths.gen_range(0..=64);
+            let mut expected = 0u64;
+            for i in position..position + num_bits {
+                let bit = (words[i / 64] >> (63 - i % 64)) & 1;
+                expected = expected.checked_shl(1).unwrap_or(0) | bit;
+            }
+            assert_eq!(bitstream.gen_bits(num_bits as u32), expected);
+            position += num_bits;
+        }
+    }
+
+    #[test]
+    fn drained_bits_can_be_pushed_into_another_bitstream() {
+        let mut reference = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let mut donor = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let mut recipient = RngBitstream::new(ChaChaRng::seed_from_u64(1));
+        assert_eq!(donor.gen_bits(20), reference.gen_bits(20));
+        assert_eq!(donor.unused_bits(), 44);
+        let (value, num_bits) = donor.drain_buffer();
+        assert_eq!((donor.unused_bits(), num_bits), (0, 44));
+        assert_eq!(value, reference.gen_bits(44));
+        recipient.gen_bits(50);
+        recipient.drain_buffer();
+        recipient.push_bits(value, num_bits);
+        assert_eq!(recipient.gen_bits(30), value & low_bits_mask(30));
+        assert_eq!(recipient.gen_bits(14), value >> 30);
+
+        let mut msb_first = RngBitstream::new_msb_first(ChaChaRng::seed_from_u64(0));
+        msb_first.gen_bits(10);
+        let (value, num_bits) = msb_first.drain_buffer();
+        msb_first.push_bits(0b101, 3);
+        msb_first.push_bits(value, num_bits);
+        assert_eq!(msb_first.gen_bits(num_bits), value);
+        assert_eq!(msb_first.gen_bits(3), 0b101);
+    }
+
+    fn check_peeking_doesnt_change_the_stream(
+        peeking: &mut impl PeekBitstream,
+        direct: &mut impl Bitstream,
+        msb_first: bool,
+    ) {
+        let mut widths = ChaChaRng::seed_from_u64(1);
+        for _ in 0..10000 {
+            let peek_width = widths.gen_range(0..=64);
+            let num_bits = widths.gen_range(0..=64);
+            let peeked = peeking.peek_bits(peek_width);
+            let value = peeking.gen_bits(num_bits);
+            assert_eq!(value, direct.gen_bits(num_bits));
+            let overlap = peek_width.min(num_bits);
+            if msb_first {
+                assert_eq!(
+                    peeked.checked_shr(peek_width - overlap).unwrap_or(0),
+                    value.checked_shr(num_bits - overlap).unwrap_or(0)
+                );
+            } else {
+                let mask = low_bits_mask(overlap);
+                assert_eq!(peeked & mask, value & mask);
+            }
+        }
+    }
+
+    #[test]
+    fn peek_bits_doesnt_change_the_stream() {
+        let direct = || RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        check_peeking_doesnt_change_the_stream(&mut direct(), &mut direct(), false);
+        check_peeking_doesnt_change_the_stream(
+            &mut PeekableBitstream::new(direct()),
+            &mut direct(),
+            false,
+        );
+        let msb_first = || RngBitstream::new_msb_first(ChaChaRng::seed_from_u64(0));
+        check_peeking_doesnt_change_the_stream(&mut msb_first(), &mut msb_first(), true);
+    }
+
+    #[test]
+    #[should_panic(expected = "num_bits must be at most 64")]
+    fn peek_bits_over_64_panics() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        bitstream.peek_bits(60);
+        bitstream.peek_bits(65);
+    }
+
+    #[test]
+    fn convenience_constructors_use_chacha20() {
+        let mut seeded = RngBitstream::seeded(7);
+        let mut explicit = RngBitstream::new(ChaChaRng::seed_from_u64(7));
+        assert_eq!(seeded.gen_bits(64), explicit.gen_bits(64));
+        let mut from_rng = RngBitstream::from_rng(&mut ChaChaRng::seed_from_u64(7));
+        let mut from_rng_again = RngBitstream::from_rng(&mut ChaChaRng::seed_from_u64(7));
+        assert_eq!(from_rng.gen_bits(64), from_rng_again.gen_bits(64));
+        RngBitstream::from_entropy().gen_bits(64);
+    }
+
+    #[test]
+    fn gen_range_uses_reasonable_bit_counts() {
+        for range_size in 1..=17 {
+            let mut bitstream = CountingRngBitstream {
+                bitstream: RngBitstream::new(ChaChaRng::seed_from_u64(0)),
+                count: 0,
+            };
+            for _ in 0..10000 {
+                bitstream.gen_range(range_size as u64);
+            }
+            dbg!((range_size, bitstream.count));
+            assert!(bitstream.count as f64 <= (range_size as f64).log2() * 2.0 * 10000.0,);
+        }
+    }
+
+    /// Counts the words `RngBitstream` draws from it.
+    struct WordCounter {
+        rng: ChaChaRng,
+        words: u32,
+    }
+
+    impl rand::RngCore for WordCounter {
+        fn next_u32(&mut self) -> u32 {
+            self.next_u64() as u32
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            self.words += 1;
+            self.rng.next_u64()
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            self.rng.fill_bytes(dest)
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+            self.rng.try_fill_bytes(dest)
+        }
+    }
+
+    #[test]
+    fn gen_bits_zero_draws_nothing() {
+        let mut bitstream = RngBitstream::new(WordCounter {
+            rng: ChaChaRng::seed_from_u64(0),
+            words: 0,
+        });
+        for _ in 0..10 {
+            assert_eq!(bitstream.gen_bits(0), 0);
+        }
+        assert_eq!(bitstream.rng.words, 0);
+        bitstream.gen_bits(64);
+        assert_eq!(bitstream.gen_bits(0), 0);
+        assert_eq!(bitstream.rng.words, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "num_bits must be at most 64, got 65")]
+    fn gen_bits_over_64_panics() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        bitstream.gen_bits(60);
+        bitstream.gen_bits(65);
+    }
+
+    #[test]
+    fn budget_stops_draws_that_would_overrun_it() {
+        let mut bitstream =
+            BudgetedBitstream::new(RngBitstream::new(ChaChaRng::seed_from_u64(0)), 100);
+        assert!(bitstream.try_gen_range(1 << 40).is_ok());
+        bitstream.try_gen_bits(50).unwrap();
+        assert_eq!(bitstream.remaining(), 10);
+        assert!(matches!(
+            bitstream.try_gen_bits(11),
+            Err(Error::BudgetExceeded {
+                budget: 10,
+                requested: 11
+            })
+        ));
+        assert_eq!(bitstream.remaining(), 10);
+        bitstream.try_gen_bits(10).unwrap();
+        assert_eq!(bitstream.remaining(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "range size must be nonzero")]
+    fn gen_range_zero_panics() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        bitstream.gen_range(0);
+    }
+}
@@ -0,0 +1,66 @@
+use crate::{Bitstream, Distribution};
+use futures_core::Stream;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// A `Stream` of samples from `distribution`. Bitstreams never block, so every
+/// poll is immediately ready; backpressure comes from the consumer polling.
+pub struct SampleStream<B, D, T> {
+    bitstream: B,
+    distribution: D,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<B, D, T> SampleStream<B, D, T> {
+    pub fn new(bitstream: B, distribution: D) -> Self {
+        SampleStream {
+            bitstream,
+            distribution,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn into_inner(self) -> B {
+        self.bitstream
+    }
+}
+
+impl<B: Bitstream + Unpin, D: Distribution<T> + Unpin, T> Stream for SampleStream<B, D, T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let this = self.get_mut();
+        Poll::Ready(Some(this.distribution.sample(&mut this.bitstream)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BitstreamExt, ConstRange, RngBitstream, SampleStream};
+    use futures_core::Stream;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+    use std::pin::Pin;
+    use std::task::{Context, Poll, Waker};
+
+    #[test]
+    fn sample_stream_yields_samples() {
+        let mut direct = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let mut stream = SampleStream::new(
+            RngBitstream::new(ChaChaRng::seed_from_u64(0)),
+            ConstRange::<6>,
+        );
+        let mut cx = Context::from_waker(Waker::noop());
+        for _ in 0..100 {
+            assert_eq!(
+                Pin::new(&mut stream).poll_next(&mut cx),
+                Poll::Ready(Some(direct.gen_range(6)))
+            );
+        }
+    }
+}
@@ -0,0 +1,320 @@
+//! Random strings for fuzzing and test data: characters from a class, or
+//! whole strings from a small regex-like pattern such as
+//! `[a-z]{3,8}-[0-9]{4}`. Everything is drawn from the bitstream, so a
+//! failing input can be replayed from its seed.
+
+use crate::{Bitstream, BitstreamExt, Distribution, Error};
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// A set of characters, sampled uniformly.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CharClass {
+    // Sorted, disjoint and nonadjacent inclusive ranges of scalar values.
+    ranges: Vec<(u32, u32)>,
+    size: u64,
+}
+
+impl CharClass {
+    /// The characters in the inclusive ranges `ranges`, which may overlap.
+    ///
+    /// # Panics
+    ///
+    /// If there are no characters, or a range is backwards.
+    pub fn new(ranges: &[(char, char)]) -> Self {
+        assert!(
+            ranges.iter().all(|(low, high)| low <= high),
+            "character ranges must not be backwards"
+        );
+        let mut sorted: Vec<(u32, u32)> = ranges
+            .iter()
+            .map(|&(low, high)| (low as u32, high as u32))
+            .collect();
+        sorted.sort_unstable();
+        let mut merged: Vec<(u32, u32)> = Vec::new();
+        for (low, high) in sorted {
+            match merged.last_mut() {
+                Some(last) if low <= last.1 + 1 => last.1 = last.1.max(high),
+                _ => merged.push((low, high)),
+            }
+        }
+        // No char lies in the surrogate range, so counting it would bias
+        // toward the character after it.
+        let ranges: Vec<(u32, u32)> = merged
+            .into_iter()
+            .flat_map(|(low, high)| {
+                let below = (low, high.min(0xd7ff));
+                let above = (low.max(0xe000), high);
+                [below, above]
+            })
+            .filter(|(low, high)| low <= high)
+            .collect();
+        let size = ranges
+            .iter()
+            .map(|(low, high)| (high - low + 1) as u64)
+            .sum();
+        assert!(size > 0, "a character class needs at least one character");
+        CharClass { ranges, size }
+    }
+
+    pub fn ascii_lowercase() -> Self {
+        CharClass::new(&[('a', 'z')])
+    }
+
+    pub fn ascii_uppercase() -> Self {
+        CharClass::new(&[('A', 'Z')])
+    }
+
+    pub fn ascii_digit() -> Self {
+        CharClass::new(&[('0', '9')])
+    }
+
+    pub fn ascii_alphanumeric() -> Self {
+        CharClass::new(&[('0', '9'), ('A', 'Z'), ('a', 'z')])
+    }
+
+    /// Letters, digits and underscore, as `\w` in a pattern.
+    pub fn word() -> Self {
+        CharClass::new(&[('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')])
+    }
+
+    /// Space through `~`, as `.` in a pattern.
+    pub fn ascii_printable() -> Self {
+        CharClass::new(&[(' ', '~')])
+    }
+
+    /// Every `char`: any Unicode scalar value, control characters and
+    /// unassigned code points included.
+    pub fn unicode() -> Self {
+        CharClass::new(&[('\0', char::MAX)])
+    }
+
+    /// The number of characters in the class.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+impl Distribution<char> for CharClass {
+    fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> char {
+        let mut index = bitstream.gen_range(self.size);
+        for &(low, high) in &self.ranges {
+            let len = (high - low + 1) as u64;
+            if index < len {
+                return char::from_u32(low + index as u32).unwrap();
+            }
+            index -= len;
+        }
+        unreachable!()
+    }
+}
+
+/// `len` characters from `class`.
+pub fn gen_string<B: Bitstream + ?Sized>(
+    bitstream: &mut B,
+    class: &CharClass,
+    len: usize,
+) -> String {
+    (0..len).map(|_| class.sample(bitstream)).collect()
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Piece {
+    class: CharClass,
+    min: u64,
+    max: u64,
+}
+
+/// Strings matching a small subset of regex syntax:
+///
+/// - literal characters, with `\` escaping any that would be special;
+/// - `.` for printable ASCII, and `\d`, `\w` and `\s` (space or tab);
+/// - classes like `[a-z_]`, without negation;
+/// - the quantifiers `?`, `{n}` and `{n,m}`, and `*` and `+`, which repeat
+///   at most 8 times.
+///
+/// Each quantified piece repeats a uniformly chosen number of times.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Pattern {
+    pieces: Vec<Piece>,
+}
+
+/// The most times `*` and `+` repeat.
+const UNBOUNDED_MAX: u64 = 8;
+
+impl Pattern {
+    pub fn new(pattern: &str) -> Result<Self, Error> {
+        let mut chars = pattern.chars().peekable();
+        let mut pieces = Vec::new();
+        while let Some(c) = chars.next() {
+            let class = match c {
+                '.' => CharClass::ascii_printable(),
+                '[' => parse_class(&mut chars)?,
+                '\\' => parse_escape(&mut chars)?,
+                '?' | '*' | '+' | '{' => {
+                    return Err(Error::InvalidParameter(
+                        "quantifier without anything to repeat",
+                    ))
+                }
+                ']' | '}' | '(' | ')' | '|' | '^' | '$' => {
+                    return Err(Error::InvalidParameter(
+                        "unsupported or unbalanced pattern syntax",
+                    ))
+                }
+                c => CharClass::new(&[(c, c)]),
+            };
+            let (min, max) = parse_quantifier(&mut chars)?;
+            pieces.push(Piece { class, min, max });
+        }
+        Ok(Pattern { pieces })
+    }
+}
+
+fn parse_escape(chars: &mut Peekable<Chars<'_>>) -> Result<CharClass, Error> {
+    Ok(match chars.next() {
+        Some('d') => CharClass::ascii_digit(),
+        Some('w') => CharClass::word(),
+        Some('s') => CharClass::new(&[('\t', '\t'), (' ', ' ')]),
+        Some(c) if !c.is_ascii_alphanumeric() => CharClass::new(&[(c, c)]),
+        Some(_) => return Err(Error::InvalidParameter("unsupported escape")),
+        None => return Err(Error::InvalidParameter("pattern ends with a backslash")),
+    })
+}
+
+fn parse_class(chars: &mut Peekable<Chars<'_>>) -> Result<CharClass, Error> {
+    let unterminated = || Error::InvalidParameter("unterminated character class");
+    let mut ranges = Vec::new();
+    if chars.peek() == Some(&'^') {
+        return Err(Error::InvalidParameter("negated classes aren't supported"));
+    }
+    loop {
+        let low = match chars.next().ok_or_else(unterminated)? {
+            ']' => break,
+            '\\' => chars.next().ok_or_else(unterminated)?,
+            c => c,
+        };
+        let mut high = low;
+        if chars.peek() == Some(&'-') {
+            chars.next();
+            high = match chars.next() {
+                // A trailing `-` is literal.
+                Some(']') => {
+                    ranges.push((low, low));
+                    ranges.push(('-', '-'));
+                    break;
+                }
+                Some('\\') => chars.next().ok_or_else(unterminated)?,
+                Some(c) => c,
+                None => return Err(unterminated()),
+            };
+            if high < low {
+                return Err(Error::InvalidParameter(
+                    "backwards range in character class",
+                ));
+            }
+        }
+        ranges.push((low, high));
+    }
+    if ranges.is_empty() {
+        return Err(Error::InvalidParameter("empty character class"));
+    }
+    Ok(CharClass::new(&ranges))
+}
+
+fn parse_quantifier(chars: &mut Peekable<Chars<'_>>) -> Result<(u64, u64), Error> {
+    let bounds = match chars.peek() {
+        Some('?') => (0, 1),
+        Some('*') => (0, UNBOUNDED_MAX),
+        Some('+') => (1, UNBOUNDED_MAX),
+        Some('{') => {
+            chars.next();
+            let mut body = String::new();
+            loop {
+                match chars.next() {
+                    Some('}') => break,
+                    Some(c) => body.push(c),
+                    None => return Err(Error::InvalidParameter("unterminated repetition")),
+                }
+            }
+            let parse = |s: &str| {
+                s.trim()
+                    .parse::<u64>()
+                    .map_err(|_| Error::InvalidParameter("repetition counts must be numbers"))
+            };
+            let (min, max) = match body.split_once(',') {
+                Some((min, max)) => (parse(min)?, parse(max)?),
+                None => (parse(&body)?, parse(&body)?),
+            };
+            if min > max {
+                return Err(Error::InvalidParameter(
+                    "repetition minimum exceeds maximum",
+                ));
+            }
+            return Ok((min, max));
+        }
+        _ => return Ok((1, 1)),
+    };
+    chars.next();
+    Ok(bounds)
+}
+
+impl Distribution<String> for Pattern {
+    fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> String {
+        let mut string = String::new();
+        for piece in &self.pieces {
+            let count = bitstream.gen_range_inclusive(piece.min, piece.max);
+            for _ in 0..count {
+                string.push(piece.class.sample(bitstream));
+            }
+        }
+        string
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RngBitstream;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn strings_match_their_classes_and_patterns() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let unicode = CharClass::unicode();
+        assert_eq!(unicode.size(), 0x110000 - 0x800);
+        let text = gen_string(&mut bitstream, &unicode, 1000);
+        assert_eq!(text.chars().count(), 1000);
+        let lower = gen_string(&mut bitstream, &CharClass::ascii_lowercase(), 100);
+        assert!(lower.chars().all(|c| c.is_ascii_lowercase()));
+        assert_eq!(
+            CharClass::new(&[('a', 'f'), ('c', 'z'), ('0', '9')]).size(),
+            36
+        );
+
+        let pattern = Pattern::new(r"[a-z]{3,8}-[0-9]{4}\.x?").unwrap();
+        let mut lengths = [false; 9];
+        for _ in 0..1000 {
+            let id = pattern.sample(&mut bitstream);
+            let (name, rest) = id.split_once('-').unwrap();
+            assert!(name.chars().all(|c| c.is_ascii_lowercase()));
+            lengths[name.len()] = true;
+            assert!(rest[..4].chars().all(|c| c.is_ascii_digit()));
+            assert!(rest[4..] == *"." || rest[4..] == *".x");
+        }
+        assert_eq!(
+            lengths,
+            [false, false, false, true, true, true, true, true, true]
+        );
+
+        let word = Pattern::new(r"\w+[_-]\d*").unwrap();
+        for _ in 0..1000 {
+            let sample = word.sample(&mut bitstream);
+            assert!(sample.len() >= 2 && sample.len() <= 17);
+        }
+
+        for bad in &["[a-z", "a{2", "a{3,1}", "*", "[^a]", "a|b", "\\"] {
+            assert!(Pattern::new(bad).is_err(), "{}", bad);
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use crate::{Bitstream, BitstreamExt, DefaultRng, RangePlan, RngBitstream};
+use std::cell::RefCell;
+
+thread_local! {
+    static THREAD_BITSTREAM: RefCell<RngBitstream<DefaultRng>> =
+        RefCell::new(RngBitstream::from_entropy());
+}
+
+/// A handle to this thread's bitstream, an `RngBitstream<DefaultRng>` seeded
+/// from OS entropy the first time it's used.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ThreadBitstream {
+    // Not Send: the handle only makes sense on the thread that created it.
+    _not_send: std::marker::PhantomData<*const ()>,
+}
+
+pub fn thread_bitstream() -> ThreadBitstream {
+    ThreadBitstream::default()
+}
+
+impl Bitstream for ThreadBitstream {
+    fn gen_bits(&mut self, num_bits: u32) -> u64 {
+        THREAD_BITSTREAM.with(|bitstream| bitstream.borrow_mut().gen_bits(num_bits))
+    }
+
+    fn range_plan(&mut self, size: u64) -> RangePlan {
+        THREAD_BITSTREAM.with(|bitstream| bitstream.borrow_mut().range_plan(size))
+    }
+}
+
+/// `gen_bits` on the thread-local bitstream.
+pub fn gen_bits(num_bits: u32) -> u64 {
+    THREAD_BITSTREAM.with(|bitstream| bitstream.borrow_mut().gen_bits(num_bits))
+}
+
+/// `gen_range` on the thread-local bitstream.
+pub fn gen_range(size: u64) -> u64 {
+    THREAD_BITSTREAM.with(|bitstream| bitstream.borrow_mut().gen_range(size))
+}
+
+/// `shuffle` on the thread-local bitstream.
+pub fn shuffle<T>(slice: &mut [T]) {
+    THREAD_BITSTREAM.with(|bitstream| bitstream.borrow_mut().shuffle(slice))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{gen_bits, gen_range, shuffle, thread_bitstream, BitstreamExt};
+
+    #[test]
+    fn free_functions_use_the_thread_bitstream() {
+        assert!(gen_bits(3) < 8);
+        assert!((0..100).all(|_| gen_range(6) < 6));
+        assert!(thread_bitstream().gen_range(6) < 6);
+        let mut values: Vec<u32> = (0..50).collect();
+        shuffle(&mut values);
+        values.sort_unstable();
+        assert_eq!(values, (0..50).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,82 @@
+//! Wrappers for the standard variance-reduction techniques in simulation.
+
+use crate::{low_bits_mask, Bitstream, DefaultRng, RangePlan, RngBitstream};
+
+/// Returns the bitwise complement of every draw from `B`. Running a
+/// simulation once on a bitstream and once on its `Antithetic` gives a pair
+/// of negatively correlated runs (each uniform `u` becomes roughly `1 - u`).
+pub struct Antithetic<B> {
+    bitstream: B,
+}
+
+impl<B> Antithetic<B> {
+    pub fn new(bitstream: B) -> Self {
+        Antithetic { bitstream }
+    }
+
+    pub fn into_inner(self) -> B {
+        self.bitstream
+    }
+}
+
+impl<B: Bitstream> Bitstream for Antithetic<B> {
+    fn gen_bits(&mut self, num_bits: u32) -> u64 {
+        !self.bitstream.gen_bits(num_bits) & low_bits_mask(num_bits)
+    }
+
+    fn range_plan(&mut self, size: u64) -> RangePlan {
+        self.bitstream.range_plan(size)
+    }
+}
+
+/// Hands out labelled streams for common random numbers: every scenario
+/// built from the same `CommonRandomNumbers` gets identical randomness for
+/// each label (e.g. `"arrivals"`, `"service"`), so differences between
+/// scenarios aren't drowned out by sampling noise.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct CommonRandomNumbers {
+    seed: u64,
+}
+
+impl CommonRandomNumbers {
+    pub fn new(seed: u64) -> Self {
+        CommonRandomNumbers { seed }
+    }
+
+    pub fn stream(&self, label: &str) -> RngBitstream<DefaultRng> {
+        RngBitstream::keyed(self.seed, label.as_bytes())
+    }
+
+    /// The complement of `stream(label)`.
+    pub fn antithetic_stream(&self, label: &str) -> Antithetic<RngBitstream<DefaultRng>> {
+        Antithetic::new(self.stream(label))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{low_bits_mask, Bitstream, CommonRandomNumbers};
+
+    #[test]
+    fn labelled_streams_are_synchronized_and_complementable() {
+        let scenario_a = CommonRandomNumbers::new(7);
+        let scenario_b = CommonRandomNumbers::new(7);
+        let mut arrivals = scenario_a.stream("arrivals");
+        let mut arrivals_again = scenario_b.stream("arrivals");
+        let mut service = scenario_a.stream("service");
+        let mut antithetic = scenario_b.antithetic_stream("arrivals");
+        let mut num_differences = 0;
+        for num_bits in (0..=64).cycle().take(1000) {
+            let value = arrivals.gen_bits(num_bits);
+            assert_eq!(arrivals_again.gen_bits(num_bits), value);
+            assert_eq!(
+                antithetic.gen_bits(num_bits),
+                value ^ low_bits_mask(num_bits)
+            );
+            if service.gen_bits(num_bits) != value {
+                num_differences += 1;
+            }
+        }
+        assert!(num_differences > 900);
+    }
+}
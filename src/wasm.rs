@@ -0,0 +1,79 @@
+use crate::error::{check_num_bits, check_size};
+use crate::{
+    Bitstream, BitstreamExt, DefaultRng, Draw, RecordingBitstream, ReplayBitstream, RngBitstream,
+    Tape, TryBitstream, TryBitstreamExt,
+};
+use wasm_bindgen::prelude::*;
+
+enum Source {
+    Recording(Box<RecordingBitstream<RngBitstream<DefaultRng>>>),
+    Replay(ReplayBitstream, Vec<u64>),
+}
+
+/// A ChaCha20-backed bitstream that records every draw, or replays a
+/// recording. Recordings are flat arrays of `[num_bits, value, ...]` pairs.
+#[wasm_bindgen]
+pub struct WasmBitstream {
+    source: Source,
+}
+
+#[wasm_bindgen]
+impl WasmBitstream {
+    #[wasm_bindgen(constructor)]
+    pub fn new(seed: u64) -> WasmBitstream {
+        WasmBitstream {
+            source: Source::Recording(Box::new(RecordingBitstream::new(RngBitstream::seeded(
+                seed,
+            )))),
+        }
+    }
+
+    /// Replays a recording made by `recording()`. Asking for different draws
+    /// than were recorded throws.
+    pub fn replay(recording: Vec<u64>) -> Result<WasmBitstream, JsError> {
+        if !recording.len().is_multiple_of(2) {
+            return Err(JsError::new("recording must have an even length"));
+        }
+        let mut tape = Tape::new();
+        for pair in recording.chunks_exact(2) {
+            if pair[0] > 64 {
+                return Err(JsError::new("recorded draws are at most 64 bits"));
+            }
+            tape.push(Draw {
+                num_bits: pair[0] as u32,
+                value: pair[1],
+            });
+        }
+        Ok(WasmBitstream {
+            source: Source::Replay(ReplayBitstream::new(tape), recording),
+        })
+    }
+
+    pub fn recording(&self) -> Vec<u64> {
+        match &self.source {
+            Source::Recording(recording) => recording
+                .tape()
+                .draws()
+                .iter()
+                .flat_map(|draw| vec![draw.num_bits as u64, draw.value])
+                .collect(),
+            Source::Replay(_, recording) => recording.clone(),
+        }
+    }
+
+    pub fn gen_bits(&mut self, num_bits: u32) -> Result<u64, JsError> {
+        check_num_bits(num_bits)?;
+        Ok(match &mut self.source {
+            Source::Recording(bitstream) => bitstream.gen_bits(num_bits),
+            Source::Replay(bitstream, _) => bitstream.try_gen_bits(num_bits)?,
+        })
+    }
+
+    pub fn gen_range(&mut self, size: u64) -> Result<u64, JsError> {
+        check_size(size)?;
+        Ok(match &mut self.source {
+            Source::Recording(bitstream) => bitstream.gen_range(size),
+            Source::Replay(bitstream, _) => bitstream.try_gen_range(size)?,
+        })
+    }
+}
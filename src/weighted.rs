@@ -0,0 +1,485 @@
+use crate::{Bitstream, Distribution, RangePlan};
+
+/// Picks an index with probability proportional to its weight, by drawing
+/// uniformly below the total weight and finding which weight's interval the
+/// draw falls in. With integer weights, the probabilities are exact.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct WeightedIndex {
+    // cumulative[i] is the sum of weights 0..=i.
+    cumulative: Vec<u64>,
+    plan: RangePlan,
+}
+
+impl WeightedIndex {
+    /// # Panics
+    ///
+    /// If the weights are all 0 (or there are none), or their sum overflows
+    /// a `u64`.
+    pub fn new(weights: &[u64]) -> Self {
+        let mut total: u64 = 0;
+        let cumulative: Vec<u64> = weights
+            .iter()
+            .map(|&weight| {
+                total = total.checked_add(weight).expect("total weight overflowed");
+                total
+            })
+            .collect();
+        assert!(total > 0, "total weight must be positive");
+        WeightedIndex {
+            cumulative,
+            plan: RangePlan::new(total),
+        }
+    }
+
+    /// Weights given as floats, rounded to multiples of 2^-60 of their sum,
+    /// so the probabilities are off by at most about 10^-18 each. Use
+    /// `ExactWeightedIndex` to sample with exactly the floats' proportions.
+    ///
+    /// # Panics
+    ///
+    /// If any weight is negative or not finite, or they are all 0.
+    pub fn from_f64(weights: &[f64]) -> Self {
+        assert!(
+            weights
+                .iter()
+                .all(|weight| weight.is_finite() && *weight >= 0.0),
+            "weights must be finite and nonnegative"
+        );
+        let sum: f64 = weights.iter().sum();
+        assert!(sum > 0.0, "total weight must be positive");
+        let scale = (1u64 << 60) as f64 / sum;
+        let integer_weights: Vec<u64> = weights
+            .iter()
+            .map(|&weight| (weight * scale).round() as u64)
+            .collect();
+        WeightedIndex::new(&integer_weights)
+    }
+
+    pub fn total_weight(&self) -> u64 {
+        self.plan.size()
+    }
+}
+
+impl Distribution<usize> for WeightedIndex {
+    fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> usize {
+        let point = self.plan.sample(bitstream);
+        self.cumulative.partition_point(|&end| end <= point)
+    }
+}
+
+/// Picks an index with probability exactly proportional to its `f64`
+/// weight. Every finite float is an integer times a power of 2, so scaling
+/// all the weights by the smallest such power makes them integers, which may
+/// be up to about 2100 bits long when the weights' magnitudes differ wildly.
+///
+/// When the scaled total fits in 64 bits, this samples just like
+/// `WeightedIndex`. Otherwise each attempt draws as many bits as the total
+/// has and rejects values past it, so it uses less than twice the bits.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ExactWeightedIndex {
+    limbs_per_value: usize,
+    // The cumulative sums, `limbs_per_value` limbs each, least significant
+    // first.
+    cumulative: Vec<u64>,
+    total_bits: u32,
+    plan: Option<RangePlan>,
+}
+
+/// `(mantissa, exponent)` with `value == mantissa * 2^exponent` and the
+/// mantissa odd (or 0).
+fn decompose(value: f64) -> (u64, i32) {
+    let bits = value.to_bits();
+    let exponent = ((bits >> 52) & 0x7ff) as i32;
+    let mantissa = bits & ((1 << 52) - 1);
+    let (mantissa, exponent) = if exponent == 0 {
+        (mantissa, -1074)
+    } else {
+        (mantissa | 1 << 52, exponent - 1075)
+    };
+    if mantissa == 0 {
+        return (0, exponent);
+    }
+    let zeros = mantissa.trailing_zeros();
+    (mantissa >> zeros, exponent + zeros as i32)
+}
+
+impl ExactWeightedIndex {
+    /// # Panics
+    ///
+    /// If any weight is negative or not finite, or they are all 0.
+    pub fn new(weights: &[f64]) -> Self {
+        assert!(
+            weights
+                .iter()
+                .all(|weight| weight.is_finite() && *weight >= 0.0),
+            "weights must be finite and nonnegative"
+        );
+        let parts: Vec<(u64, i32)> = weights.iter().map(|&weight| decompose(weight)).collect();
+        let min_exponent = parts
+            .iter()
+            .filter(|&&(mantissa, _)| mantissa != 0)
+            .map(|&(_, exponent)| exponent)
+            .min()
+            .expect("total weight must be positive");
+        let max_exponent = parts.iter().map(|&(_, exponent)| exponent).max().unwrap();
+        let sum_bits =
+            53 + (max_exponent - min_exponent) as u32 + usize::BITS - weights.len().leading_zeros();
+        let limbs_per_value = sum_bits.div_ceil(64) as usize;
+
+        let mut cumulative = Vec::with_capacity(weights.len() * limbs_per_value);
+        let mut total = vec![0u64; limbs_per_value];
+        for &(mantissa, exponent) in &parts {
+            if mantissa != 0 {
+                // Add mantissa << shift, which spans at most two limbs.
+                let shift = (exponent - min_exponent) as u32;
+                let (limb, offset) = ((shift / 64) as usize, shift % 64);
+                let wide = (mantissa as u128) << offset;
+                let mut carry = 0;
+                for (index, &part) in [wide as u64, (wide >> 64) as u64].iter().enumerate() {
+                    if limb + index < limbs_per_value {
+                        let (sum, overflow_a) = total[limb + index].overflowing_add(part);
+                        let (sum, overflow_b) = sum.overflowing_add(carry);
+                        total[limb + index] = sum;
+                        carry = (overflow_a || overflow_b) as u64;
+                    }
+                }
+                for total_limb in &mut total[(limb + 2).min(limbs_per_value)..] {
+                    let (sum, overflow) = total_limb.overflowing_add(carry);
+                    *total_limb = sum;
+                    carry = overflow as u64;
+                }
+            }
+            cumulative.extend_from_slice(&total);
+        }
+        let top = total.iter().rposition(|&limb| limb != 0).unwrap();
+        let total_bits = 64 * top as u32 + 64 - total[top].leading_zeros();
+        ExactWeightedIndex {
+            limbs_per_value,
+            cumulative,
+            total_bits,
+            plan: (top == 0).then(|| RangePlan::new(total[0])),
+        }
+    }
+
+    fn total(&self) -> &[u64] {
+        &self.cumulative[self.cumulative.len() - self.limbs_per_value..]
+    }
+}
+
+/// Picks an index whose probabilities are all dyadic, `numerators[i] /
+/// 2^precision_bits`, by walking the Knuth–Yao discrete distribution
+/// generating tree one bit at a time. No exact sampler uses fewer bits on
+/// average; when every probability is a power of 1/2, an outcome of
+/// probability 2^-k takes exactly k bits, so the mean is exactly the entropy.
+/// Otherwise the mean is less than the entropy plus 2.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DyadicIndex {
+    numerators: Vec<u64>,
+    precision_bits: u32,
+}
+
+impl DyadicIndex {
+    /// # Panics
+    ///
+    /// If `precision_bits > 64` or the numerators don't sum to
+    /// `2^precision_bits`.
+    pub fn new(numerators: &[u64], precision_bits: u32) -> Self {
+        assert!(precision_bits <= 64, "precision_bits must be at most 64");
+        let total: u128 = numerators.iter().map(|&numerator| numerator as u128).sum();
+        assert!(
+            total == 1 << precision_bits,
+            "numerators must sum to 2^precision_bits"
+        );
+        DyadicIndex {
+            numerators: numerators.to_vec(),
+            precision_bits,
+        }
+    }
+
+    /// The Shannon entropy of the distribution, in bits.
+    pub fn entropy(&self) -> f64 {
+        let scale = (self.precision_bits as f64).exp2();
+        self.numerators
+            .iter()
+            .filter(|&&numerator| numerator > 0)
+            .map(|&numerator| {
+                let probability = numerator as f64 / scale;
+                -probability * probability.log2()
+            })
+            .sum()
+    }
+}
+
+impl Distribution<usize> for DyadicIndex {
+    fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> usize {
+        // A numerator of 2^precision_bits has no 1 bit below the top place
+        // the tree walks, so a certain outcome is handled up front.
+        let certain = self
+            .numerators
+            .iter()
+            .position(|&numerator| numerator as u128 == 1 << self.precision_bits);
+        if let Some(index) = certain {
+            return index;
+        }
+        // `unvisited` counts the internal nodes to the left on the current
+        // level; each leaf on it, one per 1 bit in that place of a
+        // numerator, takes one of them.
+        let mut unvisited: i64 = 0;
+        for place in (0..self.precision_bits).rev() {
+            unvisited = 2 * unvisited + bitstream.gen_bit() as i64;
+            for (index, &numerator) in self.numerators.iter().enumerate().rev() {
+                unvisited -= ((numerator >> place) & 1) as i64;
+                if unvisited < 0 {
+                    return index;
+                }
+            }
+        }
+        unreachable!("the numerators sum to 2^precision_bits")
+    }
+}
+
+/// Picks an index with probability proportional to its weight by reading
+/// a uniform point in the total weight one bit at a time, like arithmetic
+/// decoding, and stopping as soon as the bits read so far pin down which
+/// interval it's in. That takes about the entropy plus 2 bits on average,
+/// which for very skewed weights is far fewer than the `log2(total weight)`
+/// bits `WeightedIndex` needs.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct IntervalHalvingIndex {
+    // cumulative[i] is the sum of weights 0..=i.
+    cumulative: Vec<u64>,
+}
+
+impl IntervalHalvingIndex {
+    /// # Panics
+    ///
+    /// If the weights are all 0 (or there are none), or their sum overflows
+    /// a `u64`.
+    pub fn new(weights: &[u64]) -> Self {
+        let mut total: u64 = 0;
+        let cumulative = weights
+            .iter()
+            .map(|&weight| {
+                total = total.checked_add(weight).expect("total weight overflowed");
+                total
+            })
+            .collect();
+        IntervalHalvingIndex::from_cumulative(cumulative)
+    }
+
+    /// From the running totals of the weights, so `cumulative[i]` is the sum
+    /// of weights `0..=i`.
+    ///
+    /// # Panics
+    ///
+    /// If `cumulative` is decreasing anywhere, or its last value is 0.
+    pub fn from_cumulative(cumulative: Vec<u64>) -> Self {
+        assert!(
+            cumulative.windows(2).all(|pair| pair[0] <= pair[1]),
+            "cumulative weights must be sorted"
+        );
+        assert!(
+            cumulative.last().is_some_and(|&total| total > 0),
+            "total weight must be positive"
+        );
+        IntervalHalvingIndex { cumulative }
+    }
+
+    pub fn total_weight(&self) -> u64 {
+        *self.cumulative.last().unwrap()
+    }
+}
+
+impl Distribution<usize> for IntervalHalvingIndex {
+    fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> usize {
+        let total = self.total_weight();
+        // After k bits forming `prefix`, the point is somewhere in
+        // [prefix * total, (prefix + 1) * total) / 2^k, and the candidates
+        // are the indices lo..=hi whose intervals overlap that.
+        let mut lo = self.cumulative.partition_point(|&end| end == 0);
+        let mut hi = self.cumulative.partition_point(|&end| end < total);
+        let mut prefix: u128 = 0;
+        for k in 1..=64 {
+            if lo == hi {
+                return lo;
+            }
+            prefix = 2 * prefix + bitstream.gen_bit() as u128;
+            let start = prefix * total as u128;
+            let end = start + total as u128;
+            let candidates = &self.cumulative[lo..hi];
+            hi = lo + candidates.partition_point(|&boundary| ((boundary as u128) << k) < end);
+            lo += candidates.partition_point(|&boundary| ((boundary as u128) << k) <= start);
+        }
+        if lo == hi {
+            return lo;
+        }
+        // Distinct boundaries are now at least 2^64 > total apart, so only
+        // one is left inside the interval, shared by lo..hi (all but lo
+        // empty). Follow the point's offset from it until it's clearly on
+        // one side.
+        let total = total as i128;
+        let boundary = (self.cumulative[lo] as u128) << 64;
+        let mut offset = boundary.wrapping_sub(prefix * total as u128) as i128;
+        loop {
+            offset = 2 * offset - total * bitstream.gen_bit() as i128;
+            if offset <= 0 {
+                return hi;
+            }
+            if offset >= total {
+                return lo;
+            }
+        }
+    }
+}
+
+/// Compares equal-length little-endian numbers.
+fn is_less(a: &[u64], b: &[u64]) -> bool {
+    a.iter().rev().lt(b.iter().rev())
+}
+
+impl Distribution<usize> for ExactWeightedIndex {
+    fn sample<B: Bitstream + ?Sized>(&self, bitstream: &mut B) -> usize {
+        let mut point = vec![0; self.limbs_per_value];
+        match &self.plan {
+            Some(plan) => point[0] = plan.sample(bitstream),
+            None => loop {
+                for (index, limb) in point.iter_mut().enumerate() {
+                    let bits = self.total_bits.saturating_sub(64 * index as u32).min(64);
+                    *limb = bitstream.gen_bits(bits);
+                }
+                if is_less(&point, self.total()) {
+                    break;
+                }
+            },
+        }
+        // The first cumulative sum past the point.
+        let end = |index: usize| {
+            &self.cumulative[index * self.limbs_per_value..(index + 1) * self.limbs_per_value]
+        };
+        let (mut low, mut high) = (0, self.cumulative.len() / self.limbs_per_value);
+        while low < high {
+            let middle = (low + high) / 2;
+            if is_less(&point, end(middle)) {
+                high = middle;
+            } else {
+                low = middle + 1;
+            }
+        }
+        low
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        measure, BitstreamExt, Draw, DyadicIndex, ExactWeightedIndex, IntervalHalvingIndex,
+        ReplayBitstream, RngBitstream, Tape, WeightedIndex,
+    };
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn weighted_index_follows_weights() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        for weighted in &[
+            WeightedIndex::new(&[1, 0, 3, 6]),
+            WeightedIndex::from_f64(&[0.05, 0.0, 0.15, 0.3]),
+        ] {
+            let mut counts = [0i32; 4];
+            for index in bitstream.samples(weighted).take(100000) {
+                counts[index] += 1;
+            }
+            assert_eq!(counts[1], 0);
+            for (&count, expected) in counts.iter().zip(&[10000, 0, 30000, 60000]) {
+                assert!((count - expected).abs() < 700, "{:?}", counts);
+            }
+        }
+    }
+
+    #[test]
+    fn exact_weights_keep_the_floats_proportions() {
+        // Exactly representable, so the cumulative integers are known:
+        // 0.25, 0, 0.5, 0.125 scale to 2, 0, 4, 1.
+        let small = ExactWeightedIndex::new(&[0.25, 0.0, 0.5, 0.125]);
+        assert_eq!(small.cumulative, vec![2, 2, 6, 7]);
+
+        // Weights 2^1000 apart need many limbs; the tiny one is never drawn
+        // and the others keep their 1:3 ratio.
+        let wide = ExactWeightedIndex::new(&[1e-300, 1.0, 3.0]);
+        assert!(wide.plan.is_none() && wide.limbs_per_value > 15);
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        for weighted in &[wide, ExactWeightedIndex::new(&[0.1, 0.2, 0.6])] {
+            let mut counts = [0i32; 3];
+            for index in bitstream.samples(weighted).take(40000) {
+                counts[index] += 1;
+            }
+            let expected = if counts[0] == 0 {
+                [0, 10000, 30000]
+            } else {
+                [4444, 8889, 26667]
+            };
+            for (&count, expected) in counts.iter().zip(&expected) {
+                assert!((count - expected).abs() < 500, "{:?}", counts);
+            }
+        }
+    }
+
+    #[test]
+    fn dyadic_index_uses_exactly_the_entropy() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        // 1/2, 1/8, 1/4, 0, 1/8: each outcome costs -log2 of its probability.
+        let dyadic = DyadicIndex::new(&[4, 1, 2, 0, 1], 3);
+        assert_eq!(dyadic.entropy(), 1.75);
+        let mut counts = [0i32; 5];
+        let measurement = measure(&mut bitstream, 80000, dyadic.entropy(), |b| {
+            let index = b.sample(&dyadic);
+            counts[index] += 1;
+        });
+        for (&count, expected) in counts.iter().zip(&[40000, 10000, 20000, 0, 10000]) {
+            assert!((count - expected).abs() < 600, "{:?}", counts);
+        }
+        let expected_bits = counts[0] + 3 * counts[1] + 2 * counts[2] + 3 * counts[4];
+        assert_eq!(measurement.total_bits, expected_bits as u64);
+        assert!((measurement.mean_bits() - 1.75).abs() < 0.02);
+
+        // Not powers of 1/2: 3/8 and 5/8 still come out right.
+        let uneven = DyadicIndex::new(&[3, 5], 3);
+        let zeros = bitstream
+            .samples(&uneven)
+            .take(80000)
+            .filter(|&index| index == 0)
+            .count();
+        assert!((zeros as i32 - 30000).abs() < 600);
+        assert_eq!(bitstream.sample(DyadicIndex::new(&[0, 1], 0)), 1);
+        assert_eq!(bitstream.sample(DyadicIndex::new(&[8, 0], 3)), 0);
+    }
+
+    #[test]
+    fn interval_halving_reads_only_the_bits_it_needs() {
+        let mut bitstream = RngBitstream::new(ChaChaRng::seed_from_u64(0));
+        let weighted = IntervalHalvingIndex::new(&[1, 0, 3, 6]);
+        let mut counts = [0i32; 4];
+        for index in bitstream.samples(&weighted).take(100000) {
+            counts[index] += 1;
+        }
+        for (&count, expected) in counts.iter().zip(&[10000, 0, 30000, 60000]) {
+            assert!((count - expected).abs() < 700, "{:?}", counts);
+        }
+
+        // About 2 bits where gen_range below the total would take over 40.
+        let skewed = IntervalHalvingIndex::from_cumulative(vec![1, 1 << 40]);
+        let measurement = measure(&mut bitstream, 10000, 0.0, |b| b.sample(&skewed));
+        assert!(measurement.mean_bits() < 3.0);
+
+        // A point that stays next to the boundary at 1/3 for over 64 bits
+        // (0.0101...), then falls below it.
+        let thirds = IntervalHalvingIndex::new(&[1, 2]);
+        let mut tape = Tape::new();
+        for value in (0..100).map(|i| i % 2).chain([0, 0]) {
+            tape.push(Draw { num_bits: 1, value });
+        }
+        let mut replay = ReplayBitstream::new(tape);
+        assert_eq!(replay.sample(&thirds), 0);
+        assert!(replay.is_finished());
+    }
+}